@@ -0,0 +1,242 @@
+//! Run configuration file (`forge-e2e.toml`).
+//!
+//! Holds the settings a team shares for every run: the forge binary, the
+//! tests directory, the comparison tolerance, the engine and its options,
+//! parallelism, tag filters, known divergences and reporting. Command-line
+//! flags override it.
+//!
+//! ```toml
+//! forge = "../forge/target/release/forge"
+//! tests = "tests"
+//! tolerance = 1e-9
+//! strict = true
+//! parallel = 4
+//! json = "e2e-results.json"
+//! skip_tags = ["random"]
+//!
+//! [engine]
+//! name = "gnumeric"
+//! env = { LC_ALL = "C" }
+//! min_version = "1.12.50"
+//! ```
+
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::engine::EngineConfig;
+use crate::runner::KeepArtifacts;
+
+/// Configuration file name, looked up in the current directory.
+pub const CONFIG_FILE: &str = "forge-e2e.toml";
+
+/// Settings read from `forge-e2e.toml`.
+///
+/// Relative paths are resolved against the file's directory, so the same
+/// file works from any working directory.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Forge binary.
+    pub forge: Option<PathBuf>,
+    /// Directory of test specs.
+    pub tests: Option<PathBuf>,
+    /// Largest absolute difference between actual and expected values that
+    /// still passes.
+    pub tolerance: Option<f64>,
+    /// Run in batch mode (single XLSX).
+    pub batch: bool,
+    /// Fail the run when a test marked `todo` passes.
+    pub strict: bool,
+    /// Report the N slowest tests and files.
+    pub slowest: Option<usize>,
+    /// Keep the generated files of `failed` or `all` tests.
+    pub keep_artifacts: Option<KeepArtifacts>,
+    /// Directory for kept artifacts.
+    pub artifacts_dir: Option<PathBuf>,
+    /// Known-divergence registry (default: `divergences.yaml` in the tests
+    /// directory).
+    pub divergences: Option<PathBuf>,
+    /// Number of spec files run at the same time.
+    pub parallel: Option<NonZeroUsize>,
+    /// JSON report of every result, written next to the TAP output.
+    pub json: Option<PathBuf>,
+    /// Run only spec files with one of these `_tags`.
+    pub tags: Vec<String>,
+    /// Skip spec files with one of these `_tags`.
+    pub skip_tags: Vec<String>,
+    /// Engine (`name`), ssconvert binary, options, environment and minimum
+    /// version.
+    pub engine: EngineConfig,
+}
+
+impl Config {
+    /// Loads a configuration file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, has an
+    /// unknown key, or a negative or non-finite tolerance.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+        let mut config: Self = toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display()))?;
+        if let Some(tolerance) = config.tolerance {
+            check_tolerance(tolerance)
+                .map_err(|e| anyhow::anyhow!("{}: tolerance: {e}", path.display()))?;
+        }
+        if let Some(base) = path.parent() {
+            config.resolve_paths(base);
+        }
+        Ok(config)
+    }
+
+    /// Loads `path`, or else `forge-e2e.toml` from the current directory if
+    /// it exists, or else the defaults.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be loaded.
+    pub fn discover(path: Option<&Path>) -> anyhow::Result<Self> {
+        match path {
+            Some(path) => Self::load(path),
+            None if Path::new(CONFIG_FILE).exists() => Self::load(Path::new(CONFIG_FILE)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Makes relative paths relative to `base`. A bare ssconvert name is
+    /// left for a `PATH` lookup.
+    fn resolve_paths(&mut self, base: &Path) {
+        for path in [
+            &mut self.forge,
+            &mut self.tests,
+            &mut self.artifacts_dir,
+            &mut self.divergences,
+            &mut self.json,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = base.join(&*path);
+        }
+        if let Some(ssconvert) = &mut self.engine.ssconvert {
+            if ssconvert.components().count() > 1 {
+                *ssconvert = base.join(&*ssconvert);
+            }
+        }
+    }
+}
+
+/// Checks that a comparison tolerance is a finite number of at least 0.
+///
+/// # Errors
+///
+/// Returns an error for a negative, infinite or NaN tolerance.
+pub fn check_tolerance(tolerance: f64) -> Result<f64, String> {
+    if tolerance.is_finite() && tolerance >= 0.0 {
+        Ok(tolerance)
+    } else {
+        Err(format!(
+            "must be a finite number of at least 0, got {tolerance}"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineKind;
+    use crate::types::GnumericVersion;
+
+    #[test]
+    fn load_reads_settings_and_resolves_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(
+            &path,
+            r#"
+forge = "bin/forge"
+tests = "tests"
+tolerance = 1e-9
+strict = true
+keep_artifacts = "all"
+divergences = "/etc/divergences.yaml"
+parallel = 4
+json = "out/results.json"
+tags = ["financial"]
+skip_tags = ["random"]
+
+[engine]
+name = "gnumeric"
+ssconvert = "ssconvert"
+args = ["--verbose"]
+env = { LC_ALL = "C" }
+min_version = "1.12.50"
+"#,
+        )
+        .unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.forge, Some(dir.path().join("bin/forge")));
+        assert_eq!(config.tests, Some(dir.path().join("tests")));
+        assert_eq!(config.tolerance, Some(1e-9));
+        assert!(config.strict && !config.batch);
+        assert_eq!(config.keep_artifacts, Some(KeepArtifacts::All));
+        assert_eq!(
+            config.divergences,
+            Some(PathBuf::from("/etc/divergences.yaml"))
+        );
+        assert_eq!(config.parallel, NonZeroUsize::new(4));
+        assert_eq!(config.json, Some(dir.path().join("out/results.json")));
+        assert_eq!(config.tags, ["financial"]);
+        assert_eq!(config.skip_tags, ["random"]);
+        assert_eq!(config.engine.name, EngineKind::Gnumeric);
+        assert_eq!(config.engine.ssconvert, Some(PathBuf::from("ssconvert")));
+        assert_eq!(config.engine.env["LC_ALL"], "C");
+        assert_eq!(
+            config.engine.min_version,
+            Some(GnumericVersion::new(1, 12, 50))
+        );
+    }
+
+    #[test]
+    fn load_rejects_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(&path, "threads = 4\n").unwrap();
+        assert!(Config::load(&path)
+            .unwrap_err()
+            .to_string()
+            .contains("threads"));
+
+        fs::write(&path, "parallel = 0\n").unwrap();
+        assert!(Config::load(&path).is_err());
+
+        fs::write(&path, "[engine]\nname = \"libreoffice\"\n").unwrap();
+        assert!(Config::load(&path).is_err());
+
+        fs::write(&path, "[engine]\nssconvert_path = \"x\"\n").unwrap();
+        assert!(Config::load(&path).is_err());
+    }
+
+    #[test]
+    fn load_rejects_negative_and_non_finite_tolerances() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(&path, "tolerance = 0\n").unwrap();
+        assert_eq!(Config::load(&path).unwrap().tolerance, Some(0.0));
+
+        for bad in ["-1e-9", "nan", "inf"] {
+            fs::write(&path, format!("tolerance = {bad}\n")).unwrap();
+            assert!(Config::load(&path)
+                .unwrap_err()
+                .to_string()
+                .contains("tolerance"));
+        }
+        assert!(check_tolerance(0.5).is_ok());
+    }
+}
@@ -0,0 +1,344 @@
+//! Function coverage against forge's function catalog.
+//!
+//! Parses every formula of the loaded tests into function calls and
+//! compares them with a checked-in manifest of the functions forge supports.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use crate::formula::{Formula, ParseError};
+use crate::runner::TestSuite;
+
+/// Test-name tokens that mark a test as exercising an edge case.
+const EDGE_MARKERS: &[&str] = &[
+    "edge", "error", "errors", "err", "zero", "negative", "neg", "empty", "blank", "boundary",
+    "invalid", "overflow", "limit", "extreme",
+];
+
+/// The functions forge supports, loaded from a manifest file.
+///
+/// The manifest lists one function name per line; blank lines and
+/// `#` comments are ignored.
+#[derive(Debug, Default)]
+pub struct FunctionCatalog {
+    names: BTreeSet<String>,
+}
+
+impl FunctionCatalog {
+    /// Loads a catalog from a manifest file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest cannot be read.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!("Failed to read function manifest {}: {e}", path.display())
+        })?;
+        Ok(Self::parse(&content))
+    }
+
+    /// Parses a catalog from manifest text.
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let names = content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(str::to_uppercase)
+            .collect();
+        Self { names }
+    }
+
+    /// Returns the number of functions in the catalog.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns `true` if the catalog has no functions.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns `true` if the catalog lists the function.
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+}
+
+/// A function call found in a formula.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCall {
+    /// Upper-cased function name.
+    pub name: String,
+    /// Whether the call is nested (at any depth) inside `ROUND`.
+    pub inside_round: bool,
+}
+
+/// Returns every function call in a formula, in order of appearance.
+///
+/// # Errors
+///
+/// Returns an error if the formula does not parse.
+pub fn function_calls(formula: &str) -> Result<Vec<FunctionCall>, ParseError> {
+    let calls = Formula::parse(formula)?
+        .calls()
+        .into_iter()
+        .map(|(name, enclosing)| FunctionCall {
+            inside_round: enclosing.iter().any(|open| open == "ROUND"),
+            name,
+        })
+        .collect();
+    Ok(calls)
+}
+
+/// Returns `true` if a test name or source file marks an edge-case test.
+#[must_use]
+pub fn is_edge_case(test_name: &str, source_file: Option<&Path>) -> bool {
+    let in_edge_dir = source_file.is_some_and(|path| {
+        path.components()
+            .any(|component| component.as_os_str() == "edge")
+    });
+
+    in_edge_dir
+        || test_name
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|token| EDGE_MARKERS.contains(&token.to_lowercase().as_str()))
+}
+
+/// How a single function is exercised by the suite.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FunctionUsage {
+    /// Number of tests calling the function.
+    pub tests: usize,
+    /// Number of edge-case tests calling the function.
+    pub edge_tests: usize,
+    /// Number of calls not nested inside `ROUND`.
+    pub outside_round: usize,
+}
+
+/// Function coverage of a test suite against a catalog.
+#[derive(Debug, Default)]
+pub struct CoverageReport {
+    /// Usage of every function called by at least one test.
+    pub usage: BTreeMap<String, FunctionUsage>,
+    /// Catalog functions without any test.
+    pub untested: Vec<String>,
+    /// Catalog functions only exercised by happy-path tests.
+    pub happy_path_only: Vec<String>,
+    /// Catalog functions only ever called inside `ROUND`.
+    pub round_only: Vec<String>,
+    /// Functions called by tests but missing from the catalog.
+    pub unknown: Vec<String>,
+    /// Tests whose formula does not parse, with the parse error.
+    pub unparsed: Vec<(String, String)>,
+    /// Number of catalog functions with at least one test.
+    pub tested: usize,
+    /// Number of catalog functions.
+    pub total: usize,
+}
+
+impl CoverageReport {
+    /// Builds the coverage report for a loaded suite.
+    #[must_use]
+    pub fn build(catalog: &FunctionCatalog, suite: &TestSuite) -> Self {
+        let mut usage: BTreeMap<String, FunctionUsage> = BTreeMap::new();
+
+        let imports: Vec<_> = suite
+            .imports
+            .iter()
+            .flat_map(|case| {
+                case.formulas()
+                    .into_iter()
+                    .map(|cell| (cell, case.source_file.as_deref()))
+            })
+            .collect();
+        let formulas = suite
+            .tests
+            .iter()
+            .map(|tc| {
+                (
+                    tc.name.clone(),
+                    tc.formula.as_str(),
+                    tc.source_file.as_deref(),
+                )
+            })
+            .chain(suite.chains.iter().flat_map(|chain| {
+                chain.scalars.iter().filter_map(|scalar| {
+                    scalar.formula.as_deref().map(|formula| {
+                        (
+                            format!("{}.{}", chain.name, scalar.name),
+                            formula,
+                            chain.source_file.as_deref(),
+                        )
+                    })
+                })
+            }))
+            .chain(suite.tables.iter().flat_map(|table| {
+                table.columns.iter().map(|column| {
+                    (
+                        format!("{}.{}", table.name, column.name),
+                        column.formula.as_str(),
+                        table.source_file.as_deref(),
+                    )
+                })
+            }))
+            .chain(suite.distributions.iter().map(|case| {
+                (
+                    case.name.clone(),
+                    case.formula.as_str(),
+                    case.source_file.as_deref(),
+                )
+            }))
+            .chain(imports.iter().map(|(cell, source_file)| {
+                (cell.name.clone(), cell.formula.as_str(), *source_file)
+            }));
+
+        let mut unparsed = Vec::new();
+        for (test_name, formula, source_file) in formulas {
+            let edge = is_edge_case(&test_name, source_file);
+            let calls = match function_calls(formula) {
+                Ok(calls) => calls,
+                Err(e) => {
+                    unparsed.push((test_name, e.to_string()));
+                    continue;
+                }
+            };
+            let mut counted = BTreeSet::new();
+            for call in calls {
+                let entry = usage.entry(call.name.clone()).or_default();
+                if !call.inside_round {
+                    entry.outside_round += 1;
+                }
+                if counted.insert(call.name) {
+                    entry.tests += 1;
+                    if edge {
+                        entry.edge_tests += 1;
+                    }
+                }
+            }
+        }
+
+        let mut report = Self {
+            unparsed,
+            total: catalog.len(),
+            ..Self::default()
+        };
+
+        for name in &catalog.names {
+            match usage.get(name) {
+                None => report.untested.push(name.clone()),
+                Some(function) => {
+                    report.tested += 1;
+                    if function.edge_tests == 0 {
+                        report.happy_path_only.push(name.clone());
+                    }
+                    if function.outside_round == 0 {
+                        report.round_only.push(name.clone());
+                    }
+                }
+            }
+        }
+
+        report.unknown = usage
+            .keys()
+            .filter(|name| !catalog.contains(name))
+            .cloned()
+            .collect();
+        report.usage = usage;
+
+        report
+    }
+
+    /// Returns the percentage of catalog functions with at least one test.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn percent_tested(&self) -> f64 {
+        if self.total == 0 {
+            return 100.0;
+        }
+        self.tested as f64 * 100.0 / self.total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates::DateSystem;
+    use crate::types::{TestCase, ValueKind};
+
+    fn test_case(name: &str, formula: &str) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            formula: formula.to_string(),
+            expected: 0.0,
+            kind: ValueKind::Number,
+            source_file: None,
+            forge_version: "1.0.0".to_string(),
+            scenario: None,
+            todo: None,
+            min_gnumeric: None,
+            date_system: DateSystem::Excel1900,
+            volatile: None,
+        }
+    }
+
+    #[test]
+    fn function_calls_tracks_round_nesting_and_skips_strings() {
+        let calls =
+            function_calls("=ROUND(PMT(0.05/12, 60, 25000), 2) + LEN(\"SUM(1)\") + rank.eq (1, x)")
+                .unwrap();
+        let names: Vec<(&str, bool)> = calls
+            .iter()
+            .map(|call| (call.name.as_str(), call.inside_round))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("ROUND", false),
+                ("PMT", true),
+                ("LEN", false),
+                ("RANK.EQ", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn edge_cases_are_detected_by_name_or_directory() {
+        assert!(is_edge_case("assumptions.test_pmt_zero_rate", None));
+        assert!(is_edge_case(
+            "assumptions.test_sum",
+            Some(Path::new("tests/edge/edge_numeric.yaml"))
+        ));
+        assert!(!is_edge_case("assumptions.test_small_basic", None));
+    }
+
+    #[test]
+    fn report_classifies_catalog_functions() {
+        let catalog = FunctionCatalog::parse("# comment\nSUM\nPMT\nABS\nsqrt  # lower case\n");
+        let suite = TestSuite {
+            tests: vec![
+                test_case("assumptions.test_sum", "=SUM(1, 2)"),
+                test_case("assumptions.test_sum_empty", "=SUM()"),
+                test_case("assumptions.test_pmt", "=ROUND(PMT(0.1, 2, 3), 2)"),
+                test_case("assumptions.test_sqrt", "=SQRT(4) + CUSTOM(1)"),
+                test_case("assumptions.test_broken", "=ABS(1"),
+            ],
+            ..TestSuite::default()
+        };
+
+        let report = CoverageReport::build(&catalog, &suite);
+        assert_eq!(report.total, 4);
+        assert_eq!(report.tested, 3);
+        assert_eq!(report.untested, ["ABS"]);
+        assert_eq!(report.happy_path_only, ["PMT", "SQRT"]);
+        assert_eq!(report.round_only, ["PMT"]);
+        assert_eq!(report.unknown, ["CUSTOM", "ROUND"]);
+        assert_eq!(report.unparsed.len(), 1);
+        assert_eq!(report.unparsed[0].0, "assumptions.test_broken");
+        assert!((report.percent_tested() - 75.0).abs() < f64::EPSILON);
+    }
+}
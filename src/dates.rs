@@ -0,0 +1,458 @@
+//! Spreadsheet date serials.
+//!
+//! Specs may write expected dates as `2024-01-01` (`expected_date`) or
+//! `2024-01-01T12:30:00` (`expected_datetime`). They are compared as serial
+//! numbers in the 1900 date system: day 1 is 1900-01-01, and day 60 is the
+//! fictitious 1900-02-29 that Excel keeps for Lotus 1-2-3 compatibility, so
+//! every later date is one more than its count of days since 1899-12-31.
+//!
+//! Workbooks flagged with the 1904 date system count from 1904-01-01
+//! (serial 0) instead, 1462 days later.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+/// Serial of the fictitious 1900-02-29.
+const LEAP_BUG_SERIAL: i64 = 60;
+/// Seconds in a day.
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Serial of 1904-01-01 in the 1900 date system.
+const EPOCH_1904: f64 = 1462.0;
+/// Serial of 1970-01-01 (the Unix epoch) in the 1900 date system.
+const UNIX_EPOCH_SERIAL: f64 = 25_569.0;
+
+/// A workbook's date system (epoch).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(try_from = "u32")]
+pub enum DateSystem {
+    /// Serial 1 is 1900-01-01 (Excel's default).
+    #[default]
+    Excel1900,
+    /// Serial 0 is 1904-01-01 (workbooks with `date1904` set).
+    Excel1904,
+}
+
+impl DateSystem {
+    /// Converts a 1900-system serial into this system.
+    #[must_use]
+    pub fn from_1900(self, serial: f64) -> f64 {
+        match self {
+            Self::Excel1900 => serial,
+            Self::Excel1904 => serial - EPOCH_1904,
+        }
+    }
+
+    /// Converts a serial in this system into the 1900 system.
+    #[must_use]
+    pub fn to_1900(self, serial: f64) -> f64 {
+        match self {
+            Self::Excel1900 => serial,
+            Self::Excel1904 => serial + EPOCH_1904,
+        }
+    }
+
+    /// Returns the year the system is named after.
+    #[must_use]
+    pub const fn year(self) -> u32 {
+        match self {
+            Self::Excel1900 => 1900,
+            Self::Excel1904 => 1904,
+        }
+    }
+}
+
+impl TryFrom<u32> for DateSystem {
+    type Error = String;
+
+    fn try_from(year: u32) -> Result<Self, Self::Error> {
+        match year {
+            1900 => Ok(Self::Excel1900),
+            1904 => Ok(Self::Excel1904),
+            _ => Err(format!("unknown date system {year}, expected 1900 or 1904")),
+        }
+    }
+}
+
+impl FromStr for DateSystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .parse::<u32>()
+            .map_err(|_| format!("unknown date system `{s}`, expected 1900 or 1904"))?
+            .try_into()
+    }
+}
+
+impl fmt::Display for DateSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.year())
+    }
+}
+
+impl serde::Serialize for DateSystem {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.year())
+    }
+}
+
+/// A date or time that is not valid ISO 8601 or is before 1900-01-01.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid {kind} `{text}`, expected {format} from 1900-01-01 on")]
+pub struct DateError {
+    kind: &'static str,
+    format: &'static str,
+    text: String,
+}
+
+/// A calendar date from 1900-01-01 on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct IsoDate {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+/// A date and time of day.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct IsoDateTime {
+    pub date: IsoDate,
+    /// Seconds since midnight.
+    pub seconds: f64,
+}
+
+/// Days from 1970-01-01 to a proleptic Gregorian date.
+const fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian date of a count of days from 1970-01-01.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+const fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+const fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+const fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl IsoDate {
+    /// Returns the date if it exists (or is 1900-02-29) and is not before
+    /// 1900-01-01.
+    #[must_use]
+    pub const fn new(year: i64, month: u32, day: u32) -> Option<Self> {
+        let valid_day = day >= 1
+            && month >= 1
+            && month <= 12
+            && (day <= days_in_month(year, month) || (year == 1900 && month == 2 && day == 29));
+        if valid_day && year >= 1900 {
+            Some(Self { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the date's serial number in the 1900 date system.
+    #[must_use]
+    pub const fn serial(self) -> i64 {
+        if self.year == 1900 && self.month == 2 && self.day == 29 {
+            return LEAP_BUG_SERIAL;
+        }
+        let days = days_from_civil(self.year, self.month, self.day) - days_from_civil(1899, 12, 31);
+        if days >= LEAP_BUG_SERIAL {
+            days + 1
+        } else {
+            days
+        }
+    }
+
+    /// Returns the date of a serial number in the 1900 date system, or
+    /// `None` before day 1.
+    #[must_use]
+    pub const fn from_serial(serial: i64) -> Option<Self> {
+        if serial < 1 {
+            return None;
+        }
+        if serial == LEAP_BUG_SERIAL {
+            return Some(Self {
+                year: 1900,
+                month: 2,
+                day: 29,
+            });
+        }
+        let days = if serial > LEAP_BUG_SERIAL {
+            serial - 1
+        } else {
+            serial
+        };
+        let (year, month, day) = civil_from_days(days_from_civil(1899, 12, 31) + days);
+        Some(Self { year, month, day })
+    }
+}
+
+impl IsoDateTime {
+    /// Returns the serial number, with the time as the fraction of a day.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn serial(self) -> f64 {
+        self.date.serial() as f64 + self.seconds / SECONDS_PER_DAY
+    }
+
+    /// Returns the date and time of a serial number, rounded to the second.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub fn from_serial(serial: f64) -> Option<Self> {
+        if !serial.is_finite() {
+            return None;
+        }
+        let total_seconds = (serial * SECONDS_PER_DAY).round();
+        let days = (total_seconds / SECONDS_PER_DAY).floor();
+        Some(Self {
+            date: IsoDate::from_serial(days as i64)?,
+            seconds: days.mul_add(-SECONDS_PER_DAY, total_seconds),
+        })
+    }
+}
+
+/// Renders a serial number as an ISO date, or as a date and time when
+/// `with_time` is set. Returns `None` for serials that are not dates.
+#[must_use]
+pub fn iso_from_serial(serial: f64, with_time: bool) -> Option<String> {
+    let datetime = IsoDateTime::from_serial(serial)?;
+    Some(if with_time {
+        datetime.to_string()
+    } else {
+        datetime.date.to_string()
+    })
+}
+
+/// Returns the 1900-system serial of a point in time, in UTC.
+#[must_use]
+pub fn serial_from_system_time(time: SystemTime) -> f64 {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_secs_f64(),
+        Err(before) => -before.duration().as_secs_f64(),
+    };
+    UNIX_EPOCH_SERIAL + seconds / SECONDS_PER_DAY
+}
+
+/// Parses `YYYY-MM-DD`.
+fn parse_date(text: &str) -> Option<IsoDate> {
+    let mut parts = text.splitn(3, '-');
+    let year = parts.next().filter(|p| p.len() == 4)?.parse().ok()?;
+    let month = parts.next().filter(|p| p.len() == 2)?.parse().ok()?;
+    let day = parts.next().filter(|p| p.len() == 2)?.parse().ok()?;
+    IsoDate::new(year, month, day)
+}
+
+/// Parses `HH:MM[:SS[.fff]]` into seconds since midnight.
+fn parse_time(text: &str) -> Option<f64> {
+    let mut parts = text.splitn(3, ':');
+    let hours: u32 = parts.next().filter(|p| p.len() == 2)?.parse().ok()?;
+    let minutes: u32 = parts.next().filter(|p| p.len() == 2)?.parse().ok()?;
+    let seconds: f64 = match parts.next() {
+        Some(p) if p.len() >= 2 && p.as_bytes()[..2].iter().all(u8::is_ascii_digit) => {
+            p.parse().ok()?
+        }
+        Some(_) => return None,
+        None => 0.0,
+    };
+    (hours < 24 && minutes < 60 && (0.0..60.0).contains(&seconds))
+        .then(|| f64::from(hours * 3600 + minutes * 60) + seconds)
+}
+
+impl FromStr for IsoDate {
+    type Err = DateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_date(s.trim()).ok_or_else(|| DateError {
+            kind: "date",
+            format: "YYYY-MM-DD",
+            text: s.to_string(),
+        })
+    }
+}
+
+impl FromStr for IsoDateTime {
+    type Err = DateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let parsed = match s.split_once(['T', ' ']) {
+            Some((date, time)) => parse_date(date)
+                .zip(parse_time(time.trim()))
+                .map(|(date, seconds)| Self { date, seconds }),
+            None => parse_date(s).map(|date| Self { date, seconds: 0.0 }),
+        };
+        parsed.ok_or_else(|| DateError {
+            kind: "date and time",
+            format: "YYYY-MM-DDTHH:MM[:SS]",
+            text: s.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for IsoDate {
+    type Error = DateError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<String> for IsoDateTime {
+    type Error = DateError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for IsoDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for IsoDateTime {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.seconds.round() as u32;
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}",
+            self.date,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> IsoDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn serials_follow_the_1900_system_with_its_leap_bug() {
+        assert_eq!(date("1900-01-01").serial(), 1);
+        assert_eq!(date("1900-02-28").serial(), 59);
+        assert_eq!(date("1900-02-29").serial(), 60);
+        assert_eq!(date("1900-03-01").serial(), 61);
+        assert_eq!(date("2024-01-01").serial(), 45292);
+        assert_eq!(date("2024-02-29").serial(), 45351);
+        assert_eq!(date("9999-12-31").serial(), 2_958_465);
+
+        for serial in [1, 59, 60, 61, 45292, 45351, 2_958_465] {
+            assert_eq!(IsoDate::from_serial(serial).unwrap().serial(), serial);
+        }
+        assert_eq!(IsoDate::from_serial(60).unwrap().to_string(), "1900-02-29");
+        assert_eq!(IsoDate::from_serial(0), None);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_and_early_dates() {
+        for text in [
+            "2023-02-29",
+            "1899-12-31",
+            "2024-13-01",
+            "2024-1-1",
+            "01/01/2024",
+            "1904-02-30",
+        ] {
+            assert!(text.parse::<IsoDate>().is_err(), "{text}");
+        }
+        assert!("2000-02-29".parse::<IsoDate>().is_ok());
+    }
+
+    #[test]
+    fn serial_from_system_time_counts_days_since_1900() {
+        use std::time::Duration;
+
+        assert!((serial_from_system_time(UNIX_EPOCH) - 25569.0).abs() < f64::EPSILON);
+        // 2024-01-01T18:00:30Z
+        let time = UNIX_EPOCH + Duration::from_secs(1_704_132_030);
+        let serial = 45292.75 + 30.0 / SECONDS_PER_DAY;
+        assert!((serial_from_system_time(time) - serial).abs() < 1e-9);
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn date_systems_shift_serials_by_the_epoch() {
+        let serial = date("2024-01-01").serial() as f64;
+        assert!((DateSystem::Excel1904.from_1900(serial) - 43830.0).abs() < f64::EPSILON);
+        assert!(
+            DateSystem::Excel1904
+                .from_1900(date("1904-01-01").serial() as f64)
+                .abs()
+                < f64::EPSILON
+        );
+        assert!((DateSystem::Excel1904.to_1900(43830.0) - serial).abs() < f64::EPSILON);
+        assert!((DateSystem::Excel1900.from_1900(serial) - serial).abs() < f64::EPSILON);
+
+        assert_eq!("1904".parse(), Ok(DateSystem::Excel1904));
+        assert!("1905".parse::<DateSystem>().is_err());
+        let systems: Vec<DateSystem> = serde_yaml_ng::from_str("[1900, 1904]").unwrap();
+        assert_eq!(systems, [DateSystem::Excel1900, DateSystem::Excel1904]);
+        assert_eq!(DateSystem::Excel1904.to_string(), "1904");
+    }
+
+    #[test]
+    fn datetimes_carry_the_time_as_a_fraction() {
+        let noon: IsoDateTime = "2024-01-01T12:00:00".parse().unwrap();
+        assert!((noon.serial() - 45292.5).abs() < 1e-9);
+        let evening: IsoDateTime = "2024-01-01 18:30".parse().unwrap();
+        assert!((evening.serial() - (45292.0 + 18.5 / 24.0)).abs() < 1e-9);
+        assert_eq!(evening.to_string(), "2024-01-01T18:30:00");
+        assert!("2024-01-01T24:00".parse::<IsoDateTime>().is_err());
+
+        assert_eq!(
+            iso_from_serial(45292.75, true).as_deref(),
+            Some("2024-01-01T18:00:00")
+        );
+        assert_eq!(
+            iso_from_serial(45292.0, false).as_deref(),
+            Some("2024-01-01")
+        );
+        assert_eq!(iso_from_serial(-3.0, false), None);
+    }
+}
@@ -0,0 +1,233 @@
+//! Known divergences between Gnumeric and Excel.
+//!
+//! For some functions Gnumeric's answer differs from Excel's and forge
+//! deliberately follows Excel. The registry lists those cases by function
+//! and argument pattern; a failing test that calls a listed function with
+//! matching arguments is reported as an expected divergence (a TAP `TODO`)
+//! instead of a failure.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::formula::{Expr, Formula};
+use crate::types::TestResult;
+
+/// Registry file name, looked up in the tests directory.
+pub const DIVERGENCES_FILE: &str = "divergences.yaml";
+
+/// Pattern matching any single argument.
+const ANY_ARG: &str = "*";
+/// Pattern matching all remaining arguments (last position only).
+const ANY_REST: &str = "...";
+
+/// A documented case where Gnumeric and Excel disagree.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Divergence {
+    /// Function name (case-insensitive).
+    pub function: String,
+    /// Argument patterns: a formula that must equal the argument, `*` for
+    /// any argument, or `...` for any remaining arguments. Omitted means
+    /// any call of the function.
+    #[serde(default)]
+    pub args: Option<Vec<serde_yaml_ng::Value>>,
+    /// Why Gnumeric differs and why forge follows Excel.
+    pub rationale: String,
+    /// Excel's value, where there is a single one.
+    #[serde(default)]
+    pub excel: Option<f64>,
+}
+
+/// The known divergences.
+#[derive(Debug, Default, Deserialize)]
+pub struct DivergenceRegistry {
+    /// All registered divergences.
+    #[serde(default)]
+    pub divergences: Vec<Divergence>,
+}
+
+/// Text of an argument pattern (numbers and booleans written as in a formula).
+fn pattern_text(pattern: &serde_yaml_ng::Value) -> String {
+    match pattern {
+        serde_yaml_ng::Value::String(s) => s.clone(),
+        serde_yaml_ng::Value::Bool(true) => "TRUE".to_string(),
+        serde_yaml_ng::Value::Bool(false) => "FALSE".to_string(),
+        other => serde_yaml_ng::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+/// Returns `true` if an argument matches a single-argument pattern.
+fn arg_matches(pattern: &str, arg: &Expr) -> bool {
+    if pattern == ANY_ARG {
+        return true;
+    }
+
+    let expected =
+        Formula::parse(pattern).map_or_else(|_| pattern.to_string(), |f| f.expr.to_string());
+    let actual = arg.to_string();
+    match (expected.parse::<f64>(), actual.parse::<f64>()) {
+        (Ok(a), Ok(b)) => (a - b).abs() < f64::EPSILON,
+        _ => expected.eq_ignore_ascii_case(&actual),
+    }
+}
+
+impl Divergence {
+    /// Returns `true` if the arguments of a call match the patterns.
+    fn args_match(&self, args: &[Expr]) -> bool {
+        let Some(patterns) = &self.args else {
+            return true;
+        };
+
+        for (i, pattern) in patterns.iter().enumerate() {
+            let pattern = pattern_text(pattern);
+            if pattern == ANY_REST {
+                return true;
+            }
+            match args.get(i) {
+                Some(arg) if arg_matches(&pattern, arg) => {}
+                _ => return false,
+            }
+        }
+        args.len() == patterns.len()
+    }
+
+    /// Returns `true` if the formula calls the function with matching
+    /// arguments anywhere.
+    #[must_use]
+    pub fn matches(&self, formula: &Formula) -> bool {
+        let mut found = false;
+        formula.expr.visit(&mut |expr| {
+            if let Expr::Call { name, args } = expr {
+                found |= name.eq_ignore_ascii_case(&self.function) && self.args_match(args);
+            }
+        });
+        found
+    }
+}
+
+impl DivergenceRegistry {
+    /// Loads the registry from a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+        serde_yaml_ng::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display()))
+    }
+
+    /// Returns the first divergence matching a formula.
+    #[must_use]
+    pub fn find(&self, formula: &str) -> Option<&Divergence> {
+        let formula = Formula::parse(formula).ok()?;
+        self.divergences
+            .iter()
+            .find(|divergence| divergence.matches(&formula))
+    }
+
+    /// Turns a failure covered by a known divergence into a `Todo`.
+    ///
+    /// Only failures with a computed value qualify: a missing value means
+    /// the run itself broke (export, recalculation or parsing), which no
+    /// divergence explains.
+    #[must_use]
+    pub fn apply(&self, result: TestResult) -> TestResult {
+        let divergence = match &result {
+            TestResult::Fail {
+                formula,
+                actual: Some(_),
+                ..
+            } => self.find(formula),
+            _ => None,
+        };
+        match divergence {
+            Some(divergence) => result.into_todo(
+                format!("expected divergence: {}", divergence.rationale),
+                divergence.excel,
+            ),
+            None => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Timings, ValueKind};
+
+    const REGISTRY: &str = r#"
+divergences:
+  - function: yearfrac
+    args: ["*", "*", 1]
+    rationale: actual/actual day count
+    excel: 0.5
+  - function: DATEDIF
+    args: ["*", "*", "\"MD\"", "..."]
+    rationale: MD unit
+"#;
+
+    fn fail(formula: &str) -> TestResult {
+        TestResult::Fail {
+            name: "assumptions.test".to_string(),
+            formula: formula.to_string(),
+            expected: 1.0,
+            actual: Some(2.0),
+            error: None,
+            timings: Timings::default(),
+            artifacts: None,
+            kind: ValueKind::Number,
+            assertion: None,
+        }
+    }
+
+    #[test]
+    fn matches_function_and_argument_patterns() {
+        let registry: DivergenceRegistry = serde_yaml_ng::from_str(REGISTRY).unwrap();
+
+        assert!(registry
+            .find("=ROUND(YEARFRAC(DATE(2024,1,1), DATE(2024,7,1), 1), 4)")
+            .is_some());
+        assert!(registry.find("=YEARFRAC(A1, B1, 1.0)").is_some());
+        assert!(registry.find("=YEARFRAC(A1, B1, 0)").is_none());
+        assert!(registry.find("=YEARFRAC(A1, B1)").is_none());
+        assert!(registry.find("=DATEDIF(A1, B1, \"md\")").is_some());
+        assert!(registry.find("=DATEDIF(A1, B1, \"D\")").is_none());
+        assert!(registry.find("=LEN(\"YEARFRAC(A1, B1, 1)\")").is_none());
+    }
+
+    #[test]
+    fn apply_turns_matching_failures_into_todos() {
+        let registry: DivergenceRegistry = serde_yaml_ng::from_str(REGISTRY).unwrap();
+
+        let todo = registry.apply(fail("=YEARFRAC(A1, B1, 1)"));
+        assert!(
+            matches!(todo, TestResult::Todo { ref reason, excel: Some(_), .. } if reason.contains("actual/actual"))
+        );
+        assert!(!todo.is_fail());
+
+        assert!(registry.apply(fail("=SUM(1)")).is_fail());
+
+        // Runs that produced no value stay failures
+        let mut broken = fail("=YEARFRAC(A1, B1, 1)");
+        if let TestResult::Fail { actual, error, .. } = &mut broken {
+            *actual = None;
+            *error = Some("forge export failed: boom".to_string());
+        }
+        assert!(registry.apply(broken).is_fail());
+
+        let pass = TestResult::Pass {
+            name: "assumptions.test".to_string(),
+            formula: "=YEARFRAC(A1, B1, 1)".to_string(),
+            expected: 1.0,
+            actual: 1.0,
+            timings: Timings::default(),
+        };
+        assert!(registry.apply(pass).is_pass());
+    }
+}
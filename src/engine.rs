@@ -2,44 +2,208 @@
 //!
 //! Uses ssconvert to recalculate Excel formulas and export to CSV.
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::excel;
+use crate::formula::Formula;
+use crate::types::GnumericVersion;
+
+/// Spreadsheet engine that recalculates forge's workbooks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineKind {
+    /// Gnumeric, through ssconvert.
+    #[default]
+    Gnumeric,
+}
+
+impl FromStr for EngineKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gnumeric" => Ok(Self::Gnumeric),
+            _ => Err(format!("expected `gnumeric`, got `{s}`")),
+        }
+    }
+}
+
+/// Which engine to run and how to find and run ssconvert.
+///
+/// The defaults run Gnumeric's `ssconvert` from `PATH` with no extra
+/// options.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// Engine recalculating the workbooks (only `gnumeric` so far).
+    pub name: EngineKind,
+    /// ssconvert binary (default: `ssconvert` on `PATH`).
+    pub ssconvert: Option<PathBuf>,
+    /// Extra arguments passed to ssconvert before the file names.
+    pub args: Vec<String>,
+    /// Importer options (`--import-options`), e.g. `locale`.
+    pub import_options: BTreeMap<String, String>,
+    /// CSV exporter options (`--export-options`), on top of `format=raw`,
+    /// e.g. `quoting-mode` or `locale`. The separator must stay `,`.
+    pub export_options: BTreeMap<String, String>,
+    /// Environment variables set for ssconvert, on top of `LC_ALL=C` and `TZ=UTC`.
+    pub env: BTreeMap<String, String>,
+    /// Minimum ssconvert version (e.g. `1.12.50`).
+    pub min_version: Option<GnumericVersion>,
+}
 
 /// Gnumeric spreadsheet engine for formula recalculation.
+#[derive(Debug)]
 pub struct GnumericEngine {
     /// Path to the ssconvert binary.
     path: PathBuf,
-    /// Version string from ssconvert.
-    version: String,
+    /// Version output of `ssconvert --version`.
+    version_text: String,
+    /// Version number read from `version_text`, if any.
+    version: Option<GnumericVersion>,
+    /// Arguments passed before the file names (options included).
+    args: Vec<String>,
+    /// Environment variables set for ssconvert.
+    env: BTreeMap<String, String>,
 }
 
 impl GnumericEngine {
     /// Engine name constant.
     pub const NAME: &'static str = "Gnumeric (ssconvert)";
 
+    /// Environment set for ssconvert unless configured otherwise. The C
+    /// locale writes numbers as `1234.5` whatever the machine's locale, and
+    /// UTC makes `NOW()` comparable with the runner's wall clock.
+    pub const DEFAULT_ENV: [(&'static str, &'static str); 2] = [("LC_ALL", "C"), ("TZ", "UTC")];
+
+    /// CSV exporter options set unless configured otherwise. Raw values
+    /// ignore cell formats (percent, currency, scientific, dates).
+    pub const DEFAULT_EXPORT_OPTIONS: [(&'static str, &'static str); 1] = [("format", "raw")];
+
+    /// ssconvert flags that recalculate a workbook and export every sheet.
+    pub const RECALC_FLAGS: [&'static str; 2] = ["--recalc", "-S"];
+
     /// Detects Gnumeric (ssconvert) installation.
     ///
     /// Returns `Some(engine)` if ssconvert is found and working,
     /// `None` otherwise.
     #[must_use]
     pub fn detect() -> Option<Self> {
-        let output = Command::new("ssconvert").arg("--version").output().ok()?;
+        Self::with_config(&EngineConfig::default()).ok()
+    }
 
-        if output.status.success() {
-            let version = String::from_utf8_lossy(&output.stderr).trim().to_string();
-            Some(Self {
-                path: PathBuf::from("ssconvert"),
-                version,
-            })
-        } else {
-            None
+    /// Finds and checks ssconvert as configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if ssconvert cannot be run, the options are
+    /// invalid, or its version is older than `min_version`.
+    pub fn with_config(config: &EngineConfig) -> Result<Self, String> {
+        if let Some(separator) = config.export_options.get("separator") {
+            if separator != "," {
+                return Err(format!(
+                    "Unsupported export separator `{separator}`: results are parsed as comma-separated"
+                ));
+            }
+        }
+
+        let env = with_defaults(&Self::DEFAULT_ENV, &config.env);
+        let export_options = with_defaults(&Self::DEFAULT_EXPORT_OPTIONS, &config.export_options);
+
+        let path = config
+            .ssconvert
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("ssconvert"));
+        let output = Command::new(&path)
+            .envs(&env)
+            .arg("--version")
+            .output()
+            .map_err(|e| format!("Failed to run {}: {e}", path.display()))?;
+        if !output.status.success() {
+            return Err(format!("{} --version failed", path.display()));
+        }
+
+        // ssconvert prints its version on stderr (stdout in some builds)
+        let mut version_text = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if version_text.is_empty() {
+            version_text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        }
+        let version = GnumericVersion::find_in(&version_text);
+
+        if let Some(required) = config.min_version {
+            let found =
+                version.ok_or_else(|| format!("Cannot read the version of {}", path.display()))?;
+            if found < required {
+                return Err(format!(
+                    "{} is version {found}, at least {required} is required",
+                    path.display()
+                ));
+            }
+        }
+
+        let mut args = config.args.clone();
+        if !config.import_options.is_empty() {
+            args.push(format!(
+                "--import-options={}",
+                options_string(&config.import_options)
+            ));
         }
+        args.push(format!(
+            "--export-options={}",
+            options_string(&export_options)
+        ));
+
+        Ok(Self {
+            path,
+            version_text,
+            version,
+            args,
+            env,
+        })
+    }
+
+    /// Returns the full ssconvert argument list that recalculates
+    /// `xlsx_path` and writes every sheet to `csv_pattern`.
+    #[must_use]
+    pub fn recalc_args(&self, xlsx_path: &Path, csv_pattern: &Path) -> Vec<String> {
+        Self::RECALC_FLAGS
+            .iter()
+            .map(ToString::to_string)
+            .chain(self.args.iter().cloned())
+            .chain([
+                xlsx_path.display().to_string(),
+                csv_pattern.display().to_string(),
+            ])
+            .collect()
+    }
+
+    /// Returns the environment variables set for ssconvert.
+    #[must_use]
+    pub const fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    /// Returns the path of the ssconvert binary.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
     }
 
-    /// Returns the engine version string.
+    /// Returns the output of `ssconvert --version`.
     #[must_use]
-    pub fn version(&self) -> &str {
-        &self.version
+    pub fn version_text(&self) -> &str {
+        &self.version_text
+    }
+
+    /// Returns the ssconvert version, if its output contained one.
+    #[must_use]
+    pub const fn version(&self) -> Option<GnumericVersion> {
+        self.version
     }
 
     /// Returns the engine name.
@@ -48,30 +212,35 @@ impl GnumericEngine {
         Self::NAME
     }
 
-    /// Converts XLSX to CSV with formula recalculation.
+    /// Converts XLSX to CSV files (all sheets) and returns the CSV of
+    /// every sheet with its name.
     ///
-    /// Uses ssconvert with the `--recalc` and `-S` flags to export all sheets
-    /// as separate CSV files. Returns paths to all generated CSV files.
+    /// Uses ssconvert with the `--recalc` and `-S` flags, which write sheet
+    /// `n` (counting from 0) to `{stem}_{n}.csv`; the sheet names are read
+    /// from the workbook.
     ///
     /// # Errors
     ///
-    /// Returns an error if the xlsx path has no file stem, ssconvert fails to
-    /// run, or ssconvert exits with a non-zero status.
-    pub fn xlsx_to_csv(&self, xlsx_path: &Path, output_dir: &Path) -> Result<PathBuf, String> {
+    /// Returns an error if the xlsx path has no file stem, the workbook's
+    /// sheets cannot be read, ssconvert fails, or a sheet has no CSV file.
+    pub fn xlsx_to_csv_all_sheets(
+        &self,
+        xlsx_path: &Path,
+        output_dir: &Path,
+    ) -> Result<Vec<SheetCsv>, String> {
         let base_name = xlsx_path
             .file_stem()
             .ok_or("Invalid xlsx path: no file stem")?
             .to_string_lossy()
             .to_string();
+        let sheet_names = excel::sheet_names(xlsx_path)?;
 
         // Export all sheets with -S flag, using %n for sheet number
         let csv_pattern = output_dir.join(format!("{base_name}_%n.csv"));
 
         let output = Command::new(&self.path)
-            .arg("--recalc")
-            .arg("-S")
-            .arg(xlsx_path)
-            .arg(&csv_pattern)
+            .envs(&self.env)
+            .args(self.recalc_args(xlsx_path, &csv_pattern))
             .output()
             .map_err(|e| format!("Failed to run ssconvert: {e}"))?;
 
@@ -82,63 +251,120 @@ impl GnumericEngine {
             ));
         }
 
-        // Return pattern path - caller will handle finding the right sheet
-        Ok(output_dir.join(format!("{base_name}_")))
+        sheet_csvs(&sheet_names, output_dir, &base_name)
     }
+}
+
+/// Returns `configured` on top of the `defaults` it does not override.
+fn with_defaults(
+    defaults: &[(&str, &str)],
+    configured: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    defaults
+        .iter()
+        .map(|&(key, value)| (key.to_string(), value.to_string()))
+        .chain(configured.clone())
+        .collect()
+}
+
+/// Formats ssconvert options as `key=value` pairs separated by spaces.
+fn options_string(options: &BTreeMap<String, String>) -> String {
+    options
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-    /// Converts XLSX to CSV files (all sheets) and returns all CSV paths.
+/// Requirements file name, looked up in the tests directory.
+pub const FUNCTIONS_FILE: &str = "gnumeric-functions.yaml";
+
+/// The Gnumeric release each function first appeared in.
+///
+/// Tests calling a listed function are skipped on older releases.
+#[derive(Debug, Default, Deserialize)]
+pub struct FunctionRequirements {
+    /// Minimum version by function name (case-insensitive).
+    #[serde(default)]
+    pub functions: BTreeMap<String, GnumericVersion>,
+}
+
+impl FunctionRequirements {
+    /// Loads the requirements from a file.
     ///
     /// # Errors
     ///
-    /// Returns an error if the xlsx path has no file stem, ssconvert fails,
-    /// or no CSV files are generated.
-    pub fn xlsx_to_csv_all_sheets(
-        &self,
-        xlsx_path: &Path,
-        output_dir: &Path,
-    ) -> Result<Vec<PathBuf>, String> {
-        let base_name = xlsx_path
-            .file_stem()
-            .ok_or("Invalid xlsx path: no file stem")?
-            .to_string_lossy()
-            .to_string();
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        serde_yaml_ng::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {e}", path.display()))
+    }
 
-        // Export all sheets with -S flag
-        let csv_pattern = output_dir.join(format!("{base_name}_%n.csv"));
+    /// Returns the newest release required by a function the formula calls.
+    #[must_use]
+    pub fn required_for(&self, formula: &str) -> Option<GnumericVersion> {
+        let formula = Formula::parse(formula).ok()?;
+        formula
+            .calls()
+            .iter()
+            .filter_map(|(name, _)| {
+                self.functions
+                    .iter()
+                    .find(|(function, _)| function.eq_ignore_ascii_case(name))
+                    .map(|(_, &version)| version)
+            })
+            .max()
+    }
+}
 
-        let output = Command::new(&self.path)
-            .arg("--recalc")
-            .arg("-S")
-            .arg(xlsx_path)
-            .arg(&csv_pattern)
-            .output()
-            .map_err(|e| format!("Failed to run ssconvert: {e}"))?;
+/// The CSV export of one sheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SheetCsv {
+    /// Sheet name in the workbook.
+    pub sheet: String,
+    /// CSV file written by ssconvert.
+    pub path: PathBuf,
+}
 
-        if !output.status.success() {
-            return Err(format!(
-                "ssconvert failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
+/// Maps each sheet to the CSV file ssconvert wrote for it.
+fn sheet_csvs(
+    sheet_names: &[String],
+    output_dir: &Path,
+    base_name: &str,
+) -> Result<Vec<SheetCsv>, String> {
+    if sheet_names.is_empty() {
+        return Err("Workbook has no sheets".to_string());
+    }
 
-        // Find all generated CSV files
-        let mut csv_files = Vec::new();
-        for i in 0..10 {
-            // Check up to 10 sheets
-            let csv_path = output_dir.join(format!("{base_name}_{i}.csv"));
-            if csv_path.exists() {
-                csv_files.push(csv_path);
+    sheet_names
+        .iter()
+        .enumerate()
+        .map(|(i, sheet)| {
+            let path = output_dir.join(format!("{base_name}_{i}.csv"));
+            if path.exists() {
+                Ok(SheetCsv {
+                    sheet: sheet.clone(),
+                    path,
+                })
             } else {
-                break;
+                Err(format!("No CSV generated for sheet `{sheet}`"))
             }
-        }
+        })
+        .collect()
+}
 
-        if csv_files.is_empty() {
-            Err("No CSV files generated".to_string())
-        } else {
-            Ok(csv_files)
-        }
-    }
+/// Returns the sheet named `name` (case-insensitive).
+///
+/// # Errors
+///
+/// Returns an error if the workbook has no such sheet.
+pub fn find_sheet<'a>(sheets: &'a [SheetCsv], name: &str) -> Result<&'a SheetCsv, String> {
+    sheets
+        .iter()
+        .find(|sheet| sheet.sheet.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("No sheet `{name}` in the recalculated workbook"))
 }
 
 #[cfg(test)]
@@ -150,6 +376,175 @@ mod tests {
         assert_eq!(GnumericEngine::name(), "Gnumeric (ssconvert)");
     }
 
+    #[test]
+    fn sheet_csvs_maps_every_sheet_to_its_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let names: Vec<String> = (0..12).map(|i| format!("sheet_{i}")).collect();
+        for i in 0..12 {
+            std::fs::write(temp_dir.path().join(format!("test_{i}.csv")), "").unwrap();
+        }
+
+        let sheets = sheet_csvs(&names, temp_dir.path(), "test").unwrap();
+        assert_eq!(sheets.len(), 12);
+        assert_eq!(sheets[11].sheet, "sheet_11");
+        assert_eq!(sheets[11].path, temp_dir.path().join("test_11.csv"));
+
+        std::fs::remove_file(temp_dir.path().join("test_5.csv")).unwrap();
+        let error = sheet_csvs(&names, temp_dir.path(), "test").unwrap_err();
+        assert!(error.contains("sheet_5"));
+
+        assert_eq!(find_sheet(&sheets, "SHEET_7").unwrap().sheet, "sheet_7");
+        assert!(find_sheet(&sheets, "sheet_12").is_err());
+    }
+
+    #[test]
+    fn find_in_reads_ssconvert_version_output() {
+        assert_eq!(
+            GnumericVersion::find_in("ssconvert version '1.12.55'\ndatadir := /usr/share"),
+            Some(GnumericVersion::new(1, 12, 55))
+        );
+        assert_eq!(
+            GnumericVersion::find_in("1.12"),
+            Some(GnumericVersion::new(1, 12, 0))
+        );
+        assert_eq!(GnumericVersion::find_in("no version 7"), None);
+        assert!(GnumericVersion::new(1, 12, 9) < GnumericVersion::new(1, 12, 50));
+    }
+
+    #[test]
+    fn required_for_takes_the_newest_called_function() {
+        let requirements: FunctionRequirements =
+            serde_yaml_ng::from_str("functions:\n  XLOOKUP: \"1.12.50\"\n  concat: \"1.12.46\"\n")
+                .unwrap();
+
+        assert_eq!(
+            requirements.required_for("=LEN(CONCAT(\"a\", XLOOKUP(1, t.a, t.b)))"),
+            Some(GnumericVersion::new(1, 12, 50))
+        );
+        assert_eq!(
+            requirements.required_for("=Concat(\"a\", \"b\")"),
+            Some(GnumericVersion::new(1, 12, 46))
+        );
+        assert_eq!(requirements.required_for("=SUM(1, 2)"), None);
+        assert!(
+            serde_yaml_ng::from_str::<FunctionRequirements>("functions: { SUM: one }").is_err()
+        );
+    }
+
+    #[test]
+    fn shipped_requirements_cover_newer_functions() {
+        let requirements =
+            FunctionRequirements::load(&Path::new("tests").join(FUNCTIONS_FILE)).unwrap();
+        for function in [
+            "LET", "LAMBDA", "XLOOKUP", "FILTER", "SORT", "UNIQUE", "SEQUENCE", "MAXIFS", "MINIFS",
+            "IFS", "SWITCH",
+        ] {
+            assert!(
+                requirements
+                    .required_for(&format!("={function}(1)"))
+                    .is_some(),
+                "{function} has no minimum Gnumeric release"
+            );
+        }
+    }
+
+    #[test]
+    fn with_defaults_lets_configuration_override() {
+        let configured = BTreeMap::from([
+            ("LC_ALL".to_string(), "de_DE.UTF-8".to_string()),
+            ("TZ".to_string(), "UTC".to_string()),
+        ]);
+        let env = with_defaults(&GnumericEngine::DEFAULT_ENV, &configured);
+        assert_eq!(env["LC_ALL"], "de_DE.UTF-8");
+        assert_eq!(env["TZ"], "UTC");
+        assert_eq!(
+            with_defaults(&GnumericEngine::DEFAULT_ENV, &BTreeMap::new())["LC_ALL"],
+            "C"
+        );
+    }
+
+    /// Recalculates formatted numbers under several numeric locales with
+    /// the default options, and parses them back as raw numbers. Needs
+    /// ssconvert.
+    #[test]
+    fn recalculated_numbers_are_raw_under_any_numeric_locale() {
+        use rust_xlsxwriter::{Format, Formula as XlsxFormula, Workbook};
+
+        if GnumericEngine::detect().is_none() {
+            return;
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let xlsx = temp_dir.path().join("locale.xlsx");
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("assumptions").unwrap();
+        let cells: [(&str, &str, &str, f64); 4] = [
+            ("plain", "=1234.5", "General", 1234.5),
+            ("percent", "=1/8", "0.00%", 0.125),
+            ("currency", "=-1234.5", "$#,##0.00", -1234.5),
+            ("scientific", "=15000000000", "0.00E+00", 1.5e10),
+        ];
+        for (row, (label, formula, format, _)) in (0u32..).zip(&cells) {
+            sheet.write(row, 0, *label).unwrap();
+            sheet
+                .write_formula_with_format(
+                    row,
+                    1,
+                    XlsxFormula::new(*formula),
+                    &Format::new().set_num_format(*format),
+                )
+                .unwrap();
+        }
+        workbook.save(&xlsx).unwrap();
+
+        // LC_ALL=C and format=raw are defaults, so the numeric locale and
+        // the cells' formats must not show
+        for locale in ["C", "de_DE.UTF-8", "fr_FR.UTF-8"] {
+            let config = EngineConfig {
+                env: BTreeMap::from([("LC_NUMERIC".to_string(), locale.to_string())]),
+                ..EngineConfig::default()
+            };
+            let engine = GnumericEngine::with_config(&config).unwrap();
+            let out = temp_dir.path().join(locale);
+            std::fs::create_dir_all(&out).unwrap();
+            let sheets = engine.xlsx_to_csv_all_sheets(&xlsx, &out).unwrap();
+
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_path(&sheets[0].path)
+                .unwrap();
+            let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+            for ((label, _, _, expected), record) in cells.iter().zip(&records) {
+                assert_eq!(&record[0], *label);
+                let actual = excel::parse_number(&record[1])
+                    .unwrap_or_else(|| panic!("{label} under {locale}: {}", &record[1]));
+                assert!(
+                    (actual - expected).abs() <= expected.abs() * 1e-9,
+                    "{label} under {locale}: {}",
+                    &record[1]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn with_config_rejects_bad_separator_and_missing_binary() {
+        let config = EngineConfig {
+            export_options: BTreeMap::from([("separator".to_string(), ";".to_string())]),
+            ..EngineConfig::default()
+        };
+        assert!(GnumericEngine::with_config(&config)
+            .unwrap_err()
+            .contains("separator"));
+
+        let config = EngineConfig {
+            ssconvert: Some(PathBuf::from("/nonexistent/ssconvert")),
+            ..EngineConfig::default()
+        };
+        assert!(GnumericEngine::with_config(&config).is_err());
+    }
+
     #[test]
     fn engine_detection_returns_valid_engine_or_none() {
         let _ = GnumericEngine::detect();
@@ -9,32 +9,172 @@ use std::path::Path;
 use calamine::{open_workbook, Data, Reader, Xlsx};
 use rust_xlsxwriter::{Formula, Workbook, XlsxError};
 
+use crate::types::{WorkbookScalar, WorkbookSheet};
+
 /// Creates a test Excel file with scalars for import testing.
 ///
 /// # Errors
 ///
 /// Returns an error if the workbook cannot be created or saved.
 pub fn create_test_scalars_xlsx(path: &Path) -> Result<(), XlsxError> {
-    let mut workbook = Workbook::new();
-    let sheet = workbook.add_worksheet();
-    sheet.set_name("Scalars")?;
+    let scalar = |name: &str, value: Option<f64>, formula: Option<&str>| WorkbookScalar {
+        name: name.to_string(),
+        value,
+        formula: formula.map(str::to_string),
+        todo: None,
+    };
+    write_workbook(
+        path,
+        &[WorkbookSheet {
+            name: "Scalars".to_string(),
+            scalars: vec![
+                scalar("revenue", Some(100_000.0), None),
+                scalar("costs", Some(40_000.0), None),
+                scalar("profit", None, Some("=B2-B3")),
+            ],
+            columns: Vec::new(),
+        }],
+    )
+}
 
-    sheet.write(0, 0, "Name")?;
-    sheet.write(0, 1, "Value")?;
+/// Writes an import workbook.
+///
+/// Scalar sheets get a `Name`/`Value` header row and one scalar per row
+/// below it; table sheets get the column names in row 1 and their values
+/// or formulas from row 2. Formulas are written without cached results.
+///
+/// # Errors
+///
+/// Returns an error if a sheet or formula is rejected, or the workbook
+/// cannot be saved.
+pub fn write_workbook(path: &Path, sheets: &[WorkbookSheet]) -> Result<(), XlsxError> {
+    let mut workbook = Workbook::new();
 
-    sheet.write(1, 0, "revenue")?;
-    sheet.write(1, 1, 100_000.0)?;
+    for spec in sheets {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(&spec.name)?;
 
-    sheet.write(2, 0, "costs")?;
-    sheet.write(2, 1, 40_000.0)?;
+        if !spec.scalars.is_empty() {
+            sheet.write(0, 0, "Name")?;
+            sheet.write(0, 1, "Value")?;
+        }
+        for (row, scalar) in (1..).zip(&spec.scalars) {
+            sheet.write(row, 0, scalar.name.as_str())?;
+            if let Some(formula) = &scalar.formula {
+                sheet.write_formula(row, 1, Formula::new(formula))?;
+            } else if let Some(value) = scalar.value {
+                sheet.write(row, 1, value)?;
+            }
+        }
 
-    sheet.write(3, 0, "profit")?;
-    sheet.write_formula(3, 1, Formula::new("=B2-B3"))?;
+        for (col, column) in (0..).zip(&spec.columns) {
+            sheet.write(0, col, column.name.as_str())?;
+            for (row, index) in (1..).zip(0..spec.rows()) {
+                if let Some(formula) = column.formula_at(index) {
+                    sheet.write_formula(row, col, Formula::new(formula))?;
+                } else if let Some(&value) = column.values.as_ref().and_then(|v| v.get(index)) {
+                    sheet.write(row, col, value)?;
+                }
+            }
+        }
+    }
 
     workbook.save(path)?;
     Ok(())
 }
 
+/// Workbook part holding the workbook properties.
+const WORKBOOK_XML: &str = "xl/workbook.xml";
+
+/// Switches an XLSX workbook to the 1904 date system (`date1904`) in place.
+///
+/// Stored numbers are left as they are; formulas such as `DATE` return
+/// 1904-system serials once the workbook is recalculated.
+///
+/// # Errors
+///
+/// Returns an error if the file is not a readable XLSX archive or cannot
+/// be rewritten.
+pub fn set_date1904(path: &Path) -> Result<(), String> {
+    let file =
+        std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+    let patched_path = path.with_extension("date1904.xlsx");
+    let patched = std::fs::File::create(&patched_path)
+        .map_err(|e| format!("Failed to create {}: {e}", patched_path.display()))?;
+    let mut writer = zip::ZipWriter::new(patched);
+    let write_error = |e: zip::result::ZipError| format!("Failed to write workbook: {e}");
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(write_error)?;
+        if entry.name() == WORKBOOK_XML {
+            let mut xml = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut xml)
+                .map_err(|e| format!("Failed to read {WORKBOOK_XML}: {e}"))?;
+            let options = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+            writer
+                .start_file(WORKBOOK_XML, options)
+                .map_err(write_error)?;
+            std::io::Write::write_all(&mut writer, with_date1904(&xml).as_bytes())
+                .map_err(|e| format!("Failed to write {WORKBOOK_XML}: {e}"))?;
+        } else {
+            drop(entry);
+            let entry = archive.by_index_raw(i).map_err(write_error)?;
+            writer.raw_copy_file(entry).map_err(write_error)?;
+        }
+    }
+    writer.finish().map_err(write_error)?;
+
+    std::fs::rename(&patched_path, path)
+        .map_err(|e| format!("Failed to replace {}: {e}", path.display()))
+}
+
+/// Sets `date1904="1"` on the `workbookPr` element of `workbook.xml`,
+/// adding the element after `fileVersion` (or first) if missing.
+fn with_date1904(xml: &str) -> String {
+    const ELEMENT: &str = "<workbookPr";
+    const ATTRIBUTE: &str = "date1904=\"";
+
+    if let Some(start) = find_element(xml, ELEMENT) {
+        let end = start + xml[start..].find('>').unwrap_or(xml.len() - start);
+        let tag = &xml[start..end];
+        if let Some(attribute) = tag.find(ATTRIBUTE) {
+            let value_start = start + attribute + ATTRIBUTE.len();
+            let value_end = value_start + xml[value_start..].find('"').unwrap_or(0);
+            return format!("{}1{}", &xml[..value_start], &xml[value_end..]);
+        }
+        let insert = start + ELEMENT.len();
+        return format!("{} date1904=\"1\"{}", &xml[..insert], &xml[insert..]);
+    }
+
+    let after = |element: &str| {
+        let start = find_element(xml, element)?;
+        Some(start + xml[start..].find('>')? + 1)
+    };
+    let insert = after("<fileVersion")
+        .or_else(|| after("<workbook"))
+        .unwrap_or(0);
+    format!(
+        "{}<workbookPr date1904=\"1\"/>{}",
+        &xml[..insert],
+        &xml[insert..]
+    )
+}
+
+/// Finds the start tag `element` (e.g. `<workbookPr`), skipping longer
+/// names that share its prefix such as `<workbookProtection`.
+fn find_element(xml: &str, element: &str) -> Option<usize> {
+    xml.match_indices(element)
+        .map(|(start, _)| start)
+        .find(|&start| {
+            xml[start + element.len()..]
+                .starts_with(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        })
+}
+
 /// Cell value from an Excel file.
 #[derive(Debug, Clone, PartialEq)]
 pub enum CellValue {
@@ -61,6 +201,40 @@ impl CellValue {
             _ => None,
         }
     }
+
+    /// Interprets a cell of Gnumeric's CSV output.
+    #[must_use]
+    pub fn from_csv(cell: &str) -> Self {
+        let cell = cell.trim();
+        if cell.is_empty() {
+            return Self::Empty;
+        }
+        if let Some(n) = parse_number(cell) {
+            return Self::Number(n);
+        }
+        match cell {
+            "TRUE" => Self::Bool(true),
+            "FALSE" => Self::Bool(false),
+            _ if cell.starts_with('#') && cell.ends_with(['!', '?', 'A']) => {
+                Self::Error(cell.to_string())
+            }
+            _ => Self::Text(cell.to_string()),
+        }
+    }
+}
+
+/// Parses a number as ssconvert writes it with `format=raw` under
+/// `LC_ALL=C` (e.g., `1234.5`, `-0.25`, `1.5E+10`).
+///
+/// Display formats (thousands separators, decimal commas, `%`, currency)
+/// are not numbers here: they mean the recalculation ran with other
+/// options, and the cell is reported as non-numeric rather than guessed.
+#[must_use]
+pub fn parse_number(text: &str) -> Option<f64> {
+    text.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
 }
 
 impl From<&Data> for CellValue {
@@ -81,6 +255,17 @@ impl From<&Data> for CellValue {
 /// Sheet data from an Excel file.
 pub type SheetData = Vec<(String, Vec<Vec<CellValue>>)>;
 
+/// Reads the sheet names of an Excel file, in workbook order.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened.
+pub fn sheet_names(path: &Path) -> Result<Vec<String>, String> {
+    let workbook: Xlsx<_> =
+        open_workbook(path).map_err(|e| format!("Failed to open Excel file: {e}"))?;
+    Ok(workbook.sheet_names())
+}
+
 /// Reads an Excel file and returns sheet data.
 ///
 /// # Errors
@@ -119,6 +304,146 @@ mod tests {
         assert_eq!(num.as_number(), Some(42.0));
     }
 
+    #[test]
+    fn write_workbook_lays_out_scalars_and_tables() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let scalars_path = temp_dir.path().join("scalars.xlsx");
+        let table_path = temp_dir.path().join("table.xlsx");
+        let yaml = r#"
+- name: sales
+  columns:
+    - { name: price, values: [10, 20] }
+    - { name: total, formula: "=A{{row}}*2" }
+"#;
+        let sheets: Vec<WorkbookSheet> = serde_yaml_ng::from_str(yaml).unwrap();
+        create_test_scalars_xlsx(&scalars_path).unwrap();
+        write_workbook(&table_path, &sheets).unwrap();
+
+        let scalars = read_xlsx(&scalars_path).unwrap();
+        assert_eq!(scalars[0].0, "Scalars");
+        assert_eq!(scalars[0].1[2][0], CellValue::Text("costs".to_string()));
+        assert_eq!(scalars[0].1[2][1], CellValue::Number(40_000.0));
+        assert_eq!(scalars[0].1[3][0], CellValue::Text("profit".to_string()));
+
+        let table = read_xlsx(&table_path).unwrap();
+        assert_eq!(table[0].0, "sales");
+        assert_eq!(
+            table[0].1[0],
+            [
+                CellValue::Text("price".to_string()),
+                CellValue::Text("total".to_string())
+            ]
+        );
+        assert_eq!(table[0].1[2][0], CellValue::Number(20.0));
+    }
+
+    #[test]
+    fn cell_value_from_csv() {
+        assert_eq!(CellValue::from_csv("1234.5"), CellValue::Number(1234.5));
+        assert_eq!(
+            CellValue::from_csv("1,234.5"),
+            CellValue::Text("1,234.5".to_string())
+        );
+        assert_eq!(CellValue::from_csv("TRUE"), CellValue::Bool(true));
+        assert_eq!(
+            CellValue::from_csv("#DIV/0!"),
+            CellValue::Error("#DIV/0!".to_string())
+        );
+        assert_eq!(
+            CellValue::from_csv("#N/A"),
+            CellValue::Error("#N/A".to_string())
+        );
+        assert_eq!(
+            CellValue::from_csv("abc"),
+            CellValue::Text("abc".to_string())
+        );
+        assert_eq!(CellValue::from_csv(""), CellValue::Empty);
+    }
+
+    #[test]
+    fn parse_number_reads_only_raw_numbers() {
+        for (text, expected) in [
+            ("1234.5", 1234.5),
+            ("-0.25", -0.25),
+            (" 42 ", 42.0),
+            ("1.5E+10", 1.5e10),
+            ("2.5e-3", 0.0025),
+            (".5", 0.5),
+        ] {
+            assert_eq!(parse_number(text), Some(expected), "{text}");
+        }
+        for text in [
+            "", "abc", "1,234.5", "1234,5", "1.234,5", "1 234", "12.5%", "$1.00", "(42)", "1.2.3",
+            "1e", "inf", "NaN", "#N/A",
+        ] {
+            assert_eq!(parse_number(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn with_date1904_sets_or_adds_the_workbook_flag() {
+        assert_eq!(
+            with_date1904(r#"<workbook><workbookPr defaultThemeVersion="124226"/></workbook>"#),
+            r#"<workbook><workbookPr date1904="1" defaultThemeVersion="124226"/></workbook>"#
+        );
+        assert_eq!(
+            with_date1904(r#"<workbook><workbookPr date1904="0"/></workbook>"#),
+            r#"<workbook><workbookPr date1904="1"/></workbook>"#
+        );
+        assert_eq!(
+            with_date1904(r#"<workbook xmlns="x"><fileVersion appName="xl"/><sheets/></workbook>"#),
+            r#"<workbook xmlns="x"><fileVersion appName="xl"/><workbookPr date1904="1"/><sheets/></workbook>"#
+        );
+        assert_eq!(
+            with_date1904(
+                r#"<workbook><workbookProtection lockStructure="1"/><workbookPr/></workbook>"#
+            ),
+            r#"<workbook><workbookProtection lockStructure="1"/><workbookPr date1904="1"/></workbook>"#
+        );
+        assert_eq!(
+            with_date1904(
+                r#"<workbook><workbookProtection lockStructure="1"/><sheets/></workbook>"#
+            ),
+            r#"<workbook><workbookPr date1904="1"/><workbookProtection lockStructure="1"/><sheets/></workbook>"#
+        );
+    }
+
+    #[test]
+    fn set_date1904_rewrites_the_workbook_in_place() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("dates.xlsx");
+        create_test_scalars_xlsx(&path).unwrap();
+
+        set_date1904(&path).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut xml = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name(WORKBOOK_XML).unwrap(), &mut xml)
+            .unwrap();
+        assert!(xml.contains(r#"date1904="1""#));
+        assert_eq!(sheet_names(&path).unwrap(), ["Scalars"]);
+        assert!(!path.with_extension("date1904.xlsx").exists());
+    }
+
+    #[test]
+    fn sheet_names_lists_every_sheet_in_order() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("many.xlsx");
+        let mut workbook = Workbook::new();
+        for i in 0..12 {
+            workbook
+                .add_worksheet()
+                .set_name(format!("sheet_{i}"))
+                .unwrap();
+        }
+        workbook.save(&path).unwrap();
+
+        let names = sheet_names(&path).unwrap();
+        assert_eq!(names.len(), 12);
+        assert_eq!(names[0], "sheet_0");
+        assert_eq!(names[11], "sheet_11");
+    }
+
     #[test]
     fn cell_value_as_text() {
         let text = CellValue::Text("hello".to_string());
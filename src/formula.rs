@@ -0,0 +1,967 @@
+//! Excel formula parsing for analysis.
+//!
+//! Tokenizes and parses formula text into an [`Expr`] tree: function calls,
+//! cell and name references, forge `table.column` references, literals,
+//! operators and array constants. [`Formula`]'s `Display` writes the tree
+//! back as canonical formula text that parses to the same tree.
+
+use std::fmt;
+
+/// A parsed formula (the text after the leading `=`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    /// Root expression.
+    pub expr: Expr,
+}
+
+/// A formula expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Number literal, kept as written (e.g., `0.06`, `1E-10`).
+    Number(String),
+    /// String literal (unescaped).
+    Text(String),
+    /// Boolean literal (`TRUE` / `FALSE` without parentheses).
+    Bool(bool),
+    /// Error literal (e.g., `#N/A`, `#DIV/0!`).
+    Error(String),
+    /// Cell reference or name (e.g., `A1`, `$B$2`, `Sheet1!A1`, LET variables).
+    Reference(String),
+    /// Forge `table.column` reference (also `section.scalar`).
+    TableRef {
+        /// Table (or section) name.
+        table: String,
+        /// Column (or scalar) name.
+        column: String,
+    },
+    /// Omitted argument (e.g., the second argument of `SUM(1, , 3)`).
+    Empty,
+    /// Function call.
+    Call {
+        /// Function name as written.
+        name: String,
+        /// Arguments.
+        args: Vec<Self>,
+    },
+    /// Call of a function value (e.g., `LAMBDA(x, x*x)(5)`).
+    Invoke {
+        /// Expression producing the function.
+        callee: Box<Self>,
+        /// Arguments.
+        args: Vec<Self>,
+    },
+    /// Prefix `-` or `+`.
+    Unary {
+        /// The operator.
+        op: UnaryOp,
+        /// The operand.
+        operand: Box<Self>,
+    },
+    /// Postfix `%`.
+    Percent(Box<Self>),
+    /// Binary operation.
+    Binary {
+        /// The operator.
+        op: BinaryOp,
+        /// Left operand.
+        left: Box<Self>,
+        /// Right operand.
+        right: Box<Self>,
+    },
+    /// Array constant: rows of columns (e.g., `{1,2;3,4}`).
+    Array(Vec<Vec<Self>>),
+}
+
+/// Prefix operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `-`
+    Neg,
+    /// `+`
+    Plus,
+}
+
+/// Binary operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    /// `:` (range)
+    Range,
+    /// `^`
+    Pow,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `&`
+    Concat,
+    /// `=`
+    Eq,
+    /// `<>`
+    Ne,
+    /// `<`
+    Lt,
+    /// `>`
+    Gt,
+    /// `<=`
+    Le,
+    /// `>=`
+    Ge,
+}
+
+/// Errors found while parsing a formula.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    /// A character that cannot start any token.
+    #[error("unexpected character `{ch}` at {pos}")]
+    UnexpectedChar { ch: char, pos: usize },
+    /// A string literal without closing quote.
+    #[error("unterminated string starting at {pos}")]
+    UnterminatedString { pos: usize },
+    /// A token that does not fit the grammar.
+    #[error("unexpected {found} at {pos}")]
+    UnexpectedToken { found: String, pos: usize },
+    /// The formula ended too early.
+    #[error("unexpected end of formula")]
+    UnexpectedEnd,
+}
+
+// Printing precedence, lowest to highest
+const PREC_COMPARISON: u8 = 1;
+const PREC_CONCAT: u8 = 2;
+const PREC_ADDITIVE: u8 = 3;
+const PREC_MULTIPLICATIVE: u8 = 4;
+const PREC_POWER: u8 = 5;
+const PREC_PERCENT: u8 = 6;
+const PREC_UNARY: u8 = 7;
+const PREC_RANGE: u8 = 8;
+const PREC_PRIMARY: u8 = 9;
+
+impl BinaryOp {
+    /// Returns the operator's formula text.
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Range => ":",
+            Self::Pow => "^",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Concat => "&",
+            Self::Eq => "=",
+            Self::Ne => "<>",
+            Self::Lt => "<",
+            Self::Gt => ">",
+            Self::Le => "<=",
+            Self::Ge => ">=",
+        }
+    }
+
+    const fn precedence(self) -> u8 {
+        match self {
+            Self::Range => PREC_RANGE,
+            Self::Pow => PREC_POWER,
+            Self::Mul | Self::Div => PREC_MULTIPLICATIVE,
+            Self::Add | Self::Sub => PREC_ADDITIVE,
+            Self::Concat => PREC_CONCAT,
+            Self::Eq | Self::Ne | Self::Lt | Self::Gt | Self::Le | Self::Ge => PREC_COMPARISON,
+        }
+    }
+}
+
+impl Formula {
+    /// Parses formula text, with or without the leading `=`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not a well-formed formula.
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let text = text.trim();
+        let body = text.strip_prefix('=').unwrap_or(text);
+        let tokens = tokenize(body)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_expr()?;
+        match parser.peek() {
+            None => Ok(Self { expr }),
+            Some(token) => Err(parser.unexpected(token)),
+        }
+    }
+
+    /// Returns every function call, in order of appearance, with the names
+    /// of the calls enclosing it (outermost first).
+    #[must_use]
+    pub fn calls(&self) -> Vec<(String, Vec<String>)> {
+        let mut calls = Vec::new();
+        let mut enclosing = Vec::new();
+        collect_calls(&self.expr, &mut enclosing, &mut calls);
+        calls
+    }
+
+    /// Returns every `table.column` reference, in order of appearance.
+    #[must_use]
+    pub fn table_refs(&self) -> Vec<(&str, &str)> {
+        let mut refs = Vec::new();
+        self.expr.visit(&mut |expr| {
+            if let Expr::TableRef { table, column } = expr {
+                refs.push((table.as_str(), column.as_str()));
+            }
+        });
+        refs
+    }
+}
+
+fn collect_calls(expr: &Expr, enclosing: &mut Vec<String>, calls: &mut Vec<(String, Vec<String>)>) {
+    match expr {
+        Expr::Call { name, args } => {
+            let name = name.to_uppercase();
+            calls.push((name.clone(), enclosing.clone()));
+            enclosing.push(name);
+            for arg in args {
+                collect_calls(arg, enclosing, calls);
+            }
+            enclosing.pop();
+        }
+        _ => {
+            for child in expr.children() {
+                collect_calls(child, enclosing, calls);
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// Returns the direct sub-expressions.
+    #[must_use]
+    pub fn children(&self) -> Vec<&Self> {
+        match self {
+            Self::Call { args, .. } => args.iter().collect(),
+            Self::Invoke { callee, args } => std::iter::once(callee.as_ref()).chain(args).collect(),
+            Self::Unary { operand, .. } | Self::Percent(operand) => vec![operand],
+            Self::Binary { left, right, .. } => vec![left, right],
+            Self::Array(rows) => rows.iter().flatten().collect(),
+            Self::Number(_)
+            | Self::Text(_)
+            | Self::Bool(_)
+            | Self::Error(_)
+            | Self::Reference(_)
+            | Self::TableRef { .. }
+            | Self::Empty => Vec::new(),
+        }
+    }
+
+    /// Calls `f` on this expression and every sub-expression (pre-order).
+    pub fn visit<'a>(&'a self, f: &mut dyn FnMut(&'a Self)) {
+        f(self);
+        for child in self.children() {
+            child.visit(f);
+        }
+    }
+
+    const fn precedence(&self) -> u8 {
+        match self {
+            Self::Binary { op, .. } => op.precedence(),
+            Self::Unary { .. } => PREC_UNARY,
+            Self::Percent(_) => PREC_PERCENT,
+            _ => PREC_PRIMARY,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, parenthesize: bool) -> fmt::Result {
+        if parenthesize {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+fn fmt_args(f: &mut fmt::Formatter<'_>, args: &[Expr]) -> fmt::Result {
+    f.write_str("(")?;
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{arg}")?;
+    }
+    f.write_str(")")
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "={}", self.expr)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => f.write_str(n),
+            Self::Text(s) => write!(f, "\"{}\"", escape_text(s)),
+            Self::Bool(true) => f.write_str("TRUE"),
+            Self::Bool(false) => f.write_str("FALSE"),
+            Self::Error(e) | Self::Reference(e) => f.write_str(e),
+            Self::TableRef { table, column } => write!(f, "{table}.{column}"),
+            Self::Empty => Ok(()),
+            Self::Call { name, args } => {
+                f.write_str(name)?;
+                fmt_args(f, args)
+            }
+            Self::Invoke { callee, args } => {
+                let bare = matches!(**callee, Self::Call { .. } | Self::Invoke { .. });
+                callee.fmt_operand(f, !bare)?;
+                fmt_args(f, args)
+            }
+            Self::Unary { op, operand } => {
+                f.write_str(match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::Plus => "+",
+                })?;
+                operand.fmt_operand(f, operand.precedence() < PREC_UNARY)
+            }
+            Self::Percent(operand) => {
+                operand.fmt_operand(f, operand.precedence() < PREC_PERCENT)?;
+                f.write_str("%")
+            }
+            Self::Binary { op, left, right } => {
+                let prec = op.precedence();
+                left.fmt_operand(f, left.precedence() < prec)?;
+                if *op == BinaryOp::Range {
+                    f.write_str(":")?;
+                } else {
+                    write!(f, " {} ", op.symbol())?;
+                }
+                right.fmt_operand(f, right.precedence() <= prec)
+            }
+            Self::Array(rows) => {
+                f.write_str("{")?;
+                for (r, row) in rows.iter().enumerate() {
+                    if r > 0 {
+                        f.write_str(";")?;
+                    }
+                    for (c, item) in row.iter().enumerate() {
+                        if c > 0 {
+                            f.write_str(",")?;
+                        }
+                        write!(f, "{item}")?;
+                    }
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Escapes text for use inside a formula string literal (`"` becomes `""`).
+#[must_use]
+pub fn escape_text(text: &str) -> String {
+    text.replace('"', "\"\"")
+}
+
+/// A lexical token with its character position.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Text(String),
+    Error(String),
+    Ident(String),
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    Semicolon,
+    Percent,
+    Op(BinaryOp),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "number `{n}`"),
+            Self::Text(s) => write!(f, "string \"{s}\""),
+            Self::Error(e) => write!(f, "error `{e}`"),
+            Self::Ident(i) => write!(f, "`{i}`"),
+            Self::LParen => f.write_str("`(`"),
+            Self::RParen => f.write_str("`)`"),
+            Self::LBrace => f.write_str("`{`"),
+            Self::RBrace => f.write_str("`}`"),
+            Self::Comma => f.write_str("`,`"),
+            Self::Semicolon => f.write_str("`;`"),
+            Self::Percent => f.write_str("`%`"),
+            Self::Op(op) => write!(f, "`{}`", op.symbol()),
+        }
+    }
+}
+
+const fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '_' | '$' | '\\')
+}
+
+const fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$')
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(ParseError::UnterminatedString { pos: start }),
+                        Some('"') if chars.get(i + 1) == Some(&'"') => {
+                            s.push('"');
+                            i += 2;
+                        }
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&ch) => {
+                            s.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                Token::Text(s)
+            }
+            '0'..='9' | '.' if c != '.' || chars.get(i + 1).is_some_and(char::is_ascii_digit) => {
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                if chars.get(i) == Some(&'.') {
+                    i += 1;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                // A second decimal point (`1.2.3`) is not a number
+                if chars.get(i) == Some(&'.') {
+                    return Err(ParseError::UnexpectedChar { ch: '.', pos: i });
+                }
+                if matches!(chars.get(i), Some('e' | 'E'))
+                    && (chars.get(i + 1).is_some_and(char::is_ascii_digit)
+                        || (matches!(chars.get(i + 1), Some('+' | '-'))
+                            && chars.get(i + 2).is_some_and(char::is_ascii_digit)))
+                {
+                    i += 2;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                Token::Number(chars[start..i].iter().collect())
+            }
+            '#' => {
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '/') {
+                    i += 1;
+                }
+                if matches!(chars.get(i), Some('!' | '?')) {
+                    i += 1;
+                }
+                Token::Error(chars[start..i].iter().collect())
+            }
+            '\'' => {
+                // Quoted sheet name: 'My Sheet'!A1, with '' for a quote
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == '\'' {
+                        if chars.get(i + 1) != Some(&'\'') {
+                            break;
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                if chars.get(i + 1) != Some(&'!') {
+                    return Err(ParseError::UnexpectedChar { ch: c, pos: start });
+                }
+                i += 2;
+                while i < chars.len() && is_ident_char(chars[i]) {
+                    i += 1;
+                }
+                Token::Ident(chars[start..i].iter().collect())
+            }
+            c if is_ident_start(c) => {
+                i += 1;
+                loop {
+                    while i < chars.len() && is_ident_char(chars[i]) {
+                        i += 1;
+                    }
+                    // Sheet-qualified reference: Sheet1!A1
+                    if chars.get(i) == Some(&'!')
+                        && chars
+                            .get(i + 1)
+                            .is_some_and(|&n| is_ident_start(n) || n.is_ascii_digit())
+                    {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                Token::Ident(chars[start..i].iter().collect())
+            }
+            '(' | ')' | '{' | '}' | ',' | ';' | '%' | ':' | '^' | '*' | '/' | '+' | '-' | '&'
+            | '=' => {
+                i += 1;
+                match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '{' => Token::LBrace,
+                    '}' => Token::RBrace,
+                    ',' => Token::Comma,
+                    ';' => Token::Semicolon,
+                    '%' => Token::Percent,
+                    ':' => Token::Op(BinaryOp::Range),
+                    '^' => Token::Op(BinaryOp::Pow),
+                    '*' => Token::Op(BinaryOp::Mul),
+                    '/' => Token::Op(BinaryOp::Div),
+                    '+' => Token::Op(BinaryOp::Add),
+                    '-' => Token::Op(BinaryOp::Sub),
+                    '&' => Token::Op(BinaryOp::Concat),
+                    _ => Token::Op(BinaryOp::Eq),
+                }
+            }
+            '<' => {
+                i += 1;
+                match chars.get(i) {
+                    Some('>') => {
+                        i += 1;
+                        Token::Op(BinaryOp::Ne)
+                    }
+                    Some('=') => {
+                        i += 1;
+                        Token::Op(BinaryOp::Le)
+                    }
+                    _ => Token::Op(BinaryOp::Lt),
+                }
+            }
+            '>' => {
+                i += 1;
+                if chars.get(i) == Some(&'=') {
+                    i += 1;
+                    Token::Op(BinaryOp::Ge)
+                } else {
+                    Token::Op(BinaryOp::Gt)
+                }
+            }
+            _ => return Err(ParseError::UnexpectedChar { ch: c, pos: start }),
+        };
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive-descent parser over the token list.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn unexpected(&self, token: &Token) -> ParseError {
+        let pos = self
+            .tokens
+            .get(self.pos.min(self.tokens.len().saturating_sub(1)))
+            .map_or(0, |(_, pos)| *pos);
+        ParseError::UnexpectedToken {
+            found: token.to_string(),
+            pos,
+        }
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(token) if token == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(token) => Err(self.unexpected(token)),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        self.parse_binary(PREC_COMPARISON)
+    }
+
+    /// Parses left-associative binary operators of at least `min_prec`.
+    fn parse_binary(&mut self, min_prec: u8) -> Result<Expr, ParseError> {
+        if min_prec > PREC_POWER {
+            return self.parse_percent();
+        }
+
+        let mut left = self.parse_binary(min_prec + 1)?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            if op.precedence() != min_prec {
+                break;
+            }
+            self.pos += 1;
+            let right = self.parse_binary(min_prec + 1)?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_percent(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Token::Percent) {
+            self.pos += 1;
+            expr = Expr::Percent(Box::new(expr));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let op = match self.peek() {
+            Some(Token::Op(BinaryOp::Sub)) => UnaryOp::Neg,
+            Some(Token::Op(BinaryOp::Add)) => UnaryOp::Plus,
+            _ => return self.parse_range(),
+        };
+        self.pos += 1;
+        let operand = self.parse_unary()?;
+        Ok(Expr::Unary {
+            op,
+            operand: Box::new(operand),
+        })
+    }
+
+    fn parse_range(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_primary()?;
+        while self.peek() == Some(&Token::Op(BinaryOp::Range)) {
+            self.pos += 1;
+            let right = self.parse_primary()?;
+            left = Expr::Binary {
+                op: BinaryOp::Range,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.next().ok_or(ParseError::UnexpectedEnd)?;
+        let mut expr = match token {
+            Token::Number(n) => Expr::Number(n),
+            Token::Text(s) => Expr::Text(s),
+            Token::Error(e) => Expr::Error(e),
+            Token::Ident(name) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.pos += 1;
+                    let args = self.parse_args()?;
+                    Expr::Call { name, args }
+                } else {
+                    ident_expr(name)
+                }
+            }
+            Token::LParen => {
+                let inner = self.parse_expr()?;
+                self.expect(&Token::RParen)?;
+                inner
+            }
+            Token::LBrace => self.parse_array()?,
+            other => {
+                self.pos -= 1;
+                return Err(self.unexpected(&other));
+            }
+        };
+
+        // Invocation of a returned function: LAMBDA(x, x*x)(5)
+        while matches!(expr, Expr::Call { .. } | Expr::Invoke { .. })
+            && self.peek() == Some(&Token::LParen)
+        {
+            self.pos += 1;
+            let args = self.parse_args()?;
+            expr = Expr::Invoke {
+                callee: Box::new(expr),
+                args,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses call arguments after the opening parenthesis.
+    fn parse_args(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.pos += 1;
+            return Ok(args);
+        }
+
+        loop {
+            let arg = match self.peek() {
+                Some(Token::Comma | Token::RParen) => Expr::Empty,
+                _ => self.parse_expr()?,
+            };
+            args.push(arg);
+            match self.next() {
+                Some(Token::Comma) => {}
+                Some(Token::RParen) => return Ok(args),
+                Some(token) => {
+                    self.pos -= 1;
+                    return Err(self.unexpected(&token));
+                }
+                None => return Err(ParseError::UnexpectedEnd),
+            }
+        }
+    }
+
+    /// Parses an array constant after the opening brace.
+    fn parse_array(&mut self) -> Result<Expr, ParseError> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        loop {
+            row.push(self.parse_expr()?);
+            match self.next() {
+                Some(Token::Comma) => {}
+                Some(Token::Semicolon) => rows.push(std::mem::take(&mut row)),
+                Some(Token::RBrace) => {
+                    rows.push(row);
+                    return Ok(Expr::Array(rows));
+                }
+                Some(token) => {
+                    self.pos -= 1;
+                    return Err(self.unexpected(&token));
+                }
+                None => return Err(ParseError::UnexpectedEnd),
+            }
+        }
+    }
+}
+
+/// Classifies an identifier that is not followed by `(`.
+fn ident_expr(name: String) -> Expr {
+    if name.eq_ignore_ascii_case("TRUE") {
+        return Expr::Bool(true);
+    }
+    if name.eq_ignore_ascii_case("FALSE") {
+        return Expr::Bool(false);
+    }
+
+    if !name.contains(['!', '$', '\'']) {
+        if let Some((table, column)) = name.split_once('.') {
+            let is_name = |part: &str| {
+                part.chars()
+                    .next()
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    && !part.contains('.')
+            };
+            if is_name(table) && is_name(column) {
+                return Expr::TableRef {
+                    table: table.to_string(),
+                    column: column.to_string(),
+                };
+            }
+        }
+    }
+
+    Expr::Reference(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Expr {
+        Formula::parse(text).unwrap().expr
+    }
+
+    fn num(n: &str) -> Expr {
+        Expr::Number(n.to_string())
+    }
+
+    #[test]
+    fn parses_calls_references_and_literals() {
+        let expr = parse("=SUMIF(sales.region, \"North\", sales.amount) + $A$1");
+        let Expr::Binary { op, left, right } = expr else {
+            panic!("expected binary");
+        };
+        assert_eq!(op, BinaryOp::Add);
+        assert_eq!(*right, Expr::Reference("$A$1".to_string()));
+        assert_eq!(
+            *left,
+            Expr::Call {
+                name: "SUMIF".to_string(),
+                args: vec![
+                    Expr::TableRef {
+                        table: "sales".to_string(),
+                        column: "region".to_string()
+                    },
+                    Expr::Text("North".to_string()),
+                    Expr::TableRef {
+                        table: "sales".to_string(),
+                        column: "amount".to_string()
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn respects_excel_operator_precedence() {
+        // Negation binds tighter than ^; ^ tighter than * and +
+        assert_eq!(
+            parse("=-2^2"),
+            Expr::Binary {
+                op: BinaryOp::Pow,
+                left: Box::new(Expr::Unary {
+                    op: UnaryOp::Neg,
+                    operand: Box::new(num("2"))
+                }),
+                right: Box::new(num("2")),
+            }
+        );
+        assert_eq!(
+            parse("1+2*3"),
+            Expr::Binary {
+                op: BinaryOp::Add,
+                left: Box::new(num("1")),
+                right: Box::new(Expr::Binary {
+                    op: BinaryOp::Mul,
+                    left: Box::new(num("2")),
+                    right: Box::new(num("3")),
+                }),
+            }
+        );
+        assert_eq!(
+            parse("=1&2=\"12\"").to_string(),
+            "1 & 2 = \"12\"",
+            "comparison binds loosest"
+        );
+    }
+
+    #[test]
+    fn parses_special_forms() {
+        assert_eq!(
+            parse("=LAMBDA(x, x*x)(5)").to_string(),
+            "LAMBDA(x, x * x)(5)"
+        );
+        assert_eq!(parse("=SUM({1,2;3,4})").to_string(), "SUM({1,2;3,4})");
+        assert_eq!(parse("=IF(TRUE, 1, )").to_string(), "IF(TRUE, 1, )");
+        assert_eq!(parse("=TRUE()").to_string(), "TRUE()");
+        assert_eq!(parse("=ISNA(#N/A)").to_string(), "ISNA(#N/A)");
+        assert_eq!(parse("=50%").to_string(), "50%");
+        assert_eq!(parse("=1E-10").to_string(), "1E-10");
+        assert_eq!(parse("=RANK.EQ(3, A1:A5)").to_string(), "RANK.EQ(3, A1:A5)");
+        assert_eq!(
+            parse("=SUM('Q1 Data'!A1, Sheet2!B2)").to_string(),
+            "SUM('Q1 Data'!A1, Sheet2!B2)"
+        );
+        assert_eq!(
+            parse("='Bob''s Sheet'!A1 + 1"),
+            Expr::Binary {
+                op: BinaryOp::Add,
+                left: Box::new(Expr::Reference("'Bob''s Sheet'!A1".to_string())),
+                right: Box::new(num("1")),
+            }
+        );
+        assert_eq!(
+            parse("=LEN(\"say \"\"hi\"\"\")"),
+            Expr::Call {
+                name: "LEN".to_string(),
+                args: vec![Expr::Text("say \"hi\"".to_string())],
+            }
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let formulas = [
+            "=ROUND(PMT(0.06/12, 360, 200000), 2)",
+            "=(1+2)*3",
+            "=1-(2-3)",
+            "=-(1+2)",
+            "=2^(3^2)",
+            "=(-2)^2",
+            "=(1+2)%",
+            "=--A1",
+            "=IFERROR(1/0, \"err\")",
+            "=LET(x, 10, y, x*2, LAMBDA(a, a+y)(x))",
+            "=IF(AND(A1>=1, A1<>2, A1<=3), \"a\"&\"b\", {1,\"x\";TRUE,-1})",
+            "=SUM(scenarios.base_case) / COUNT(assumptions.q1, , 3)",
+        ];
+        for text in formulas {
+            let formula = Formula::parse(text).unwrap();
+            let printed = formula.to_string();
+            assert_eq!(
+                Formula::parse(&printed).unwrap(),
+                formula,
+                "{text} printed as {printed}"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_calls_with_enclosing_functions() {
+        let formula = Formula::parse("=ROUND(NPV(0.1, SUM(a.b), 3), 2) + ABS(-1)").unwrap();
+        let calls = formula.calls();
+        assert_eq!(calls.len(), 4);
+        assert_eq!(calls[0], ("ROUND".to_string(), vec![]));
+        assert_eq!(
+            calls[2],
+            (
+                "SUM".to_string(),
+                vec!["ROUND".to_string(), "NPV".to_string()]
+            )
+        );
+        assert_eq!(formula.table_refs(), [("a", "b")]);
+    }
+
+    #[test]
+    fn rejects_malformed_formulas() {
+        assert_eq!(Formula::parse("=SUM(1, 2"), Err(ParseError::UnexpectedEnd));
+        assert!(matches!(
+            Formula::parse("=LEN(\"abc)"),
+            Err(ParseError::UnterminatedString { pos: 4 })
+        ));
+        assert!(matches!(
+            Formula::parse("=1 + * 2"),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+        assert!(matches!(
+            Formula::parse("=1 ~ 2"),
+            Err(ParseError::UnexpectedChar { ch: '~', .. })
+        ));
+        assert_eq!(
+            Formula::parse("=1.2.3"),
+            Err(ParseError::UnexpectedChar { ch: '.', pos: 3 })
+        );
+        assert!(matches!(
+            Formula::parse("='Bob''s Sheet!A1"),
+            Err(ParseError::UnexpectedChar { ch: '\'', pos: 0 })
+        ));
+    }
+}
@@ -0,0 +1,625 @@
+//! Differential fuzzing of forge against Gnumeric.
+//!
+//! Generates random, type-correct formulas from a grammar of forge's
+//! functions, evaluates each with forge and with Gnumeric, and turns every
+//! disagreement into a ready-to-commit YAML spec. Generation only depends
+//! on the seed, so a run can be reproduced exactly.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::coverage::FunctionCatalog;
+use crate::excel::CellValue;
+use crate::formula::{BinaryOp, Expr, Formula, UnaryOp};
+
+/// Seeded pseudo-random number generator (`SplitMix64`).
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator from a seed.
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next 64 random bits.
+    pub const fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n` (`n` must be non-zero).
+    pub fn below(&mut self, n: usize) -> usize {
+        let n = n as u64;
+        usize::try_from(self.next_u64() % n).unwrap_or_default()
+    }
+
+    /// Returns `true` with the given probability in percent.
+    pub const fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+
+    /// Returns a random element of a non-empty slice.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// Type of a formula value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    /// Number.
+    Number,
+    /// Text.
+    Text,
+    /// Boolean.
+    Bool,
+}
+
+/// Kind of a function argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arg {
+    Number,
+    /// Small non-negative integer literal (digits, counts, periods).
+    Count,
+    Text,
+    Bool,
+    /// One to four numbers.
+    Numbers,
+}
+
+/// A function of the grammar: name, arguments and result type.
+struct Signature {
+    name: &'static str,
+    args: &'static [Arg],
+    returns: ValueType,
+}
+
+const fn sig(name: &'static str, args: &'static [Arg], returns: ValueType) -> Signature {
+    Signature {
+        name,
+        args,
+        returns,
+    }
+}
+
+use Arg::{Bool as B, Count as C, Number as N, Numbers as NS, Text as T};
+use ValueType::{Bool as BOOL, Number as NUM, Text as TEXT};
+
+/// Deterministic functions the fuzzer calls, with their argument types.
+const SIGNATURES: &[Signature] = &[
+    // Math
+    sig("ABS", &[N], NUM),
+    sig("CEILING", &[N, N], NUM),
+    sig("EXP", &[N], NUM),
+    sig("FLOOR", &[N, N], NUM),
+    sig("INT", &[N], NUM),
+    sig("LN", &[N], NUM),
+    sig("LOG", &[N, N], NUM),
+    sig("LOG10", &[N], NUM),
+    sig("MOD", &[N, N], NUM),
+    sig("PI", &[], NUM),
+    sig("POWER", &[N, N], NUM),
+    sig("ROUND", &[N, C], NUM),
+    sig("ROUNDDOWN", &[N, C], NUM),
+    sig("ROUNDUP", &[N, C], NUM),
+    sig("SIGN", &[N], NUM),
+    sig("SQRT", &[N], NUM),
+    sig("TRUNC", &[N], NUM),
+    // Aggregation and statistics
+    sig("AVERAGE", &[NS], NUM),
+    sig("COUNT", &[NS], NUM),
+    sig("MAX", &[NS], NUM),
+    sig("MEDIAN", &[NS], NUM),
+    sig("MIN", &[NS], NUM),
+    sig("PRODUCT", &[NS], NUM),
+    sig("STDEV", &[N, NS], NUM),
+    sig("SUM", &[NS], NUM),
+    sig("VAR", &[N, NS], NUM),
+    // Trigonometry
+    sig("ATAN", &[N], NUM),
+    sig("COS", &[N], NUM),
+    sig("COSH", &[N], NUM),
+    sig("DEGREES", &[N], NUM),
+    sig("RADIANS", &[N], NUM),
+    sig("SIN", &[N], NUM),
+    sig("TAN", &[N], NUM),
+    sig("TANH", &[N], NUM),
+    // Financial
+    sig("EFFECT", &[N, C], NUM),
+    sig("FV", &[N, C, N], NUM),
+    sig("NOMINAL", &[N, C], NUM),
+    sig("PMT", &[N, C, N], NUM),
+    sig("PV", &[N, C, N], NUM),
+    sig("SLN", &[N, N, C], NUM),
+    // Date
+    sig("DATE", &[C, C, C], NUM),
+    sig("DAY", &[N], NUM),
+    sig("MONTH", &[N], NUM),
+    sig("WEEKDAY", &[N], NUM),
+    sig("YEAR", &[N], NUM),
+    // Text
+    sig("CONCATENATE", &[T, T], TEXT),
+    sig("EXACT", &[T, T], BOOL),
+    sig("FIND", &[T, T], NUM),
+    sig("LEFT", &[T, C], TEXT),
+    sig("LEN", &[T], NUM),
+    sig("LOWER", &[T], TEXT),
+    sig("MID", &[T, C, C], TEXT),
+    sig("REPT", &[T, C], TEXT),
+    sig("RIGHT", &[T, C], TEXT),
+    sig("SEARCH", &[T, T], NUM),
+    sig("SUBSTITUTE", &[T, T, T], TEXT),
+    sig("TRIM", &[T], TEXT),
+    sig("UPPER", &[T], TEXT),
+    sig("VALUE", &[T], NUM),
+    // Logical and information
+    sig("AND", &[B, B], BOOL),
+    sig("IF", &[B, N, N], NUM),
+    sig("IFERROR", &[N, N], NUM),
+    sig("ISERROR", &[N], BOOL),
+    sig("ISEVEN", &[C], BOOL),
+    sig("ISNUMBER", &[N], BOOL),
+    sig("ISODD", &[C], BOOL),
+    sig("ISTEXT", &[T], BOOL),
+    sig("NOT", &[B], BOOL),
+    sig("OR", &[B, B], BOOL),
+    sig("XOR", &[B, B], BOOL),
+];
+
+/// Text literals, including whitespace, digits and non-ASCII text.
+const TEXTS: &[&str] = &[
+    "",
+    "a",
+    "abc",
+    "Hello World",
+    "  padded  ",
+    "A-B-C",
+    "42",
+    "3.5",
+    "ÄÖü",
+    "x\"y",
+];
+
+/// Number literals that tend to hit edge cases.
+const SPECIAL_NUMBERS: &[&str] = &["0", "1", "0.5", "2", "10", "100", "0.001", "1E-10", "1E+15"];
+
+/// Random formula generator over the functions of a catalog.
+pub struct Generator {
+    signatures: Vec<&'static Signature>,
+    max_depth: usize,
+}
+
+impl Generator {
+    /// Creates a generator using the grammar's functions that are in the
+    /// catalog, nesting calls up to `max_depth` deep.
+    #[must_use]
+    pub fn new(catalog: &FunctionCatalog, max_depth: usize) -> Self {
+        let signatures = SIGNATURES
+            .iter()
+            .filter(|signature| catalog.contains(signature.name))
+            .collect();
+        Self {
+            signatures,
+            max_depth: max_depth.max(1),
+        }
+    }
+
+    /// Returns the number of functions the generator can call.
+    #[must_use]
+    pub const fn function_count(&self) -> usize {
+        self.signatures.len()
+    }
+
+    /// Generates a numeric formula.
+    pub fn formula(&self, rng: &mut Rng) -> Formula {
+        let mut expr = self.expr(rng, ValueType::Number, self.max_depth);
+        // Keep the top level a call so every case exercises a function
+        if !matches!(expr, Expr::Call { .. }) {
+            if let Some(call) = self.call(rng, ValueType::Number, self.max_depth) {
+                expr = call;
+            }
+        }
+        Formula { expr }
+    }
+
+    fn expr(&self, rng: &mut Rng, ty: ValueType, depth: usize) -> Expr {
+        if depth == 0 || rng.chance(25) {
+            return literal(rng, ty);
+        }
+
+        if rng.chance(70) {
+            if let Some(call) = self.call(rng, ty, depth) {
+                return call;
+            }
+        }
+
+        match ty {
+            ValueType::Number if rng.chance(15) => Expr::Unary {
+                op: UnaryOp::Neg,
+                operand: Box::new(self.expr(rng, ty, depth - 1)),
+            },
+            ValueType::Number => {
+                let op = *rng.pick(&[
+                    BinaryOp::Add,
+                    BinaryOp::Sub,
+                    BinaryOp::Mul,
+                    BinaryOp::Div,
+                    BinaryOp::Pow,
+                ]);
+                self.binary(rng, op, ValueType::Number, depth)
+            }
+            ValueType::Text => self.binary(rng, BinaryOp::Concat, ValueType::Text, depth),
+            ValueType::Bool => {
+                let op = *rng.pick(&[
+                    BinaryOp::Eq,
+                    BinaryOp::Ne,
+                    BinaryOp::Lt,
+                    BinaryOp::Gt,
+                    BinaryOp::Le,
+                    BinaryOp::Ge,
+                ]);
+                self.binary(rng, op, ValueType::Number, depth)
+            }
+        }
+    }
+
+    fn binary(&self, rng: &mut Rng, op: BinaryOp, operands: ValueType, depth: usize) -> Expr {
+        Expr::Binary {
+            op,
+            left: Box::new(self.expr(rng, operands, depth - 1)),
+            right: Box::new(self.expr(rng, operands, depth - 1)),
+        }
+    }
+
+    fn call(&self, rng: &mut Rng, ty: ValueType, depth: usize) -> Option<Expr> {
+        let candidates: Vec<&Signature> = self
+            .signatures
+            .iter()
+            .copied()
+            .filter(|signature| signature.returns == ty)
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let signature = *rng.pick(&candidates);
+        let mut args = Vec::new();
+        for arg in signature.args {
+            match arg {
+                Arg::Number => args.push(self.expr(rng, ValueType::Number, depth - 1)),
+                Arg::Count => args.push(count_literal(rng)),
+                Arg::Text => args.push(self.expr(rng, ValueType::Text, depth - 1)),
+                Arg::Bool => args.push(self.expr(rng, ValueType::Bool, depth - 1)),
+                Arg::Numbers => {
+                    for _ in 0..=rng.below(4) {
+                        args.push(self.expr(rng, ValueType::Number, depth - 1));
+                    }
+                }
+            }
+        }
+
+        Some(Expr::Call {
+            name: signature.name.to_string(),
+            args,
+        })
+    }
+}
+
+fn literal(rng: &mut Rng, ty: ValueType) -> Expr {
+    match ty {
+        ValueType::Number => {
+            let text = match rng.below(3) {
+                0 => (*rng.pick(SPECIAL_NUMBERS)).to_string(),
+                1 => rng.below(200).to_string(),
+                _ => format!("{}.{:02}", rng.below(1000), rng.below(100)),
+            };
+            let number = Expr::Number(text);
+            if rng.chance(20) {
+                Expr::Unary {
+                    op: UnaryOp::Neg,
+                    operand: Box::new(number),
+                }
+            } else {
+                number
+            }
+        }
+        ValueType::Text => Expr::Text((*rng.pick(TEXTS)).to_string()),
+        ValueType::Bool => Expr::Bool(rng.chance(50)),
+    }
+}
+
+fn count_literal(rng: &mut Rng) -> Expr {
+    Expr::Number(rng.below(13).to_string())
+}
+
+/// Returns `true` if forge's and Gnumeric's values agree.
+///
+/// Numbers agree within a relative `tolerance`; errors agree regardless
+/// of their kind, since error codes differ between engines for the same
+/// failure.
+#[must_use]
+pub fn values_agree(forge: &CellValue, gnumeric: &CellValue, tolerance: f64) -> bool {
+    match (forge, gnumeric) {
+        (CellValue::Number(a), CellValue::Number(b)) => {
+            (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1.0)
+        }
+        (CellValue::Error(_), CellValue::Error(_)) => true,
+        (a, b) => a == b,
+    }
+}
+
+/// How forge and Gnumeric disagree on a formula.
+///
+/// Two results disagree the same way only if they fail in the same step or
+/// yield the same error codes, so a minimized formula keeps the original
+/// failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Disagreement {
+    /// Both evaluated the formula, to different values.
+    Values {
+        /// Forge's error code, if it returned one.
+        forge_error: Option<String>,
+        /// Gnumeric's error code, if it returned one.
+        gnumeric_error: Option<String>,
+    },
+    /// The formula could not be evaluated (e.g., forge rejected it): the
+    /// failing step, i.e. the error message up to its first `:`.
+    Evaluation(String),
+}
+
+/// Classifies the result of [`TestRunner::evaluate_formula`], returning
+/// `None` when forge and Gnumeric agree.
+///
+/// [`TestRunner::evaluate_formula`]: crate::runner::TestRunner::evaluate_formula
+#[must_use]
+pub fn disagreement(
+    result: &Result<(CellValue, CellValue), String>,
+    tolerance: f64,
+) -> Option<Disagreement> {
+    let error_code = |value: &CellValue| match value {
+        CellValue::Error(code) => Some(code.clone()),
+        _ => None,
+    };
+    match result {
+        Ok((forge, gnumeric)) if values_agree(forge, gnumeric, tolerance) => None,
+        Ok((forge, gnumeric)) => Some(Disagreement::Values {
+            forge_error: error_code(forge),
+            gnumeric_error: error_code(gnumeric),
+        }),
+        Err(e) => {
+            let line = e.lines().next().unwrap_or_default();
+            let step = line.split_once(':').map_or(line, |(step, _)| step);
+            Some(Disagreement::Evaluation(step.trim().to_string()))
+        }
+    }
+}
+
+/// Formats a cell value for reports.
+#[must_use]
+pub fn describe(value: &CellValue) -> String {
+    match value {
+        CellValue::Empty => "(empty)".to_string(),
+        CellValue::Number(n) => n.to_string(),
+        CellValue::Text(s) => format!("\"{s}\""),
+        CellValue::Bool(b) => b.to_string().to_uppercase(),
+        CellValue::Error(e) => e.clone(),
+    }
+}
+
+/// A formula on which forge and Gnumeric disagree.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    /// Seed of the run.
+    pub seed: u64,
+    /// Index of the case within the run.
+    pub case: usize,
+    /// The formula.
+    pub formula: String,
+    /// Forge's result (or why there is none).
+    pub forge: Result<CellValue, String>,
+    /// Gnumeric's result, if recalculation got that far.
+    pub gnumeric: Option<CellValue>,
+}
+
+#[derive(Serialize)]
+struct FindingSpec<'a> {
+    #[serde(rename = "_forge_version")]
+    forge_version: &'a str,
+    assumptions: BTreeMap<String, FindingTest<'a>>,
+}
+
+#[derive(Serialize)]
+struct FindingTest<'a> {
+    formula: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skip: Option<String>,
+}
+
+impl Finding {
+    /// Returns the test name used in the saved spec.
+    #[must_use]
+    pub fn test_name(&self) -> String {
+        format!("test_fuzz_{}_{}", self.seed, self.case)
+    }
+
+    /// Renders the finding as a YAML spec.
+    ///
+    /// When Gnumeric produced a number it becomes the expected value;
+    /// otherwise the test is a `skip` describing the disagreement.
+    pub fn to_spec_yaml(&self) -> Result<String, String> {
+        let forge = match &self.forge {
+            Ok(value) => describe(value),
+            Err(e) => e.lines().next().unwrap_or_default().to_string(),
+        };
+        let gnumeric = self
+            .gnumeric
+            .as_ref()
+            .map_or_else(|| "(not evaluated)".to_string(), describe);
+
+        let (expected, skip) = match &self.gnumeric {
+            Some(CellValue::Number(n)) if self.forge.is_ok() => (Some(*n), None),
+            _ => (
+                None,
+                Some(format!(
+                    "fuzz: forge gives {forge}, Gnumeric gives {gnumeric}"
+                )),
+            ),
+        };
+
+        let spec = FindingSpec {
+            forge_version: "1.0.0",
+            assumptions: BTreeMap::from([(
+                self.test_name(),
+                FindingTest {
+                    formula: &self.formula,
+                    expected,
+                    skip,
+                },
+            )]),
+        };
+        let yaml = serde_yaml_ng::to_string(&spec)
+            .map_err(|e| format!("Failed to serialize finding: {e}"))?;
+
+        Ok(format!(
+            "# Found by `forge-e2e-gnumeric fuzz --seed {}` (case {})\n# forge: {forge}\n# Gnumeric: {gnumeric}\n{yaml}",
+            self.seed, self.case
+        ))
+    }
+
+    /// Saves the finding as `<dir>/fuzz_<seed>_<case>.yaml`.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        let path = dir.join(format!("fuzz_{}_{}.yaml", self.seed, self.case));
+        fs::write(&path, self.to_spec_yaml()?)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{extract_skip_cases, extract_test_cases, TestSpec};
+
+    fn catalog() -> FunctionCatalog {
+        FunctionCatalog::parse(&fs::read_to_string("tests/forge-functions.txt").unwrap())
+    }
+
+    #[test]
+    fn generation_is_reproducible_and_parses() {
+        let generator = Generator::new(&catalog(), 3);
+        assert_eq!(generator.function_count(), SIGNATURES.len());
+
+        let run = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..50)
+                .map(|_| generator.formula(&mut rng).to_string())
+                .collect::<Vec<_>>()
+        };
+        let first = run(7);
+        assert_eq!(first, run(7));
+        assert_ne!(first, run(8));
+
+        for text in &first {
+            let formula = Formula::parse(text).unwrap();
+            assert!(matches!(formula.expr, Expr::Call { .. }), "{text}");
+        }
+    }
+
+    #[test]
+    fn generator_only_calls_catalog_functions() {
+        let generator = Generator::new(&FunctionCatalog::parse("ABS\nLEN\n"), 4);
+        let mut rng = Rng::new(1);
+        for _ in 0..50 {
+            for (name, _) in generator.formula(&mut rng).calls() {
+                assert!(name == "ABS" || name == "LEN", "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn values_agree_within_tolerance() {
+        let n = CellValue::Number;
+        assert!(values_agree(&n(1e6), &n(1e6 + 1e-4), 1e-9));
+        assert!(!values_agree(&n(1.0), &n(1.001), 1e-9));
+        assert!(values_agree(
+            &CellValue::Error("#NUM!".into()),
+            &CellValue::Error("#VALUE!".into()),
+            1e-9
+        ));
+        assert!(!values_agree(&CellValue::Empty, &n(0.0), 1e-9));
+    }
+
+    #[test]
+    fn disagreements_are_classified() {
+        let agree = Ok((CellValue::Number(2.0), CellValue::Number(2.0)));
+        let differ = Ok((CellValue::Number(2.0), CellValue::Error("#NUM!".into())));
+        assert_eq!(disagreement(&agree, 1e-9), None);
+        assert_eq!(
+            disagreement(&differ, 1e-9),
+            Some(Disagreement::Values {
+                forge_error: None,
+                gnumeric_error: Some("#NUM!".to_string()),
+            })
+        );
+        let other_error = Ok((CellValue::Number(2.0), CellValue::Error("#VALUE!".into())));
+        assert_ne!(
+            disagreement(&differ, 1e-9),
+            disagreement(&other_error, 1e-9)
+        );
+
+        // Evaluation failures compare by failing step, not by their details
+        let export = disagreement(&Err("forge export failed: bad `=X(1)`".into()), 1e-9);
+        assert_eq!(
+            export,
+            Some(Disagreement::Evaluation("forge export failed".to_string()))
+        );
+        assert_eq!(
+            export,
+            disagreement(&Err("forge export failed: bad `=X()`".into()), 1e-9)
+        );
+        assert_ne!(
+            export,
+            disagreement(&Err("CSV conversion failed: timeout".into()), 1e-9)
+        );
+    }
+
+    #[test]
+    fn findings_become_loadable_specs() {
+        let finding = Finding {
+            seed: 42,
+            case: 3,
+            formula: "=ROUND(LEN(\"x\"\"y\") / 3, 2)".to_string(),
+            forge: Ok(CellValue::Number(1.0)),
+            gnumeric: Some(CellValue::Number(0.67)),
+        };
+        let spec: TestSpec = serde_yaml_ng::from_str(&finding.to_spec_yaml().unwrap()).unwrap();
+        let cases = extract_test_cases(&spec, None).unwrap();
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].name, "assumptions.test_fuzz_42_3");
+        assert_eq!(cases[0].formula, finding.formula);
+        assert!((cases[0].expected - 0.67).abs() < f64::EPSILON);
+
+        let rejected = Finding {
+            forge: Err("forge export failed: unknown function".to_string()),
+            gnumeric: None,
+            ..finding
+        };
+        let spec: TestSpec = serde_yaml_ng::from_str(&rejected.to_spec_yaml().unwrap()).unwrap();
+        let skips = extract_skip_cases(&spec, None);
+        assert_eq!(skips.len(), 1);
+        assert!(skips[0].reason.contains("forge export failed"));
+    }
+}
@@ -3,7 +3,17 @@
 //! Validates Excel-compatible functions by comparing forge output
 //! against Gnumeric (via ssconvert) at runtime.
 
+pub mod config;
+pub mod coverage;
+pub mod dates;
+pub mod divergence;
 pub mod engine;
 pub mod excel;
+pub mod formula;
+pub mod fuzz;
+pub mod json;
+pub mod minimize;
+pub mod model;
 pub mod runner;
+pub mod stats;
 pub mod types;
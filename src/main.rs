@@ -3,41 +3,241 @@
 //! Validates forge against Gnumeric (Excel-compatible functions).
 //! Outputs results in TAP (Test Anything Protocol) version 14 format.
 
+use std::fs;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use serde::Serialize;
 
-use forge_e2e_gnumeric::engine::GnumericEngine;
-use forge_e2e_gnumeric::runner::TestRunner;
-use forge_e2e_gnumeric::types::TestResult;
+use forge_e2e_gnumeric::config::{check_tolerance, Config, CONFIG_FILE};
+use forge_e2e_gnumeric::coverage::{CoverageReport, FunctionCatalog};
+use forge_e2e_gnumeric::divergence::DivergenceRegistry;
+use forge_e2e_gnumeric::engine::{EngineConfig, EngineKind, GnumericEngine};
+use forge_e2e_gnumeric::formula::Formula;
+use forge_e2e_gnumeric::fuzz::{describe, disagreement, Finding, Generator, Rng};
+use forge_e2e_gnumeric::json;
+use forge_e2e_gnumeric::minimize::minimize;
+use forge_e2e_gnumeric::runner::{Artifacts, KeepArtifacts, TagFilter, TestRunner};
+use forge_e2e_gnumeric::types::{GnumericVersion, TestResult, Timings, ValueKind};
 
 #[derive(Parser)]
 #[command(name = "forge-e2e-gnumeric")]
 #[command(about = "E2E validation of forge against Gnumeric")]
 #[command(version)]
+#[allow(clippy::struct_excessive_bools)]
 struct Cli {
-    /// Path to test specs directory.
-    #[arg(short, long, default_value = "tests")]
-    tests: PathBuf,
+    /// Run configuration file (default: `forge-e2e.toml` if present).
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Path to test specs directory (default: `tests`).
+    #[arg(short, long)]
+    tests: Option<PathBuf>,
 
     /// Path to forge binary (or set `FORGE_BIN` env var).
     #[arg(short, long)]
     binary: Option<PathBuf>,
 
     /// Use batch mode (single XLSX, faster).
-    #[arg(long)]
+    #[arg(long, overrides_with = "no_batch")]
     batch: bool,
+
+    /// Run each test in its own XLSX, even if the config enables batch mode.
+    #[arg(long, overrides_with = "batch")]
+    no_batch: bool,
+
+    /// Fail the run when a test marked `todo` unexpectedly passes.
+    #[arg(long, overrides_with = "no_strict")]
+    strict: bool,
+
+    /// Let unexpected passes through, even if the config enables strict mode.
+    #[arg(long, overrides_with = "strict")]
+    no_strict: bool,
+
+    /// Largest absolute difference from the expected value that passes
+    /// (spec tests; `fuzz` and `minimize` take `--relative-tolerance`).
+    #[arg(long, value_name = "TOLERANCE", value_parser = parse_tolerance)]
+    tolerance: Option<f64>,
+
+    /// Known-divergence registry (default: `divergences.yaml` in the tests
+    /// directory).
+    #[arg(long, value_name = "PATH")]
+    divergences: Option<PathBuf>,
+
+    /// Keep the generated YAML, XLSX and CSV files of `failed` (default) or
+    /// `all` tests.
+    #[arg(
+        long,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "failed"
+    )]
+    keep_artifacts: Option<KeepArtifacts>,
+
+    /// Directory for kept artifacts, one subdirectory per test (default:
+    /// `e2e-artifacts`).
+    #[arg(long, value_name = "PATH")]
+    artifacts_dir: Option<PathBuf>,
+
+    /// ssconvert binary (default: `ssconvert` on `PATH`).
+    #[arg(long, value_name = "PATH")]
+    ssconvert: Option<PathBuf>,
+
+    /// Extra ssconvert argument (repeatable).
+    #[arg(long = "ssconvert-arg", value_name = "ARG", allow_hyphen_values = true)]
+    ssconvert_args: Vec<String>,
+
+    /// ssconvert importer option (repeatable).
+    #[arg(long = "import-option", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    import_options: Vec<(String, String)>,
+
+    /// ssconvert CSV exporter option, e.g. `locale=C` (repeatable).
+    #[arg(long = "export-option", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    export_options: Vec<(String, String)>,
+
+    /// Environment variable for ssconvert, e.g. `LC_ALL=C` (repeatable).
+    #[arg(long = "ssconvert-env", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    ssconvert_env: Vec<(String, String)>,
+
+    /// Minimum ssconvert version (e.g. `1.12.50`).
+    #[arg(long, value_name = "VERSION")]
+    min_ssconvert_version: Option<GnumericVersion>,
+
+    /// Report the N slowest tests and files, with per-stage timings.
+    #[arg(long, value_name = "N")]
+    slowest: Option<usize>,
+
+    /// Write every result, with its stage timings, to a JSON file.
+    #[arg(long, value_name = "PATH")]
+    json: Option<PathBuf>,
+
+    /// Number of spec files run at the same time (default: 1).
+    #[arg(short = 'j', long, value_name = "N")]
+    parallel: Option<NonZeroUsize>,
+
+    /// Run only spec files with this `_tags` entry (repeatable).
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// Skip spec files with this `_tags` entry (repeatable).
+    #[arg(long = "skip-tag", value_name = "TAG")]
+    skip_tags: Vec<String>,
+
+    /// Engine recalculating the workbooks (default: `gnumeric`).
+    #[arg(long, value_name = "NAME")]
+    engine: Option<EngineKind>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Report function coverage against forge's function catalog.
+    Coverage {
+        /// Function manifest (one forge function per line).
+        #[arg(long, default_value = "tests/forge-functions.txt")]
+        manifest: PathBuf,
+    },
+    /// Compare forge and Gnumeric on randomly generated formulas.
+    Fuzz {
+        /// Random seed (defaults to one derived from the clock).
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Number of formulas to generate.
+        #[arg(long, default_value_t = 100)]
+        count: usize,
+
+        /// Maximum nesting depth of generated formulas.
+        #[arg(long, default_value_t = 3)]
+        depth: usize,
+
+        /// Relative tolerance for comparing numbers (the top-level
+        /// `--tolerance` is absolute and applies to spec tests only).
+        #[arg(long, default_value_t = 1e-9, value_parser = parse_tolerance)]
+        relative_tolerance: f64,
+
+        /// Function manifest limiting which functions are generated.
+        #[arg(long, default_value = "tests/forge-functions.txt")]
+        manifest: PathBuf,
+
+        /// Directory for specs of disagreements.
+        #[arg(long, default_value = "fuzz-findings")]
+        out: PathBuf,
+    },
+    /// Shrink a formula on which forge and Gnumeric disagree.
+    Minimize {
+        /// Formula (starting with `=`) or name of a loaded test.
+        target: String,
+
+        /// Maximum number of candidate formulas to evaluate.
+        #[arg(long, default_value_t = 500)]
+        max_evaluations: usize,
+
+        /// Relative tolerance for comparing numbers (the top-level
+        /// `--tolerance` is absolute and applies to spec tests only).
+        #[arg(long, default_value_t = 1e-9, value_parser = parse_tolerance)]
+        relative_tolerance: f64,
+    },
+}
+
+impl Cli {
+    /// Fills settings not given on the command line from the config file.
+    fn apply_config(&mut self, config: &Config) {
+        self.tests = self.tests.take().or_else(|| config.tests.clone());
+        self.batch = !self.no_batch && (self.batch || config.batch);
+        self.strict = !self.no_strict && (self.strict || config.strict);
+        self.tolerance = self.tolerance.or(config.tolerance);
+        self.divergences = self
+            .divergences
+            .take()
+            .or_else(|| config.divergences.clone());
+        self.keep_artifacts = self.keep_artifacts.or(config.keep_artifacts);
+        self.artifacts_dir = self
+            .artifacts_dir
+            .take()
+            .or_else(|| config.artifacts_dir.clone());
+        self.slowest = self.slowest.or(config.slowest);
+        self.json = self.json.take().or_else(|| config.json.clone());
+        self.parallel = self.parallel.or(config.parallel);
+        if self.tags.is_empty() {
+            self.tags.clone_from(&config.tags);
+        }
+        if self.skip_tags.is_empty() {
+            self.skip_tags.clone_from(&config.skip_tags);
+        }
+    }
+
+    fn tests_dir(&self) -> &Path {
+        self.tests.as_deref().unwrap_or_else(|| Path::new("tests"))
+    }
+
+    fn artifacts_dir(&self) -> &Path {
+        self.artifacts_dir
+            .as_deref()
+            .unwrap_or_else(|| Path::new("e2e-artifacts"))
+    }
 }
 
 fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    let config = Config::discover(cli.config.as_deref())?;
+    cli.apply_config(&config);
+
+    if let Some(Commands::Coverage { manifest }) = &cli.command {
+        return run_coverage(&cli, manifest);
+    }
 
     // Find forge binary
     let forge_binary = cli
         .binary
         .clone()
         .or_else(|| std::env::var("FORGE_BIN").ok().map(PathBuf::from))
+        .or_else(|| config.forge.clone())
         .or_else(|| {
             let relative = PathBuf::from("../forge/target/release/forge");
             if relative.exists() {
@@ -48,7 +248,7 @@ fn main() -> anyhow::Result<()> {
         })
         .ok_or_else(|| {
             anyhow::anyhow!(
-                "Forge binary not found. Set FORGE_BIN or use --binary, or build forge at ../forge/"
+                "Forge binary not found. Set FORGE_BIN, use --binary, set `forge` in {CONFIG_FILE}, or build forge at ../forge/"
             )
         })?;
 
@@ -56,75 +256,625 @@ fn main() -> anyhow::Result<()> {
         anyhow::bail!("Forge binary not found: {}", forge_binary.display());
     }
 
-    // Detect Gnumeric
-    let engine = GnumericEngine::detect().ok_or_else(|| {
-        anyhow::anyhow!(
-            "Gnumeric (ssconvert) not found. Install with:\n  macOS: brew install gnumeric\n  Ubuntu: apt install gnumeric"
-        )
+    // Detect the engine
+    let engine_config = engine_config(&cli, config.engine);
+    let engine = match engine_config.name {
+        EngineKind::Gnumeric => GnumericEngine::with_config(&engine_config),
+    }
+    .map_err(|e| {
+        if engine_config.ssconvert.is_some() {
+            anyhow::anyhow!("{e}")
+        } else {
+            anyhow::anyhow!(
+                "{e}\nGnumeric (ssconvert) not found. Install with:\n  macOS: brew install gnumeric\n  Ubuntu: apt install gnumeric"
+            )
+        }
     })?;
 
     // Create runner and execute
-    let runner = TestRunner::new(forge_binary.clone(), engine, cli.tests.clone())?;
-    run_all(&cli, &runner, &forge_binary);
+    let mut runner = TestRunner::new(forge_binary.clone(), engine, cli.tests_dir().to_path_buf())?;
+    if let Some(keep) = cli.keep_artifacts {
+        runner = runner.with_artifacts(Artifacts {
+            dir: cli.artifacts_dir().to_path_buf(),
+            keep,
+        });
+    }
+    if let Some(tolerance) = cli.tolerance {
+        runner = runner.with_tolerance(tolerance);
+    }
+    if let Some(path) = &cli.divergences {
+        runner = runner.with_divergences(DivergenceRegistry::load(path)?);
+    }
+    if !(cli.tags.is_empty() && cli.skip_tags.is_empty()) {
+        runner = runner.with_tag_filter(&TagFilter {
+            tags: cli.tags.clone(),
+            skip_tags: cli.skip_tags.clone(),
+        });
+    }
+    if let Some(Commands::Fuzz {
+        seed,
+        count,
+        depth,
+        relative_tolerance,
+        manifest,
+        out,
+    }) = &cli.command
+    {
+        let seed = seed.unwrap_or_else(clock_seed);
+        let options = FuzzOptions {
+            seed,
+            count: *count,
+            depth: *depth,
+            relative_tolerance: *relative_tolerance,
+            manifest,
+            out,
+        };
+        return run_fuzz(&runner, &forge_binary, &options);
+    }
+    if let Some(Commands::Minimize {
+        target,
+        max_evaluations,
+        relative_tolerance,
+    }) = &cli.command
+    {
+        return run_minimize(&runner, target, *max_evaluations, *relative_tolerance);
+    }
+    run_all(&cli, &runner, &forge_binary)
+}
 
-    Ok(())
+/// Parses a comparison tolerance: a finite number of at least 0.
+fn parse_tolerance(arg: &str) -> Result<f64, String> {
+    let tolerance = arg
+        .parse::<f64>()
+        .map_err(|e| format!("invalid number `{arg}`: {e}"))?;
+    check_tolerance(tolerance)
+}
+
+/// Parses a `KEY=VALUE` argument.
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{arg}`"))
+}
+
+/// Builds the ssconvert configuration: the config file's `[engine]`
+/// table with the command line's options on top.
+fn engine_config(cli: &Cli, mut config: EngineConfig) -> EngineConfig {
+    config.name = cli.engine.unwrap_or(config.name);
+    if let Some(ssconvert) = &cli.ssconvert {
+        config.ssconvert = Some(ssconvert.clone());
+    }
+    if !cli.ssconvert_args.is_empty() {
+        config.args.clone_from(&cli.ssconvert_args);
+    }
+    config
+        .import_options
+        .extend(cli.import_options.iter().cloned());
+    config
+        .export_options
+        .extend(cli.export_options.iter().cloned());
+    config.env.extend(cli.ssconvert_env.iter().cloned());
+    config.min_version = cli.min_ssconvert_version.or(config.min_version);
+    config
+}
+
+/// Indentation of a TAP 14 subtest.
+const SUBTEST_INDENT: &str = "    ";
+
+/// The detected ssconvert version, or `unknown` if its output had none.
+fn engine_version(engine: &GnumericEngine) -> String {
+    engine
+        .version()
+        .map_or_else(|| "unknown".to_string(), |version| version.to_string())
 }
 
-fn run_all(cli: &Cli, runner: &TestRunner, forge_binary: &Path) {
-    let total = runner.total_tests();
+fn run_all(cli: &Cli, runner: &TestRunner, forge_binary: &Path) -> anyhow::Result<()> {
+    let files = runner.files();
     let mode = if cli.batch { "batch" } else { "streaming" };
 
-    // TAP header: diagnostic comments then version and plan
+    // TAP header: diagnostic comments then version and plan (one subtest per file)
     println!("# forge-e2e-gnumeric");
     println!("# Forge: {}", forge_binary.display());
-    println!(
-        "# Engine: {} (ssconvert)",
-        GnumericEngine::name()
-    );
-    println!("# Tests: {}", cli.tests.display());
+    println!("# Engine: {} (ssconvert)", GnumericEngine::name());
+    println!("# Engine version: {}", engine_version(runner.engine()));
+    println!("# Tests: {}", cli.tests_dir().display());
     println!("# Mode: {mode}");
+    if let Some(jobs) = cli.parallel.filter(|jobs| jobs.get() > 1) {
+        println!("# Parallel: {jobs} files at a time");
+    }
+    if !cli.tags.is_empty() {
+        println!("# Tags: {}", cli.tags.join(", "));
+    }
+    if !cli.skip_tags.is_empty() {
+        println!("# Skipped tags: {}", cli.skip_tags.join(", "));
+    }
+    if let Some(keep) = cli.keep_artifacts {
+        println!("# Artifacts: {} ({keep:?})", cli.artifacts_dir().display());
+    }
+    println!("# {} tests in {} files", runner.total_tests(), files.len());
     println!("TAP version 14");
-    println!("1..{total}");
+    println!("1..{}", files.len());
 
     let start = Instant::now();
+    let mut run = RunResults::default();
 
-    let results = if cli.batch {
-        let results = runner.run_batch();
-        for (n, result) in results.iter().enumerate() {
-            print_tap_line(n + 1, result);
+    if cli.batch {
+        for (n, group) in runner.run_batch().into_iter().enumerate() {
+            let name = file_name(cli, &group.file);
+            print_subtest(n + 1, &name, &group.results, cli.strict);
+            run.add_file(name, group.results);
         }
-        results
+    } else if let Some(jobs) = cli.parallel.filter(|jobs| jobs.get() > 1) {
+        // Results arrive a whole file at a time, in file order
+        runner.run_files_parallel(jobs, |group| {
+            let name = file_name(cli, &group.file);
+            print_subtest(run.file_times.len() + 1, &name, &group.results, cli.strict);
+            run.add_file(name, group.results);
+        });
     } else {
-        let mut n: usize = 1;
-        runner.run_all_streaming(|result| {
-            print_tap_line(n, result);
-            n += 1;
-        })
-    };
+        for (n, file) in files.iter().enumerate() {
+            let name = file_name(cli, file);
+            print_subtest_header(&name, runner.file_tests(file));
+            let mut i: usize = 1;
+            let file_results = runner.run_file_streaming(file, |result| {
+                print_tap_line(SUBTEST_INDENT, i, result);
+                i += 1;
+            });
+            print_subtest_end(n + 1, &name, &file_results, cli.strict);
+            run.add_file(name, file_results);
+        }
+    }
+    let RunResults {
+        results,
+        files: result_files,
+        file_times,
+    } = run;
 
     let elapsed = start.elapsed();
+    let summary = Summary::of(&results);
+
+    println!("# {summary} in {:.2}s", elapsed.as_secs_f64());
+    for name in &summary.unexpected {
+        println!("# unexpected pass (remove its todo): {name}");
+    }
+    if let Some(count) = cli.slowest {
+        print_slowest(count, &results, file_times);
+    }
+    if let Some(path) = &cli.json {
+        let report = JsonReport {
+            forge: forge_binary,
+            engine_version: engine_version(runner.engine()),
+            elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+            passed: summary.passed,
+            failed: summary.failed,
+            todo: summary.todo,
+            unexpected: &summary.unexpected,
+            skipped: summary.skipped,
+            results: result_files
+                .iter()
+                .zip(&results)
+                .map(|(file, result)| JsonResult { file, result })
+                .collect(),
+        };
+        let json = json::to_string(&report)?;
+        fs::write(path, json + "\n")
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", path.display()))?;
+        println!("# JSON report: {}", path.display());
+    }
+
+    if !summary.is_ok(cli.strict) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
 
-    let passed = results.iter().filter(|r| r.is_pass()).count();
-    let failed = results.iter().filter(|r| r.is_fail()).count();
-    let skipped = results
+/// Results of a run, kept for the summary, `--slowest` and `--json`.
+#[derive(Default)]
+struct RunResults {
+    /// Every result, in file order.
+    results: Vec<TestResult>,
+    /// Spec file name of each result.
+    files: Vec<String>,
+    /// Total stage time of each file.
+    file_times: Vec<(String, Duration)>,
+}
+
+impl RunResults {
+    fn add_file(&mut self, name: String, results: Vec<TestResult>) {
+        self.files
+            .extend(std::iter::repeat_n(name.clone(), results.len()));
+        self.file_times.push((name, total_time(&results)));
+        self.results.extend(results);
+    }
+}
+
+/// The `--json` report: run summary and every result with its timings.
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    forge: &'a Path,
+    engine_version: String,
+    elapsed_ms: f64,
+    passed: usize,
+    failed: usize,
+    todo: usize,
+    unexpected: &'a [&'a str],
+    skipped: usize,
+    results: Vec<JsonResult<'a>>,
+}
+
+/// A result in the `--json` report, with the spec file it came from.
+#[derive(Serialize)]
+struct JsonResult<'a> {
+    file: &'a str,
+    #[serde(flatten)]
+    result: &'a TestResult,
+}
+
+/// Total stage time of a set of results.
+fn total_time(results: &[TestResult]) -> Duration {
+    results
+        .iter()
+        .filter_map(TestResult::timings)
+        .map(Timings::total)
+        .sum()
+}
+
+/// Prints the `count` slowest tests and files as TAP comments.
+fn print_slowest(count: usize, results: &[TestResult], mut file_times: Vec<(String, Duration)>) {
+    let mut tests: Vec<(&str, &Timings)> = results
         .iter()
-        .filter(|r| matches!(r, TestResult::Skip { .. }))
-        .count();
+        .filter_map(|r| r.timings().map(|timings| (r.name(), timings)))
+        .collect();
+    tests.sort_by_key(|(_, timings)| std::cmp::Reverse(timings.total()));
+
+    println!("# Slowest tests:");
+    for (name, timings) in tests.iter().take(count) {
+        println!(
+            "#   {:>8.3}s {name} (yaml {:.3}s, export {:.3}s, recalc {:.3}s, parse {:.3}s)",
+            timings.total().as_secs_f64(),
+            timings.yaml.as_secs_f64(),
+            timings.export.as_secs_f64(),
+            timings.recalc.as_secs_f64(),
+            timings.parse.as_secs_f64()
+        );
+    }
+
+    file_times.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+    println!("# Slowest files:");
+    for (name, time) in file_times.iter().take(count) {
+        println!("#   {:>8.3}s {name}", time.as_secs_f64());
+    }
+}
+
+/// Prints stage timings inside a TAP YAML diagnostic block.
+fn print_timings(indent: &str, timings: &Timings) {
+    println!("{indent}  timings_ms:");
+    for (stage, duration) in [
+        ("yaml", timings.yaml),
+        ("export", timings.export),
+        ("recalc", timings.recalc),
+        ("parse", timings.parse),
+    ] {
+        println!(
+            "{indent}    {stage}: {:.1}",
+            duration.as_secs_f64() * 1000.0
+        );
+    }
+}
+
+/// Prints a value inside a TAP YAML diagnostic block, followed by its ISO
+/// form (`expected_iso`) when the test expects a date.
+fn print_value(indent: &str, key: &str, value: f64, kind: ValueKind) {
+    println!("{indent}  {key}: {value}");
+    if let Some(iso) = kind.render(value) {
+        println!("{indent}  {key}_iso: \"{iso}\"");
+    }
+}
+
+/// Prints the expected value, or the assertions of a volatile test.
+fn print_expected(indent: &str, expected: f64, kind: ValueKind, assertion: Option<&str>) {
+    match assertion {
+        Some(assertion) => println!("{indent}  expected: \"{assertion}\""),
+        None => print_value(indent, "expected", expected, kind),
+    }
+}
+
+/// Prints the kept artifacts inside a TAP YAML diagnostic block.
+fn print_artifacts(indent: &str, artifacts: Option<&Path>) {
+    if let Some(dir) = artifacts {
+        println!("{indent}  artifacts: \"{}\"", dir.display());
+        println!("{indent}  repro: \"{}\"", dir.join("repro.sh").display());
+    }
+}
+
+/// Name of a spec file relative to the tests directory.
+fn file_name(cli: &Cli, file: &Path) -> String {
+    file.strip_prefix(cli.tests_dir())
+        .unwrap_or(file)
+        .display()
+        .to_string()
+}
+
+/// Prints a whole subtest: header, result lines and summary.
+fn print_subtest(n: usize, name: &str, results: &[TestResult], strict: bool) {
+    print_subtest_header(name, results.len());
+    for (i, result) in results.iter().enumerate() {
+        print_tap_line(SUBTEST_INDENT, i + 1, result);
+    }
+    print_subtest_end(n, name, results, strict);
+}
+
+fn print_subtest_header(name: &str, total: usize) {
+    println!("# Subtest: {name}");
+    println!("{SUBTEST_INDENT}1..{total}");
+}
+
+/// Prints a subtest's summary and its result line in the parent.
+fn print_subtest_end(n: usize, name: &str, results: &[TestResult], strict: bool) {
+    let summary = Summary::of(results);
+    println!("{SUBTEST_INDENT}# {summary}");
+    let status = if summary.is_ok(strict) {
+        "ok"
+    } else {
+        "not ok"
+    };
+    println!("{status} {n} - {name}");
+}
+
+/// Counts of a set of results.
+struct Summary<'a> {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    todo: usize,
+    /// Names of tests marked as known failures that passed.
+    unexpected: Vec<&'a str>,
+}
+
+impl<'a> Summary<'a> {
+    fn of(results: &'a [TestResult]) -> Self {
+        Self {
+            passed: results.iter().filter(|r| r.is_pass()).count(),
+            failed: results.iter().filter(|r| r.is_fail()).count(),
+            skipped: results
+                .iter()
+                .filter(|r| matches!(r, TestResult::Skip { .. }))
+                .count(),
+            todo: results
+                .iter()
+                .filter(|r| matches!(r, TestResult::Todo { .. }))
+                .count(),
+            unexpected: results
+                .iter()
+                .filter(|r| r.is_unexpected_pass())
+                .map(TestResult::name)
+                .collect(),
+        }
+    }
+
+    /// Returns `true` unless a test failed (or, when strict, a known
+    /// failure passed).
+    const fn is_ok(&self, strict: bool) -> bool {
+        self.failed == 0 && (!strict || self.unexpected.is_empty())
+    }
+}
+
+impl std::fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} passed, {} failed, {} todo ({} unexpectedly passed), {} skipped",
+            self.passed,
+            self.failed,
+            self.todo,
+            self.unexpected.len(),
+            self.skipped
+        )
+    }
+}
+
+fn run_coverage(cli: &Cli, manifest: &Path) -> anyhow::Result<()> {
+    let catalog = FunctionCatalog::load(manifest)?;
+    let suite = TestRunner::load_test_cases(cli.tests_dir())?;
+    let report = CoverageReport::build(&catalog, &suite);
+
+    println!("# forge-e2e-gnumeric coverage");
+    println!("# Manifest: {}", manifest.display());
+    println!("# Tests: {}", cli.tests_dir().display());
+    println!(
+        "# Tested: {}/{} functions ({:.1}%)",
+        report.tested,
+        report.total,
+        report.percent_tested()
+    );
+
+    print_function_list("Untested", &report.untested);
+    print_function_list(
+        "Happy-path only (no edge-case tests)",
+        &report.happy_path_only,
+    );
+    print_function_list("Only tested inside ROUND", &report.round_only);
+    print_function_list("Called by tests but not in manifest", &report.unknown);
+
+    if !report.unparsed.is_empty() {
+        println!();
+        println!("Formulas that do not parse ({}):", report.unparsed.len());
+        for (test, error) in &report.unparsed {
+            println!("  {test}: {error}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Settings of a `fuzz` run.
+struct FuzzOptions<'a> {
+    seed: u64,
+    count: usize,
+    depth: usize,
+    relative_tolerance: f64,
+    manifest: &'a Path,
+    out: &'a Path,
+}
+
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| {
+            elapsed.as_secs() ^ u64::from(elapsed.subsec_nanos())
+        })
+}
+
+fn run_fuzz(runner: &TestRunner, forge_binary: &Path, options: &FuzzOptions) -> anyhow::Result<()> {
+    let catalog = FunctionCatalog::load(options.manifest)?;
+    let generator = Generator::new(&catalog, options.depth);
+    let mut rng = Rng::new(options.seed);
+
+    println!("# forge-e2e-gnumeric fuzz");
+    println!("# Forge: {}", forge_binary.display());
+    println!("# Engine: {} (ssconvert)", GnumericEngine::name());
+    println!("# Engine version: {}", engine_version(runner.engine()));
+    println!("# Seed: {}", options.seed);
+    println!("# Functions: {}", generator.function_count());
+    println!("TAP version 14");
+    println!("1..{}", options.count);
+
+    let start = Instant::now();
+    let mut disagreements = 0;
+
+    for case in 0..options.count {
+        let formula = generator.formula(&mut rng).to_string();
+        let n = case + 1;
+
+        let result = runner.evaluate_formula(&formula, None);
+        if disagreement(&result, options.relative_tolerance).is_none() {
+            println!("ok {n} - {formula}");
+            continue;
+        }
+
+        let finding = match result {
+            Ok((forge, gnumeric)) => Finding {
+                seed: options.seed,
+                case,
+                formula,
+                forge: Ok(forge),
+                gnumeric: Some(gnumeric),
+            },
+            Err(e) => Finding {
+                seed: options.seed,
+                case,
+                formula,
+                forge: Err(e),
+                gnumeric: None,
+            },
+        };
+
+        disagreements += 1;
+        println!("not ok {n} - {}", finding.formula);
+        println!("  ---");
+        match &finding.forge {
+            Ok(value) => println!("  forge: {}", describe(value)),
+            Err(e) => println!("  error: \"{}\"", e.replace('"', "\\\"").replace('\n', " ")),
+        }
+        if let Some(value) = &finding.gnumeric {
+            println!("  gnumeric: {}", describe(value));
+        }
+        match finding.save(options.out) {
+            Ok(path) => println!("  saved: {}", path.display()),
+            Err(e) => println!("  save_error: \"{e}\""),
+        }
+        println!("  ...");
+    }
 
     println!(
-        "# {passed} passed, {failed} failed, {skipped} skipped in {:.2}s",
-        elapsed.as_secs_f64()
+        "# {} agreed, {disagreements} disagreed in {:.2}s (seed {})",
+        options.count - disagreements,
+        start.elapsed().as_secs_f64(),
+        options.seed
     );
 
-    if failed > 0 {
+    if disagreements > 0 {
         std::process::exit(1);
     }
+    Ok(())
+}
+
+fn run_minimize(
+    runner: &TestRunner,
+    target: &str,
+    max_evaluations: usize,
+    relative_tolerance: f64,
+) -> anyhow::Result<()> {
+    // A formula, or a loaded test whose formula runs next to its spec's data
+    let (text, source_file) = if target.trim_start().starts_with('=') {
+        (target.to_string(), None)
+    } else {
+        let test = runner
+            .test_cases()
+            .iter()
+            .find(|tc| tc.name == target)
+            .ok_or_else(|| anyhow::anyhow!("No formula or loaded test named `{target}`"))?;
+        (test.formula.clone(), test.source_file.as_deref())
+    };
+    let formula =
+        Formula::parse(&text).map_err(|e| anyhow::anyhow!("Cannot minimize `{text}`: {e}"))?;
+
+    let report = |result: &Result<_, String>| match result {
+        Ok((forge, gnumeric)) => format!(
+            "forge: {}, Gnumeric: {}",
+            describe(forge),
+            describe(gnumeric)
+        ),
+        Err(e) => format!("error: {}", e.lines().next().unwrap_or_default()),
+    };
+
+    let original = runner.evaluate_formula(&text, source_file);
+    let Some(kind) = disagreement(&original, relative_tolerance) else {
+        anyhow::bail!("forge and Gnumeric agree on {text} ({})", report(&original));
+    };
+
+    println!("# forge-e2e-gnumeric minimize");
+    println!("# Original: {text}");
+    println!("# {}", report(&original));
+
+    let minimized = minimize(&formula, max_evaluations, |candidate| {
+        let result = runner.evaluate_formula(&candidate.to_string(), source_file);
+        let still_fails = disagreement(&result, relative_tolerance).as_ref() == Some(&kind);
+        if still_fails {
+            println!("# Reduced: {candidate}");
+        }
+        still_fails
+    });
+
+    let result = runner.evaluate_formula(&minimized.formula.to_string(), source_file);
+    println!(
+        "# {} reductions in {} evaluations{}",
+        minimized.steps.len(),
+        minimized.evaluations,
+        if minimized.exhausted {
+            " (budget exhausted)"
+        } else {
+            ""
+        }
+    );
+    println!("# {}", report(&result));
+    println!("{}", minimized.formula);
+
+    Ok(())
+}
+
+fn print_function_list(title: &str, names: &[String]) {
+    println!();
+    println!("{title} ({}):", names.len());
+    for name in names {
+        println!("  {name}");
+    }
 }
 
-fn print_tap_line(n: usize, result: &TestResult) {
+fn print_tap_line(indent: &str, n: usize, result: &TestResult) {
     match result {
         TestResult::Pass { name, .. } => {
-            println!("ok {n} - {name}");
+            println!("{indent}ok {n} - {name}");
         }
         TestResult::Fail {
             name,
@@ -132,21 +882,62 @@ fn print_tap_line(n: usize, result: &TestResult) {
             expected,
             actual,
             error,
+            timings,
+            artifacts,
+            kind,
+            assertion,
         } => {
-            println!("not ok {n} - {name}");
-            println!("  ---");
-            println!("  formula: \"{formula}\"");
-            println!("  expected: {expected}");
+            println!("{indent}not ok {n} - {name}");
+            println!("{indent}  ---");
+            println!("{indent}  formula: \"{formula}\"");
+            print_expected(indent, *expected, *kind, assertion.as_deref());
             if let Some(actual) = actual {
-                println!("  actual: {actual}");
+                print_value(indent, "actual", *actual, *kind);
             }
             if let Some(error) = error {
-                println!("  error: \"{error}\"");
+                println!("{indent}  error: \"{error}\"");
             }
-            println!("  ...");
+            print_timings(indent, timings);
+            print_artifacts(indent, artifacts.as_deref());
+            println!("{indent}  ...");
         }
         TestResult::Skip { name, reason } => {
-            println!("ok {n} - {name} # SKIP {reason}");
+            println!("{indent}ok {n} - {name} # SKIP {reason}");
+        }
+        TestResult::Todo {
+            name,
+            formula,
+            expected,
+            actual,
+            error,
+            reason,
+            excel,
+            passed,
+            timings,
+            artifacts,
+            kind,
+            assertion,
+        } => {
+            let status = if *passed { "ok" } else { "not ok" };
+            println!("{indent}{status} {n} - {name} # TODO {reason}");
+            println!("{indent}  ---");
+            println!("{indent}  formula: \"{formula}\"");
+            print_expected(indent, *expected, *kind, assertion.as_deref());
+            if let Some(actual) = actual {
+                print_value(indent, "actual", *actual, *kind);
+            }
+            if let Some(error) = error {
+                println!("{indent}  error: \"{error}\"");
+            }
+            if *passed {
+                println!("{indent}  unexpected_pass: true");
+            }
+            if let Some(excel) = excel {
+                println!("{indent}  excel: {excel}");
+            }
+            print_timings(indent, timings);
+            print_artifacts(indent, artifacts.as_deref());
+            println!("{indent}  ...");
         }
     }
 }
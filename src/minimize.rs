@@ -0,0 +1,283 @@
+//! Test-case minimization of failing formulas.
+//!
+//! Greedily shrinks a formula (unwrapping nested calls and operators,
+//! removing arguments, simplifying literals) while a caller-supplied check
+//! still reports the failure, and returns the smallest formula found.
+
+use std::collections::HashSet;
+
+use crate::formula::{Expr, Formula};
+
+/// Result of a minimization.
+#[derive(Debug, Clone)]
+pub struct Minimized {
+    /// The smallest formula that still fails.
+    pub formula: Formula,
+    /// Every accepted reduction, in order.
+    pub steps: Vec<Formula>,
+    /// Number of candidate formulas checked.
+    pub evaluations: usize,
+    /// Whether the evaluation budget ran out before no reduction was left.
+    pub exhausted: bool,
+}
+
+/// Shrinks `formula` while `still_fails` holds, checking at most
+/// `max_evaluations` candidates.
+///
+/// Only reductions that make the formula strictly smaller (fewer nodes,
+/// or as many nodes and shorter text) are tried, so minimization always
+/// terminates.
+pub fn minimize<F>(formula: &Formula, max_evaluations: usize, mut still_fails: F) -> Minimized
+where
+    F: FnMut(&Formula) -> bool,
+{
+    let mut current = formula.clone();
+    let mut steps = Vec::new();
+    let mut evaluations = 0;
+    let mut rejected = HashSet::new();
+
+    'shrink: loop {
+        let current_size = size(&current.expr);
+        for expr in reductions(&current.expr) {
+            if size(&expr) >= current_size {
+                continue;
+            }
+            let candidate = Formula { expr };
+            if !rejected.insert(candidate.to_string()) {
+                continue;
+            }
+            if evaluations == max_evaluations {
+                return Minimized {
+                    formula: current,
+                    steps,
+                    evaluations,
+                    exhausted: true,
+                };
+            }
+
+            evaluations += 1;
+            if still_fails(&candidate) {
+                steps.push(candidate.clone());
+                current = candidate;
+                continue 'shrink;
+            }
+        }
+        break;
+    }
+
+    Minimized {
+        formula: current,
+        steps,
+        evaluations,
+        exhausted: false,
+    }
+}
+
+/// Size used to order formulas: node count, then text length.
+fn size(expr: &Expr) -> (usize, usize) {
+    let mut nodes = 0;
+    expr.visit(&mut |_| nodes += 1);
+    (nodes, expr.to_string().len())
+}
+
+/// Returns every formula one reduction step away from `expr`, largest
+/// reductions first.
+#[must_use]
+pub fn reductions(expr: &Expr) -> Vec<Expr> {
+    let mut out = local_reductions(expr);
+
+    // Reduce one sub-expression in place
+    match expr {
+        Expr::Call { name, args } => {
+            for (i, arg) in args.iter().enumerate() {
+                for reduced in reductions(arg) {
+                    let mut args = args.clone();
+                    args[i] = reduced;
+                    out.push(Expr::Call {
+                        name: name.clone(),
+                        args,
+                    });
+                }
+            }
+        }
+        Expr::Invoke { callee, args } => {
+            for reduced in reductions(callee) {
+                out.push(Expr::Invoke {
+                    callee: Box::new(reduced),
+                    args: args.clone(),
+                });
+            }
+            for (i, arg) in args.iter().enumerate() {
+                for reduced in reductions(arg) {
+                    let mut args = args.clone();
+                    args[i] = reduced;
+                    out.push(Expr::Invoke {
+                        callee: callee.clone(),
+                        args,
+                    });
+                }
+            }
+        }
+        Expr::Unary { op, operand } => {
+            for reduced in reductions(operand) {
+                out.push(Expr::Unary {
+                    op: *op,
+                    operand: Box::new(reduced),
+                });
+            }
+        }
+        Expr::Percent(operand) => {
+            for reduced in reductions(operand) {
+                out.push(Expr::Percent(Box::new(reduced)));
+            }
+        }
+        Expr::Binary { op, left, right } => {
+            for reduced in reductions(left) {
+                out.push(Expr::Binary {
+                    op: *op,
+                    left: Box::new(reduced),
+                    right: right.clone(),
+                });
+            }
+            for reduced in reductions(right) {
+                out.push(Expr::Binary {
+                    op: *op,
+                    left: left.clone(),
+                    right: Box::new(reduced),
+                });
+            }
+        }
+        Expr::Array(rows) => {
+            for (r, row) in rows.iter().enumerate() {
+                for (c, item) in row.iter().enumerate() {
+                    for reduced in reductions(item) {
+                        let mut rows = rows.clone();
+                        rows[r][c] = reduced;
+                        out.push(Expr::Array(rows));
+                    }
+                }
+            }
+        }
+        Expr::Number(_)
+        | Expr::Text(_)
+        | Expr::Bool(_)
+        | Expr::Error(_)
+        | Expr::Reference(_)
+        | Expr::TableRef { .. }
+        | Expr::Empty => {}
+    }
+
+    out
+}
+
+/// Replacements of the node itself by something simpler.
+fn local_reductions(expr: &Expr) -> Vec<Expr> {
+    match expr {
+        Expr::Call { name, args } => {
+            // Unwrap the call to one of its arguments, then drop arguments
+            let mut out: Vec<Expr> = args
+                .iter()
+                .filter(|arg| !matches!(arg, Expr::Empty))
+                .cloned()
+                .collect();
+            for i in 0..args.len() {
+                let mut args = args.clone();
+                args.remove(i);
+                out.push(Expr::Call {
+                    name: name.clone(),
+                    args,
+                });
+            }
+            out
+        }
+        Expr::Invoke { args, .. } => args.clone(),
+        Expr::Unary { operand, .. } | Expr::Percent(operand) => vec![(**operand).clone()],
+        Expr::Binary { left, right, .. } => vec![(**left).clone(), (**right).clone()],
+        Expr::Array(rows) => rows.iter().flatten().cloned().collect(),
+        Expr::Number(text) => simpler_numbers(text)
+            .into_iter()
+            .map(Expr::Number)
+            .collect(),
+        Expr::Text(text) => {
+            let mut out = vec![Expr::Text(String::new())];
+            if let Some(first) = text.chars().next() {
+                out.push(Expr::Text(first.to_string()));
+            }
+            out
+        }
+        Expr::Bool(_)
+        | Expr::Error(_)
+        | Expr::Reference(_)
+        | Expr::TableRef { .. }
+        | Expr::Empty => Vec::new(),
+    }
+}
+
+/// Simpler spellings of a number literal: `0`, `1`, its integer part and
+/// the literal with one decimal digit fewer.
+fn simpler_numbers(text: &str) -> Vec<String> {
+    let mut out = vec!["0".to_string(), "1".to_string()];
+    if let Ok(value) = text.parse::<f64>() {
+        if value.is_finite() && value.abs() < 1e15 {
+            out.push(format!("{}", value.trunc()));
+        }
+    }
+    if let Some((_, decimals)) = text.split_once('.') {
+        if decimals.len() > 1 && decimals.chars().all(|c| c.is_ascii_digit()) {
+            out.push(text[..text.len() - 1].to_string());
+        }
+    }
+    out.retain(|simpler| simpler != text);
+    out.dedup();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Formula {
+        Formula::parse(text).unwrap()
+    }
+
+    #[test]
+    fn reductions_unwrap_remove_and_simplify() {
+        let reduced: Vec<String> = reductions(&parse("=ROUND(SQRT(12.34), 2)").expr)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(reduced[0], "SQRT(12.34)", "unwrapping comes first");
+        assert!(reduced.contains(&"ROUND(2)".to_string()));
+        assert!(reduced.contains(&"ROUND(12.34, 2)".to_string()));
+        assert!(reduced.contains(&"ROUND(SQRT(12.3), 2)".to_string()));
+        assert!(reduced.contains(&"ROUND(SQRT(12), 2)".to_string()));
+        assert!(reduced.contains(&"ROUND(SQRT(12.34), 0)".to_string()));
+    }
+
+    #[test]
+    fn minimize_finds_smallest_failing_formula() {
+        // Pretend forge disagrees whenever SQRT gets a negative literal
+        let fails = |formula: &Formula| {
+            let text = formula.to_string();
+            text.contains("SQRT(-") && !text.contains("SQRT(-0")
+        };
+        let original = parse(
+            "=IFERROR(LET(x, 10, y, x * 2, ROUND(SUM(y, SQRT(-4.25 * x), 3.75), 2)), \"n/a\")",
+        );
+        assert!(fails(&original));
+
+        let minimized = minimize(&original, 1000, fails);
+        assert!(!minimized.exhausted);
+        assert_eq!(minimized.formula.to_string(), "=SQRT(-1)");
+        assert_eq!(minimized.steps.last(), Some(&minimized.formula));
+    }
+
+    #[test]
+    fn minimize_stops_at_evaluation_budget() {
+        let minimized = minimize(&parse("=SUM(1, 2, 3)"), 2, |_| false);
+        assert!(minimized.exhausted);
+        assert_eq!(minimized.evaluations, 2);
+        assert_eq!(minimized.formula, parse("=SUM(1, 2, 3)"));
+    }
+}
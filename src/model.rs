@@ -0,0 +1,320 @@
+//! Generated forge models.
+//!
+//! The runner builds every model it hands to `forge export` as a
+//! [`ForgeModel`] and serializes it with `serde_yaml_ng`, so formulas and
+//! table strings are quoted correctly whatever characters they contain.
+
+use std::collections::BTreeMap;
+
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+
+use crate::types::{CalculatedColumn, Scenarios, Section, TableColumn, TestSpec};
+
+/// Section holding the scalars of a generated model.
+pub const ASSUMPTIONS: &str = "assumptions";
+
+/// A forge model: table sections, scenarios and scalars.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ForgeModel {
+    /// Value of `_forge_version`.
+    pub forge_version: String,
+    /// Table sections by name, each with its columns by name.
+    pub tables: BTreeMap<String, BTreeMap<String, ModelColumn>>,
+    /// The `scenarios` block, if any.
+    pub scenarios: Option<ModelScenarios>,
+    /// Scalars of the `assumptions` section, in declaration order.
+    pub scalars: Vec<(String, ModelScalar)>,
+}
+
+/// A table column of a generated model.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ModelColumn {
+    /// Numeric data.
+    Numbers(Vec<f64>),
+    /// String data.
+    Strings(Vec<String>),
+    /// Row-wise formula.
+    Formula(String),
+}
+
+/// The `scenarios` block of a generated model.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ModelScenarios {
+    /// Named scenarios with scalar overrides.
+    Overrides(BTreeMap<String, BTreeMap<String, f64>>),
+    /// A plain data table named `scenarios`.
+    Table(BTreeMap<String, ModelColumn>),
+}
+
+/// A scalar of a generated model.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelScalar {
+    /// Input value (`null` for formula scalars).
+    pub value: Option<f64>,
+    /// Formula, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formula: Option<String>,
+}
+
+impl From<&TableColumn> for ModelColumn {
+    fn from(column: &TableColumn) -> Self {
+        match column {
+            TableColumn::Numbers(nums) => Self::Numbers(nums.clone()),
+            TableColumn::Strings(strs) => Self::Strings(strs.clone()),
+            TableColumn::Formula(f)
+            | TableColumn::Calculated(CalculatedColumn { formula: f, .. }) => {
+                Self::Formula(f.clone())
+            }
+        }
+    }
+}
+
+fn model_columns<'a, I>(columns: I) -> BTreeMap<String, ModelColumn>
+where
+    I: IntoIterator<Item = (&'a String, &'a TableColumn)>,
+{
+    columns
+        .into_iter()
+        .map(|(name, column)| (name.clone(), ModelColumn::from(column)))
+        .collect()
+}
+
+impl ForgeModel {
+    /// Creates an empty model.
+    #[must_use]
+    pub fn new(forge_version: &str) -> Self {
+        Self {
+            forge_version: forge_version.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds the table sections and scenarios of a spec.
+    ///
+    /// Metadata sections (`_*`) and scalar groups are left out; they hold
+    /// the tests themselves.
+    pub fn add_spec_data(&mut self, spec: &TestSpec) {
+        for (section_name, section) in &spec.sections {
+            if section_name.starts_with('_') || section_name == ASSUMPTIONS {
+                continue;
+            }
+            if let Section::Table(columns) = section {
+                self.tables
+                    .insert(section_name.clone(), model_columns(columns));
+            }
+        }
+
+        self.scenarios = match &spec.scenarios {
+            Some(Scenarios::Table(columns)) => Some(ModelScenarios::Table(model_columns(columns))),
+            Some(Scenarios::Overrides(scenarios)) => {
+                Some(ModelScenarios::Overrides(scenarios.clone()))
+            }
+            None => None,
+        };
+    }
+
+    /// Appends a scalar to the `assumptions` section.
+    pub fn add_scalar(&mut self, name: &str, value: Option<f64>, formula: Option<&str>) {
+        self.scalars.push((
+            name.to_string(),
+            ModelScalar {
+                value,
+                formula: formula.map(str::to_string),
+            },
+        ));
+    }
+
+    /// Serializes the model to the YAML read by `forge export`.
+    pub fn to_yaml(&self) -> Result<String, String> {
+        serde_yaml_ng::to_string(self).map_err(|e| format!("Failed to serialize forge model: {e}"))
+    }
+}
+
+/// Scalars serialized as a map, keeping declaration order.
+struct ScalarMap<'a>(&'a [(String, ModelScalar)]);
+
+impl Serialize for ScalarMap<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, scalar) in self.0 {
+            map.serialize_entry(name, scalar)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for ForgeModel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("_forge_version", &self.forge_version)?;
+        for (name, columns) in &self.tables {
+            map.serialize_entry(name, columns)?;
+        }
+        if let Some(scenarios) = &self.scenarios {
+            map.serialize_entry("scenarios", scenarios)?;
+        }
+        if !self.scalars.is_empty() {
+            map.serialize_entry(ASSUMPTIONS, &ScalarMap(&self.scalars))?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::excel::{read_xlsx, CellValue};
+    use crate::formula::escape_text;
+    use proptest::prelude::*;
+    use serde_yaml_ng::Value;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    #[test]
+    fn serializes_tables_scenarios_and_ordered_scalars() {
+        let spec: TestSpec = serde_yaml_ng::from_str(
+            r#"
+_forge_version: "1.0.0"
+scenarios:
+  base:
+    growth_rate: 0.05
+sales:
+  region: ["North", "South"]
+  amount: [1, 2]
+  doubled:
+    formula: "=amount * 2"
+    expected: [2, 4]
+assumptions:
+  test_one:
+    formula: "=SUM(sales.amount)"
+    expected: 3
+"#,
+        )
+        .unwrap();
+
+        let mut model = ForgeModel::new("1.0.0");
+        model.add_spec_data(&spec);
+        model.add_scalar("zeta", Some(1.5), None);
+        model.add_scalar("alpha", None, Some("=assumptions.zeta * 2"));
+
+        assert_eq!(
+            model.tables["sales"]["doubled"],
+            ModelColumn::Formula("=amount * 2".to_string())
+        );
+        assert!(!model.tables.contains_key(ASSUMPTIONS));
+
+        let yaml = model.to_yaml().unwrap();
+        let parsed: Value = serde_yaml_ng::from_str(&yaml).unwrap();
+        assert_eq!(parsed["_forge_version"], Value::from("1.0.0"));
+        assert_eq!(
+            parsed["scenarios"]["base"]["growth_rate"],
+            Value::from(0.05)
+        );
+        assert_eq!(parsed["sales"]["region"][1], Value::from("South"));
+        assert!(parsed["assumptions"]["alpha"]["value"].is_null());
+        assert!(yaml.find("zeta:").unwrap() < yaml.find("alpha:").unwrap());
+    }
+
+    /// The forge binary from `FORGE_BIN` or a sibling forge checkout, if any.
+    fn forge_binary() -> Option<PathBuf> {
+        std::env::var_os("FORGE_BIN")
+            .map(PathBuf::from)
+            .or_else(|| {
+                Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("../forge/target/release/forge"))
+            })
+            .filter(|path| path.exists())
+    }
+
+    #[test]
+    fn forge_exports_generated_models() {
+        let Some(forge) = forge_binary() else {
+            eprintln!("skipping: no forge binary (set FORGE_BIN)");
+            return;
+        };
+        // Table strings are non-empty and don't start with `=`, which forge
+        // reads as a formula
+        proptest!(
+            ProptestConfig::with_cases(16),
+            |(
+                strings in prop::collection::vec("[^\\x00-\\x1f=][^\\x00-\\x1f]{0,19}", 1..6),
+                text in "[^\\x00-\\x1f]{0,20}",
+            )| {
+                let mut model = ForgeModel::new("1.0.0");
+                model.tables.insert(
+                    "data".to_string(),
+                    BTreeMap::from([("names".to_string(), ModelColumn::Strings(strings.clone()))]),
+                );
+                let formula = format!("=LEN(\"{}\")", escape_text(&text));
+                model.add_scalar("test_result", None, Some(&formula));
+
+                let dir = tempfile::tempdir().unwrap();
+                let yaml_path = dir.path().join("model.yaml");
+                let xlsx_path = dir.path().join("model.xlsx");
+                fs::write(&yaml_path, model.to_yaml().unwrap()).unwrap();
+                let output = Command::new(&forge)
+                    .arg("export")
+                    .arg(&yaml_path)
+                    .arg(&xlsx_path)
+                    .output()
+                    .unwrap();
+                prop_assert!(
+                    output.status.success(),
+                    "forge export failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+
+                let sheets = read_xlsx(&xlsx_path).unwrap();
+                let Some((_, rows)) = sheets.iter().find(|(name, _)| name == "data") else {
+                    return Err(TestCaseError::fail("no data sheet in the export"));
+                };
+                let exported: Vec<String> = rows
+                    .iter()
+                    .skip(1)
+                    .map(|row| match row.first() {
+                        Some(CellValue::Text(s)) => s.clone(),
+                        _ => String::new(),
+                    })
+                    .collect();
+                prop_assert_eq!(exported, strings);
+            }
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn formulas_survive_serialization(formula in any::<String>()) {
+            let mut model = ForgeModel::new("1.0.0");
+            model.add_scalar("test_result", None, Some(&formula));
+
+            let parsed: Value = serde_yaml_ng::from_str(&model.to_yaml().unwrap()).unwrap();
+            prop_assert_eq!(
+                parsed["assumptions"]["test_result"]["formula"].as_str(),
+                Some(formula.as_str())
+            );
+        }
+
+        #[test]
+        fn table_strings_survive_serialization(strings in prop::collection::vec(any::<String>(), 0..8)) {
+            let mut model = ForgeModel::new("1.0.0");
+            model.tables.insert(
+                "data".to_string(),
+                BTreeMap::from([("names".to_string(), ModelColumn::Strings(strings.clone()))]),
+            );
+
+            let spec: TestSpec = serde_yaml_ng::from_str(&model.to_yaml().unwrap()).unwrap();
+            let Some(Section::Table(columns)) = spec.sections.get("data") else {
+                return Err(TestCaseError::fail("data table did not parse back"));
+            };
+            match &columns["names"] {
+                TableColumn::Strings(parsed) => prop_assert_eq!(parsed, &strings),
+                // An empty list reads back as the first untagged variant
+                TableColumn::Numbers(parsed) => prop_assert!(parsed.is_empty() && strings.is_empty()),
+                other => return Err(TestCaseError::fail(format!("unexpected column {other:?}"))),
+            }
+        }
+    }
+}
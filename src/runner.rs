@@ -6,19 +6,101 @@
 //! 3. Run forge export to create XLSX
 //! 4. Use Gnumeric (ssconvert) to recalculate and export to CSV
 //! 5. Compare results against expected values
+//!
+//! Import workbooks run the other direction: the workbook is written with
+//! `rust_xlsxwriter`, imported and calculated by forge, and compared with
+//! Gnumeric's recalculation of the original workbook.
 
-use std::fmt::Write;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Deserialize;
 
-use crate::engine::GnumericEngine;
+use crate::dates::{serial_from_system_time, DateSystem};
+use crate::divergence::{DivergenceRegistry, DIVERGENCES_FILE};
+use crate::engine::{find_sheet, FunctionRequirements, GnumericEngine, SheetCsv, FUNCTIONS_FILE};
+use crate::excel::{self, CellValue, SheetData};
+use crate::formula::Formula;
+use crate::fuzz::describe;
+use crate::model::{ForgeModel, ASSUMPTIONS};
 use crate::types::{
-    extract_skip_cases, extract_table_data_yaml, extract_test_cases, SkipCase, TestCase,
-    TestResult, TestSpec,
+    extract_chain_cases, extract_distribution_cases, extract_import_cases, extract_skip_cases,
+    extract_table_cases, extract_test_cases, ChainCase, ChainScalar, DistributionCase, Fixture,
+    GnumericVersion, ImportCase, ImportFormula, Sampling, SkipCase, TableCase, TestCase,
+    TestResult, TestSpec, Timings, ValueKind, VolatileExpectation, WallClock,
 };
 
+/// Directory name for shared fixture files (skipped when loading specs).
+pub const FIXTURES_DIR: &str = "fixtures";
+
+/// Which tests keep their generated files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeepArtifacts {
+    /// Only runs with at least one failed test.
+    Failed,
+    /// Every run.
+    All,
+}
+
+impl FromStr for KeepArtifacts {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "failed" => Ok(Self::Failed),
+            "all" => Ok(Self::All),
+            _ => Err(format!("expected `failed` or `all`, got `{s}`")),
+        }
+    }
+}
+
+/// Suffix of the workbook forge exports from an imported model.
+const FORGE_SUFFIX: &str = "_forge";
+
+/// The forge commands a kept `repro.sh` reruns before Gnumeric's
+/// recalculation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ForgeStep<'a> {
+    /// `forge export` of the generated model, in a date system.
+    Export(DateSystem),
+    /// `forge export --scenario` of the generated model.
+    Scenario(&'a str),
+    /// `forge import` of the written workbook, then `forge export`.
+    Import,
+}
+
+/// Where and for which tests the generated YAML, XLSX and CSV files are
+/// kept.
+#[derive(Debug, Clone)]
+pub struct Artifacts {
+    /// Directory holding one subdirectory per test (or chain, table, batch).
+    pub dir: PathBuf,
+    /// Which tests keep their files.
+    pub keep: KeepArtifacts,
+}
+
+/// Quotes a value for a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Runs `f`, adding the time it takes to `stage`.
+fn timed<T>(stage: &mut Duration, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let value = f();
+    *stage += start.elapsed();
+    value
+}
+
 /// Test runner for E2E validation.
 pub struct TestRunner {
     /// Path to the forge binary.
@@ -29,11 +111,83 @@ pub struct TestRunner {
     tests_dir: PathBuf,
     /// All loaded test cases.
     test_cases: Vec<TestCase>,
+    /// All loaded dependent scalar chains.
+    chain_cases: Vec<ChainCase>,
+    /// All loaded tables with asserted calculated columns.
+    table_cases: Vec<TableCase>,
+    /// All loaded random formulas with asserted sample statistics.
+    distribution_cases: Vec<DistributionCase>,
+    /// All loaded import workbooks.
+    import_cases: Vec<ImportCase>,
     /// All loaded skip cases.
     skip_cases: Vec<SkipCase>,
+    /// Spec files with at least one test, in load order.
+    files: Vec<PathBuf>,
+    /// `_tags` of each spec file.
+    file_tags: HashMap<PathBuf, Vec<String>>,
+    /// Known Gnumeric-vs-Excel divergences.
+    divergences: DivergenceRegistry,
+    /// Gnumeric releases required by functions.
+    requirements: FunctionRequirements,
+    /// Where to keep generated files, if at all.
+    artifacts: Option<Artifacts>,
+    /// Largest absolute difference between actual and expected values
+    /// that still passes.
+    tolerance: f64,
+}
+
+/// Everything loaded from the tests directory.
+#[derive(Debug, Default)]
+pub struct TestSuite {
+    /// Single-formula test cases.
+    pub tests: Vec<TestCase>,
+    /// Dependent scalar chains.
+    pub chains: Vec<ChainCase>,
+    /// Tables with asserted calculated columns.
+    pub tables: Vec<TableCase>,
+    /// Random formulas with asserted sample statistics.
+    pub distributions: Vec<DistributionCase>,
+    /// Workbooks imported into forge.
+    pub imports: Vec<ImportCase>,
+    /// Skipped tests.
+    pub skips: Vec<SkipCase>,
+    /// Spec files with at least one test, in load order.
+    pub files: Vec<PathBuf>,
+    /// `_tags` of each spec file with at least one test.
+    pub tags: HashMap<PathBuf, Vec<String>>,
+}
+
+/// Selects spec files by their `_tags`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter {
+    /// Run only files with one of these tags (all files if empty).
+    pub tags: Vec<String>,
+    /// Skip files with one of these tags.
+    pub skip_tags: Vec<String>,
+}
+
+impl TagFilter {
+    /// Returns `true` if a file with these tags runs.
+    #[must_use]
+    pub fn selects(&self, tags: &[String]) -> bool {
+        (self.tags.is_empty() || self.tags.iter().any(|tag| tags.contains(tag)))
+            && !self.skip_tags.iter().any(|tag| tags.contains(tag))
+    }
+}
+
+/// Results of the tests of one spec file.
+#[derive(Debug)]
+pub struct FileResults {
+    /// The spec file.
+    pub file: PathBuf,
+    /// Results in run order.
+    pub results: Vec<TestResult>,
 }
 
 impl TestRunner {
+    /// Default comparison tolerance: values must match exactly.
+    pub const DEFAULT_TOLERANCE: f64 = f64::EPSILON;
+
     /// Creates a new test runner.
     ///
     /// # Errors
@@ -45,17 +199,83 @@ impl TestRunner {
         engine: GnumericEngine,
         tests_dir: PathBuf,
     ) -> anyhow::Result<Self> {
-        let (test_cases, skip_cases) = Self::load_test_cases(&tests_dir)?;
+        let loaded = Self::load_test_cases(&tests_dir)?;
+        let divergences_path = tests_dir.join(DIVERGENCES_FILE);
+        let divergences = if divergences_path.exists() {
+            DivergenceRegistry::load(&divergences_path)?
+        } else {
+            DivergenceRegistry::default()
+        };
+        let requirements_path = tests_dir.join(FUNCTIONS_FILE);
+        let requirements = if requirements_path.exists() {
+            FunctionRequirements::load(&requirements_path).map_err(anyhow::Error::msg)?
+        } else {
+            FunctionRequirements::default()
+        };
 
         Ok(Self {
             forge_binary,
             engine,
             tests_dir,
-            test_cases,
-            skip_cases,
+            test_cases: loaded.tests,
+            chain_cases: loaded.chains,
+            table_cases: loaded.tables,
+            distribution_cases: loaded.distributions,
+            import_cases: loaded.imports,
+            skip_cases: loaded.skips,
+            files: loaded.files,
+            file_tags: loaded.tags,
+            divergences,
+            requirements,
+            artifacts: None,
+            tolerance: Self::DEFAULT_TOLERANCE,
         })
     }
 
+    /// Passes results within `tolerance` of the expected value.
+    #[must_use]
+    pub const fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Replaces the known divergences read from the tests directory.
+    #[must_use]
+    pub fn with_divergences(mut self, divergences: DivergenceRegistry) -> Self {
+        self.divergences = divergences;
+        self
+    }
+
+    /// Returns `true` if `actual` is within the tolerance of `expected`.
+    fn matches(&self, actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() <= self.tolerance
+    }
+
+    /// Keeps only the spec files (and their tests) the filter selects.
+    #[must_use]
+    pub fn with_tag_filter(mut self, filter: &TagFilter) -> Self {
+        let tags = &self.file_tags;
+        self.files
+            .retain(|file| filter.selects(tags.get(file).map_or(&[], Vec::as_slice)));
+        let files = &self.files;
+        let kept = |source: &Option<PathBuf>| source.as_ref().is_some_and(|f| files.contains(f));
+        self.test_cases.retain(|case| kept(&case.source_file));
+        self.chain_cases.retain(|case| kept(&case.source_file));
+        self.table_cases.retain(|case| kept(&case.source_file));
+        self.distribution_cases
+            .retain(|case| kept(&case.source_file));
+        self.import_cases.retain(|case| kept(&case.source_file));
+        self.skip_cases.retain(|case| kept(&case.source_file));
+        self
+    }
+
+    /// Keeps the generated files of tests in `artifacts.dir`.
+    #[must_use]
+    pub fn with_artifacts(mut self, artifacts: Artifacts) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
     /// Finds all YAML files in a directory recursively.
     #[allow(dead_code)]
     fn find_yaml_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
@@ -73,38 +293,79 @@ impl TestRunner {
     }
 
     /// Loads all test cases from the tests directory.
-    fn load_test_cases(tests_dir: &Path) -> anyhow::Result<(Vec<TestCase>, Vec<SkipCase>)> {
-        let mut all_cases = Vec::new();
-        let mut all_skips = Vec::new();
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tests directory does not exist, a file cannot
+    /// be read, or a spec's fixtures or templates are invalid.
+    pub fn load_test_cases(tests_dir: &Path) -> anyhow::Result<TestSuite> {
+        let mut loaded = TestSuite::default();
 
         if !tests_dir.exists() {
             anyhow::bail!("Tests directory does not exist: {}", tests_dir.display());
         }
 
-        Self::load_test_cases_recursive(tests_dir, &mut all_cases, &mut all_skips)?;
+        Self::load_test_cases_recursive(tests_dir, &mut loaded)?;
 
-        Ok((all_cases, all_skips))
+        Ok(loaded)
     }
 
-    fn load_test_cases_recursive(
-        dir: &Path,
-        all_cases: &mut Vec<TestCase>,
-        all_skips: &mut Vec<SkipCase>,
-    ) -> anyhow::Result<()> {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    fn load_test_cases_recursive(dir: &Path, loaded: &mut TestSuite) -> anyhow::Result<()> {
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.sort();
 
+        for path in paths {
             if path.is_dir() {
-                Self::load_test_cases_recursive(&path, all_cases, all_skips)?;
-            } else if path.extension().is_some_and(|e| e == "yaml") {
+                if path.file_name().is_some_and(|n| n == FIXTURES_DIR) {
+                    continue;
+                }
+                Self::load_test_cases_recursive(&path, loaded)?;
+            } else if path.extension().is_some_and(|e| e == "yaml")
+                && path
+                    .file_name()
+                    .is_some_and(|n| n != DIVERGENCES_FILE && n != FUNCTIONS_FILE)
+            {
                 let content = fs::read_to_string(&path)?;
                 match serde_yaml_ng::from_str::<TestSpec>(&content) {
-                    Ok(spec) => {
-                        let cases = extract_test_cases(&spec, Some(&path));
-                        let skips = extract_skip_cases(&spec);
-                        all_cases.extend(cases);
-                        all_skips.extend(skips);
+                    Ok(mut spec) => {
+                        Self::merge_includes(&mut spec, &path)?;
+                        let cases = extract_test_cases(&spec, Some(&path))
+                            .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+                        let chains = extract_chain_cases(&spec, Some(&path))
+                            .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+                        let tables = extract_table_cases(&spec, Some(&path));
+                        let distributions = extract_distribution_cases(&spec, Some(&path))
+                            .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+                        let imports = extract_import_cases(&spec, Some(&path))
+                            .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+                        let skips = extract_skip_cases(&spec, Some(&path));
+                        let formulas =
+                            Self::case_formulas(&cases, &chains, &tables, &distributions);
+                        spec.check_scalar_refs()
+                            .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+                        for (name, formula, chain) in &formulas {
+                            spec.check_table_refs(name, formula, chain)
+                                .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+                        }
+                        Self::warn_unparsed_formulas(&path, formulas, &imports);
+                        if !(cases.is_empty()
+                            && chains.is_empty()
+                            && tables.is_empty()
+                            && distributions.is_empty()
+                            && imports.is_empty()
+                            && skips.is_empty())
+                        {
+                            loaded.files.push(path.clone());
+                            loaded.tags.insert(path.clone(), spec.tags.clone());
+                        }
+                        loaded.tests.extend(cases);
+                        loaded.chains.extend(chains);
+                        loaded.tables.extend(tables);
+                        loaded.distributions.extend(distributions);
+                        loaded.imports.extend(imports);
+                        loaded.skips.extend(skips);
                     }
                     Err(e) => {
                         eprintln!("Warning: Failed to parse {}: {e}", path.display());
@@ -116,11 +377,182 @@ impl TestRunner {
         Ok(())
     }
 
+    /// Returns the formulas of a spec's test cases, by test name, with the
+    /// chain each formula belongs to (empty outside chains).
+    fn case_formulas<'a>(
+        cases: &'a [TestCase],
+        chains: &'a [ChainCase],
+        tables: &'a [TableCase],
+        distributions: &'a [DistributionCase],
+    ) -> Vec<(String, &'a str, &'a [ChainScalar])> {
+        cases
+            .iter()
+            .map(|tc| (tc.name.clone(), tc.formula.as_str(), &[][..]))
+            .chain(chains.iter().flat_map(|chain| {
+                chain.scalars.iter().filter_map(|scalar| {
+                    scalar.formula.as_deref().map(|formula| {
+                        (
+                            chain.assertion_name(scalar),
+                            formula,
+                            chain.scalars.as_slice(),
+                        )
+                    })
+                })
+            }))
+            .chain(tables.iter().flat_map(|table| {
+                table.columns.iter().map(|column| {
+                    (
+                        format!("{}.{}", table.name, column.name),
+                        column.formula.as_str(),
+                        &[][..],
+                    )
+                })
+            }))
+            .chain(
+                distributions
+                    .iter()
+                    .map(|case| (case.name.clone(), case.formula.as_str(), &[][..])),
+            )
+            .collect()
+    }
+
+    /// Warns about formulas the formula parser cannot read.
+    ///
+    /// Such formulas still run, but are invisible to coverage and other
+    /// formula analysis.
+    fn warn_unparsed_formulas(
+        path: &Path,
+        formulas: Vec<(String, &str, &[ChainScalar])>,
+        imports: &[ImportCase],
+    ) {
+        let import_formulas: Vec<ImportFormula> =
+            imports.iter().flat_map(ImportCase::formulas).collect();
+        let formulas = formulas
+            .into_iter()
+            .map(|(name, formula, _)| (name, formula))
+            .chain(
+                import_formulas
+                    .iter()
+                    .map(|cell| (cell.name.clone(), cell.formula.as_str())),
+            );
+
+        for (name, formula) in formulas {
+            if let Err(e) = Formula::parse(formula) {
+                eprintln!(
+                    "Warning: {}: formula of {name} does not parse: {e}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    /// Loads a spec file and merges its `_include` fixtures.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the spec or a fixture cannot be read or parsed,
+    /// or if a fixture table conflicts with an existing table.
+    pub fn load_spec(path: &Path) -> anyhow::Result<TestSpec> {
+        let content = fs::read_to_string(path)?;
+        let mut spec = serde_yaml_ng::from_str::<TestSpec>(&content)?;
+        Self::merge_includes(&mut spec, path)?;
+        Ok(spec)
+    }
+
+    /// Merges every fixture listed in the spec's `_include` into the spec.
+    fn merge_includes(spec: &mut TestSpec, spec_path: &Path) -> anyhow::Result<()> {
+        for name in spec.include.clone() {
+            let fixture_path = Self::resolve_fixture(spec_path, &name)?;
+            let content = fs::read_to_string(&fixture_path)?;
+            let fixture: Fixture = serde_yaml_ng::from_str(&content).map_err(|e| {
+                anyhow::anyhow!("Failed to parse fixture {}: {e}", fixture_path.display())
+            })?;
+            spec.merge_fixture(&name, fixture)
+                .map_err(|e| anyhow::anyhow!("{}: {e}", spec_path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Resolves an `_include` entry to a fixture file.
+    ///
+    /// Entries ending in `.yaml` are paths relative to the spec. Bare names
+    /// are looked up as `fixtures/<name>.yaml` in the spec's directory and
+    /// then each parent directory.
+    fn resolve_fixture(spec_path: &Path, name: &str) -> anyhow::Result<PathBuf> {
+        let spec_dir = spec_path.parent().unwrap_or_else(|| Path::new(""));
+
+        if Path::new(name).extension().is_some_and(|e| e == "yaml") {
+            return Ok(spec_dir.join(name));
+        }
+
+        spec_dir
+            .ancestors()
+            .map(|dir| dir.join(FIXTURES_DIR).join(format!("{name}.yaml")))
+            .find(|candidate| candidate.exists())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{}: fixture `{name}` not found in any {FIXTURES_DIR}/ directory",
+                    spec_path.display()
+                )
+            })
+    }
+
     /// Returns the total number of test cases.
     #[must_use]
-    #[allow(clippy::missing_const_for_fn)] // Uses len() which isn't const
     pub fn total_tests(&self) -> usize {
-        self.test_cases.len() + self.skip_cases.len()
+        self.files.iter().map(|file| self.file_tests(file)).sum()
+    }
+
+    /// Returns the spec files with at least one test, in load order.
+    #[must_use]
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Returns the number of test cases of one spec file.
+    #[must_use]
+    pub fn file_tests(&self, file: &Path) -> usize {
+        let in_file = |source: &Option<PathBuf>| source.as_deref() == Some(file);
+        let tests = self
+            .test_cases
+            .iter()
+            .filter(|tc| in_file(&tc.source_file))
+            .count();
+        let chain_assertions: usize = self
+            .chain_cases
+            .iter()
+            .filter(|chain| in_file(&chain.source_file))
+            .map(|chain| chain.assertions().count())
+            .sum();
+        let table_assertions: usize = self
+            .table_cases
+            .iter()
+            .filter(|table| in_file(&table.source_file))
+            .map(TableCase::assertion_count)
+            .sum();
+        let distribution_assertions: usize = self
+            .distribution_cases
+            .iter()
+            .filter(|case| in_file(&case.source_file))
+            .map(|case| case.assertion_names().len())
+            .sum();
+        let import_assertions: usize = self
+            .import_cases
+            .iter()
+            .filter(|case| in_file(&case.source_file))
+            .map(|case| case.formulas().len())
+            .sum();
+        let skips = self
+            .skip_cases
+            .iter()
+            .filter(|sc| in_file(&sc.source_file))
+            .count();
+        tests
+            + chain_assertions
+            + table_assertions
+            + distribution_assertions
+            + import_assertions
+            + skips
     }
 
     #[allow(dead_code)]
@@ -136,6 +568,36 @@ impl TestRunner {
         &self.test_cases
     }
 
+    /// Returns all dependent scalar chains.
+    #[must_use]
+    pub fn chain_cases(&self) -> &[ChainCase] {
+        &self.chain_cases
+    }
+
+    /// Returns all tables with asserted calculated columns.
+    #[must_use]
+    pub fn table_cases(&self) -> &[TableCase] {
+        &self.table_cases
+    }
+
+    /// Returns all random formulas with asserted sample statistics.
+    #[must_use]
+    pub fn distribution_cases(&self) -> &[DistributionCase] {
+        &self.distribution_cases
+    }
+
+    /// Returns all loaded import workbooks.
+    #[must_use]
+    pub fn import_cases(&self) -> &[ImportCase] {
+        &self.import_cases
+    }
+
+    /// Returns the Gnumeric engine tests run against.
+    #[must_use]
+    pub const fn engine(&self) -> &GnumericEngine {
+        &self.engine
+    }
+
     /// Returns all skip cases.
     #[must_use]
     pub fn skip_cases(&self) -> &[SkipCase] {
@@ -145,233 +607,522 @@ impl TestRunner {
     /// Runs all tests and returns results.
     #[must_use]
     pub fn run_all(&self) -> Vec<TestResult> {
-        self.skip_cases
+        self.run_all_streaming(|_| {})
+    }
+
+    /// Runs all tests with streaming output via callback, file by file.
+    pub fn run_all_streaming<F>(&self, mut on_result: F) -> Vec<TestResult>
+    where
+        F: FnMut(&TestResult),
+    {
+        self.files
             .iter()
-            .map(|sc| TestResult::Skip {
-                name: sc.name.clone(),
-                reason: sc.reason.clone(),
-            })
-            .chain(self.test_cases.iter().map(|tc| self.run_test(tc)))
+            .flat_map(|file| self.run_file_streaming(file, &mut on_result))
             .collect()
     }
 
-    /// Runs all tests with streaming output via callback.
-    pub fn run_all_streaming<F>(&self, mut on_result: F) -> Vec<TestResult>
+    /// Runs the spec files on up to `jobs` threads at once, handing each
+    /// file's results to `on_file` in file order.
+    pub fn run_files_parallel<F>(&self, jobs: NonZeroUsize, mut on_file: F)
+    where
+        F: FnMut(FileResults),
+    {
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..jobs.get().min(self.files.len()) {
+                let sender = sender.clone();
+                let next = &next;
+                scope.spawn(move || loop {
+                    let n = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(file) = self.files.get(n) else {
+                        break;
+                    };
+                    let results = self.run_file_streaming(file, |_| {});
+                    if sender.send((n, results)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            // Files finish out of order; hold them back until their turn
+            let mut finished = BTreeMap::new();
+            let mut turn = 0;
+            for (n, results) in receiver {
+                finished.insert(n, results);
+                while let Some(results) = finished.remove(&turn) {
+                    on_file(FileResults {
+                        file: self.files[turn].clone(),
+                        results,
+                    });
+                    turn += 1;
+                }
+            }
+        });
+    }
+
+    /// Runs the tests of one spec file with streaming output via callback.
+    pub fn run_file_streaming<F>(&self, file: &Path, mut on_result: F) -> Vec<TestResult>
     where
         F: FnMut(&TestResult),
     {
+        let in_file = |source: &Option<PathBuf>| source.as_deref() == Some(file);
         let mut results = Vec::new();
+        let mut emit = |result: TestResult| {
+            on_result(&result);
+            results.push(result);
+        };
 
-        for skip_case in &self.skip_cases {
-            let result = TestResult::Skip {
+        for skip_case in self.skip_cases.iter().filter(|sc| in_file(&sc.source_file)) {
+            emit(TestResult::Skip {
                 name: skip_case.name.clone(),
                 reason: skip_case.reason.clone(),
-            };
-            on_result(&result);
-            results.push(result);
+            });
         }
 
-        for tc in &self.test_cases {
-            let result = self.run_test(tc);
-            on_result(&result);
-            results.push(result);
+        for tc in self.test_cases.iter().filter(|tc| in_file(&tc.source_file)) {
+            match self.version_skip(tc.min_gnumeric, [tc.formula.as_str()]) {
+                Some(reason) => emit(TestResult::Skip {
+                    name: tc.name.clone(),
+                    reason,
+                }),
+                None => emit(self.finish(self.run_test(tc), tc.todo.as_deref())),
+            }
+        }
+
+        for chain in self
+            .chain_cases
+            .iter()
+            .filter(|chain| in_file(&chain.source_file))
+        {
+            for result in self.check_chain(chain) {
+                emit(result);
+            }
+        }
+
+        for table in self
+            .table_cases
+            .iter()
+            .filter(|table| in_file(&table.source_file))
+        {
+            for result in self.check_table(table) {
+                emit(result);
+            }
+        }
+
+        for case in self
+            .distribution_cases
+            .iter()
+            .filter(|case| in_file(&case.source_file))
+        {
+            for result in self.check_distribution(case) {
+                emit(result);
+            }
+        }
+
+        for case in self
+            .import_cases
+            .iter()
+            .filter(|case| in_file(&case.source_file))
+        {
+            for result in self.check_import(case) {
+                emit(result);
+            }
         }
 
         results
     }
 
-    /// Runs all tests in batch mode (single XLSX, faster).
+    /// Runs all tests in batch mode (single XLSX, faster), grouped by file.
+    ///
+    /// Scenario tests, scalar chains, calculated table columns,
+    /// distribution tests and import workbooks need their own model and are
+    /// run one by one after the batch.
     #[must_use]
-    #[allow(clippy::too_many_lines)]
-    pub fn run_batch(&self) -> Vec<TestResult> {
-        let mut results: Vec<TestResult> = self
+    pub fn run_batch(&self) -> Vec<FileResults> {
+        let mut checked: Vec<(Option<&Path>, TestResult)> = self
             .skip_cases
             .iter()
-            .map(|sc| TestResult::Skip {
-                name: sc.name.clone(),
-                reason: sc.reason.clone(),
+            .map(|sc| {
+                (
+                    sc.source_file.as_deref(),
+                    TestResult::Skip {
+                        name: sc.name.clone(),
+                        reason: sc.reason.clone(),
+                    },
+                )
             })
             .collect();
 
-        if self.test_cases.is_empty() {
-            return results;
+        let mut runnable = Vec::new();
+        for tc in &self.test_cases {
+            match self.version_skip(tc.min_gnumeric, [tc.formula.as_str()]) {
+                Some(reason) => checked.push((
+                    tc.source_file.as_deref(),
+                    TestResult::Skip {
+                        name: tc.name.clone(),
+                        reason,
+                    },
+                )),
+                None => runnable.push(tc),
+            }
         }
 
-        // Create a single YAML with all test formulas
-        let mut yaml_content = String::from("_forge_version: \"1.0.0\"\nassumptions:\n");
-        for (i, tc) in self.test_cases.iter().enumerate() {
-            let escaped_formula = tc.formula.replace('"', "\\\"");
-            let _ = write!(
-                yaml_content,
-                "  test_{i}:\n    value: null\n    formula: \"{escaped_formula}\"\n"
-            );
+        // Scenario and volatile tests need a workbook of their own
+        let (single_cases, batch_cases): (Vec<&TestCase>, Vec<&TestCase>) = runnable
+            .into_iter()
+            .partition(|tc| tc.scenario.is_some() || tc.volatile.is_some());
+        // One workbook per date system
+        let mut date_systems: Vec<DateSystem> =
+            batch_cases.iter().map(|tc| tc.date_system).collect();
+        date_systems.sort_unstable();
+        date_systems.dedup();
+        let batches: Vec<(DateSystem, Vec<&TestCase>)> = date_systems
+            .into_iter()
+            .map(|system| {
+                let cases = batch_cases
+                    .iter()
+                    .copied()
+                    .filter(|tc| tc.date_system == system)
+                    .collect();
+                (system, cases)
+            })
+            .collect();
+        checked.extend(
+            batches
+                .iter()
+                .flat_map(|(system, cases)| {
+                    self.run_formula_batch(cases, *system)
+                        .into_iter()
+                        .zip(cases.iter())
+                })
+                .chain(single_cases.iter().map(|tc| (self.run_test(tc), tc)))
+                .map(|(result, tc)| {
+                    (
+                        tc.source_file.as_deref(),
+                        self.finish(result, tc.todo.as_deref()),
+                    )
+                }),
+        );
+        for chain in &self.chain_cases {
+            for result in self.check_chain(chain) {
+                checked.push((chain.source_file.as_deref(), result));
+            }
+        }
+        for table in &self.table_cases {
+            for result in self.check_table(table) {
+                checked.push((table.source_file.as_deref(), result));
+            }
+        }
+        for case in &self.distribution_cases {
+            for result in self.check_distribution(case) {
+                checked.push((case.source_file.as_deref(), result));
+            }
+        }
+        for case in &self.import_cases {
+            for result in self.check_import(case) {
+                checked.push((case.source_file.as_deref(), result));
+            }
         }
 
-        let temp_dir = match tempfile::tempdir() {
-            Ok(d) => d,
-            Err(e) => {
-                for tc in &self.test_cases {
-                    results.push(TestResult::Fail {
-                        name: tc.name.clone(),
-                        formula: tc.formula.clone(),
-                        expected: tc.expected,
-                        actual: None,
-                        error: Some(format!("Failed to create temp dir: {e}")),
-                    });
-                }
-                return results;
+        let mut grouped: Vec<FileResults> = self
+            .files
+            .iter()
+            .map(|file| FileResults {
+                file: file.clone(),
+                results: Vec::new(),
+            })
+            .collect();
+        for (source, result) in checked {
+            if let Some(group) = grouped
+                .iter_mut()
+                .find(|group| Some(group.file.as_path()) == source)
+            {
+                group.results.push(result);
             }
-        };
+        }
+        grouped
+    }
 
-        let yaml_path = temp_dir.path().join("batch.yaml");
-        let xlsx_path = temp_dir.path().join("batch.xlsx");
+    /// Runs a chain, or skips its assertions if Gnumeric is too old.
+    fn check_chain(&self, chain: &ChainCase) -> Vec<TestResult> {
+        let formulas = chain.scalars.iter().filter_map(|s| s.formula.as_deref());
+        match self.version_skip(chain.min_gnumeric, formulas) {
+            Some(reason) => chain
+                .assertions()
+                .map(|(scalar, _)| TestResult::Skip {
+                    name: chain.assertion_name(scalar),
+                    reason: reason.clone(),
+                })
+                .collect(),
+            None => self
+                .run_chain(chain)
+                .into_iter()
+                .map(|result| self.finish(result, chain.todo.as_deref()))
+                .collect(),
+        }
+    }
 
-        if let Err(e) = fs::write(&yaml_path, &yaml_content) {
-            for tc in &self.test_cases {
-                results.push(TestResult::Fail {
-                    name: tc.name.clone(),
-                    formula: tc.formula.clone(),
-                    expected: tc.expected,
-                    actual: None,
-                    error: Some(format!("Failed to write YAML: {e}")),
-                });
-            }
-            return results;
+    /// Runs a table, or skips its rows if Gnumeric is too old.
+    fn check_table(&self, table: &TableCase) -> Vec<TestResult> {
+        let formulas = table.columns.iter().map(|c| c.formula.as_str());
+        match self.version_skip(table.min_gnumeric, formulas) {
+            Some(reason) => table
+                .columns
+                .iter()
+                .flat_map(|column| {
+                    (0..column.expected.len()).map(move |row| table.row_name(column, row))
+                })
+                .map(|name| TestResult::Skip {
+                    name,
+                    reason: reason.clone(),
+                })
+                .collect(),
+            None => self
+                .run_table(table)
+                .into_iter()
+                .map(|result| {
+                    let todo = table.todo_for(result.name());
+                    self.finish(result, todo)
+                })
+                .collect(),
         }
+    }
 
-        // Run forge export once
-        let output = match Command::new(&self.forge_binary)
-            .arg("export")
-            .arg(&yaml_path)
-            .arg(&xlsx_path)
-            .output()
-        {
-            Ok(o) => o,
-            Err(e) => {
-                for tc in &self.test_cases {
-                    results.push(TestResult::Fail {
-                        name: tc.name.clone(),
-                        formula: tc.formula.clone(),
-                        expected: tc.expected,
-                        actual: None,
-                        error: Some(format!("Failed to run forge: {e}")),
-                    });
-                }
-                return results;
-            }
-        };
+    /// Runs a distribution test, or skips its statistics if Gnumeric is too
+    /// old.
+    fn check_distribution(&self, case: &DistributionCase) -> Vec<TestResult> {
+        match self.version_skip(case.min_gnumeric, [case.formula.as_str()]) {
+            Some(reason) => case
+                .assertion_names()
+                .into_iter()
+                .map(|name| TestResult::Skip {
+                    name,
+                    reason: reason.clone(),
+                })
+                .collect(),
+            None => self
+                .run_distribution(case)
+                .into_iter()
+                .map(|result| self.finish(result, case.todo.as_deref()))
+                .collect(),
+        }
+    }
 
-        if !output.status.success() {
-            let err = String::from_utf8_lossy(&output.stderr);
-            for tc in &self.test_cases {
-                results.push(TestResult::Fail {
+    /// Runs an import workbook, or skips its formula cells if Gnumeric is
+    /// too old.
+    fn check_import(&self, case: &ImportCase) -> Vec<TestResult> {
+        let formulas = case.formulas();
+        match self.version_skip(
+            case.min_gnumeric,
+            formulas.iter().map(|cell| cell.formula.as_str()),
+        ) {
+            Some(reason) => formulas
+                .into_iter()
+                .map(|cell| TestResult::Skip {
+                    name: cell.name,
+                    reason: reason.clone(),
+                })
+                .collect(),
+            None => self
+                .run_import(case)
+                .into_iter()
+                .map(|result| {
+                    let todo = formulas
+                        .iter()
+                        .find(|cell| cell.name == result.name())
+                        .and_then(|cell| cell.todo.as_deref());
+                    self.finish(result, todo)
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns why a test cannot run on the detected Gnumeric: its own
+    /// minimum or a called function's is newer. Nothing is skipped when the
+    /// engine's version is unknown.
+    fn version_skip<'a>(
+        &self,
+        min_gnumeric: Option<GnumericVersion>,
+        formulas: impl IntoIterator<Item = &'a str>,
+    ) -> Option<String> {
+        let found = self.engine.version()?;
+        let required = formulas
+            .into_iter()
+            .filter_map(|formula| self.requirements.required_for(formula))
+            .chain(min_gnumeric)
+            .max()?;
+        (found < required).then(|| format!("requires Gnumeric >= {required} (found {found})"))
+    }
+
+    /// Applies a test's `todo` marker, or else the known divergences.
+    fn finish(&self, result: TestResult, todo: Option<&str>) -> TestResult {
+        match todo {
+            Some(reason) => result.into_todo(reason.to_string(), None),
+            None => self.divergences.apply(result),
+        }
+    }
+
+    /// Runs single-formula test cases of one date system in one XLSX.
+    fn run_formula_batch(
+        &self,
+        test_cases: &[&TestCase],
+        date_system: DateSystem,
+    ) -> Vec<TestResult> {
+        if test_cases.is_empty() {
+            return Vec::new();
+        }
+
+        let mut timings = Timings::default();
+        let fail_all = |error: &str, timings: Timings| -> Vec<TestResult> {
+            let share = timings.share(test_cases.len());
+            test_cases
+                .iter()
+                .map(|tc| TestResult::Fail {
                     name: tc.name.clone(),
                     formula: tc.formula.clone(),
                     expected: tc.expected,
                     actual: None,
-                    error: Some(format!("forge export failed: {err}")),
-                });
-            }
-            return results;
-        }
+                    error: Some(error.to_string()),
+                    timings: share,
+                    artifacts: None,
+                    kind: tc.kind,
+                    assertion: None,
+                })
+                .collect()
+        };
 
-        // Convert XLSX to CSV using Gnumeric
-        let csv_path = match self.engine.xlsx_to_csv(&xlsx_path, temp_dir.path()) {
-            Ok(p) => p,
-            Err(e) => {
-                for tc in &self.test_cases {
-                    results.push(TestResult::Fail {
-                        name: tc.name.clone(),
-                        formula: tc.formula.clone(),
-                        expected: tc.expected,
-                        actual: None,
-                        error: Some(format!("CSV conversion failed: {e}")),
-                    });
-                }
-                return results;
+        // Create a single model with all test formulas
+        let yaml_content = match timed(&mut timings.yaml, || {
+            let mut model = ForgeModel::new("1.0.0");
+            for (i, tc) in test_cases.iter().enumerate() {
+                model.add_scalar(&format!("test_{i}"), None, Some(&tc.formula));
             }
+            model.to_yaml()
+        }) {
+            Ok(yaml) => yaml,
+            Err(e) => return fail_all(&e, timings),
+        };
+
+        let temp_dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => return fail_all(&format!("Failed to create temp dir: {e}"), timings),
         };
+        let mut results = 'run: {
+            let sheets = match self.export_and_recalc(
+                &yaml_content,
+                temp_dir.path(),
+                "batch",
+                date_system,
+                None,
+                &mut timings,
+            ) {
+                Ok(sheets) => sheets,
+                Err(e) => break 'run fail_all(&e, timings),
+            };
+
+            let sheet = match find_sheet(&sheets, ASSUMPTIONS) {
+                Ok(sheet) => sheet,
+                Err(e) => break 'run fail_all(&e, timings),
+            };
 
-        // Parse CSV and match results
-        let csv_results = Self::parse_batch_csv(&csv_path, self.test_cases.len());
-        for (i, tc) in self.test_cases.iter().enumerate() {
-            match csv_results.get(i) {
-                Some(Ok(actual)) => {
-                    if (*actual - tc.expected).abs() < f64::EPSILON {
-                        results.push(TestResult::Pass {
+            // Parse CSV and match results
+            let csv_results = timed(&mut timings.parse, || {
+                Self::parse_batch_csv(sheet, test_cases.len())
+            });
+            // A 1904 run also checks the values forge cached itself
+            let forge_rows = match date_system {
+                DateSystem::Excel1900 => None,
+                DateSystem::Excel1904 => Some(timed(&mut timings.parse, || {
+                    Self::forge_assumptions(&temp_dir.path().join("batch.xlsx"))
+                })),
+            };
+            let share = timings.share(test_cases.len());
+            test_cases
+                .iter()
+                .zip(csv_results)
+                .enumerate()
+                .map(|(i, (tc, csv_result))| {
+                    let forge = match &forge_rows {
+                        None => Ok(()),
+                        Some(Ok(rows)) => {
+                            self.check_forge_value(rows, &format!("test_{i}"), tc.expected, tc.kind)
+                        }
+                        Some(Err(e)) => Err(e.clone()),
+                    };
+                    match (csv_result, forge) {
+                        (Ok(actual), Ok(())) if self.matches(actual, tc.expected) => {
+                            TestResult::Pass {
+                                name: tc.name.clone(),
+                                formula: tc.formula.clone(),
+                                expected: tc.expected,
+                                actual,
+                                timings: share,
+                            }
+                        }
+                        (Ok(actual), forge) => TestResult::Fail {
                             name: tc.name.clone(),
                             formula: tc.formula.clone(),
                             expected: tc.expected,
-                            actual: *actual,
-                        });
-                    } else {
-                        results.push(TestResult::Fail {
+                            actual: Some(actual),
+                            error: forge.err(),
+                            timings: share,
+                            artifacts: None,
+                            kind: tc.kind,
+                            assertion: None,
+                        },
+                        (Err(e), _) => TestResult::Fail {
                             name: tc.name.clone(),
                             formula: tc.formula.clone(),
                             expected: tc.expected,
-                            actual: Some(*actual),
-                            error: None,
-                        });
+                            actual: None,
+                            error: Some(e),
+                            timings: share,
+                            artifacts: None,
+                            kind: tc.kind,
+                            assertion: None,
+                        },
                     }
-                }
-                Some(Err(e)) => {
-                    results.push(TestResult::Fail {
-                        name: tc.name.clone(),
-                        formula: tc.formula.clone(),
-                        expected: tc.expected,
-                        actual: None,
-                        error: Some(e.clone()),
-                    });
-                }
-                None => {
-                    results.push(TestResult::Fail {
-                        name: tc.name.clone(),
-                        formula: tc.formula.clone(),
-                        expected: tc.expected,
-                        actual: None,
-                        error: Some("Missing result in CSV".to_string()),
-                    });
-                }
-            }
-        }
-
+                })
+                .collect()
+        };
+        let key = match date_system {
+            DateSystem::Excel1900 => PathBuf::from("batch"),
+            DateSystem::Excel1904 => PathBuf::from(format!("batch-{date_system}")),
+        };
+        self.keep_artifacts(
+            temp_dir.path(),
+            "batch",
+            ForgeStep::Export(date_system),
+            &key,
+            &mut results,
+        );
         results
     }
 
-    fn parse_batch_csv(csv_path: &Path, count: usize) -> Vec<Result<f64, String>> {
+    fn parse_batch_csv(sheet: &SheetCsv, count: usize) -> Vec<Result<f64, String>> {
         let mut results: Vec<Result<f64, String>> =
-            vec![Err("Missing result in CSV output".to_string()); count];
+            vec![Err(format!("Missing result on sheet `{}`", sheet.sheet)); count];
 
-        let file = match fs::File::open(csv_path) {
-            Ok(f) => f,
-            Err(e) => {
-                for r in &mut results {
-                    *r = Err(format!("Failed to open CSV: {e}"));
-                }
-                return results;
-            }
-        };
-
-        let reader = BufReader::new(file);
-        for line in reader.lines() {
-            let Ok(line) = line else { continue };
-            let cells: Vec<&str> = line
-                .split(',')
-                .map(|s| s.trim_matches('"').trim())
-                .collect();
-
-            if cells.len() >= 2 {
-                let label = cells[0];
-                if let Some(idx_str) = label
-                    .strip_prefix("assumptions.test_")
-                    .or_else(|| label.strip_prefix("test_"))
-                {
-                    if let Ok(idx) = idx_str.parse::<usize>() {
-                        if idx < count {
-                            if let Ok(value) = cells[1].replace(',', "").parse::<f64>() {
-                                results[idx] = Ok(value);
-                            }
-                        }
+        let records = match Self::csv_records(&sheet.path) {
+            Ok(records) => records,
+            Err(e) => return vec![Err(format!("CSV of sheet `{}`: {e}", sheet.sheet)); count],
+        };
+
+        for record in &records {
+            let (Some(label), Some(cell)) = (record.get(0), record.get(1)) else {
+                continue;
+            };
+            if let Some(idx_str) = label
+                .trim()
+                .strip_prefix("assumptions.test_")
+                .or_else(|| label.trim().strip_prefix("test_"))
+            {
+                if let Ok(idx) = idx_str.parse::<usize>() {
+                    if idx < count && results[idx].is_err() {
+                        results[idx] = excel::parse_number(cell)
+                            .ok_or_else(|| format!("Non-numeric result: {cell}"));
                     }
                 }
             }
@@ -380,169 +1131,1121 @@ impl TestRunner {
         results
     }
 
-    /// Runs a single test case.
-    #[must_use]
-    #[allow(clippy::too_many_lines)]
-    pub fn run_test(&self, test_case: &TestCase) -> TestResult {
-        let escaped_formula = test_case.formula.replace('"', "\\\"");
+    /// Creates a model holding the table data and scenarios of a test's
+    /// source spec, if it has one that loads.
+    fn source_model(source_file: Option<&Path>, forge_version: &str) -> ForgeModel {
+        let mut model = ForgeModel::new(forge_version);
+        if let Some(spec) = source_file.and_then(|path| Self::load_spec(path).ok()) {
+            model.add_spec_data(&spec);
+        }
+        model
+    }
 
-        // Load table data from source file if available
-        let table_data = if let Some(ref source_path) = test_case.source_file {
-            if let Ok(content) = fs::read_to_string(source_path) {
-                if let Ok(spec) = serde_yaml_ng::from_str::<TestSpec>(&content) {
-                    extract_table_data_yaml(&spec)
+    /// Returns the artifact subdirectory of a test: its spec path relative
+    /// to the tests directory (without extension), then its name.
+    fn artifact_key(&self, source_file: Option<&Path>, name: &str) -> PathBuf {
+        let name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+                    c
                 } else {
-                    String::new()
+                    '_'
                 }
-            } else {
-                String::new()
+            })
+            .collect();
+        source_file
+            .map(|file| {
+                file.strip_prefix(&self.tests_dir)
+                    .unwrap_or(file)
+                    .with_extension("")
+            })
+            .unwrap_or_default()
+            .join(name)
+    }
+
+    /// Copies the files of a work directory to the artifacts directory and
+    /// records the copy in the failed results, if configured to.
+    ///
+    /// A `repro.sh` next to the files reruns forge export and ssconvert.
+    fn keep_artifacts(
+        &self,
+        work_dir: &Path,
+        stem: &str,
+        forge_step: ForgeStep<'_>,
+        key: &Path,
+        results: &mut [TestResult],
+    ) {
+        let Some(artifacts) = &self.artifacts else {
+            return;
+        };
+        if artifacts.keep == KeepArtifacts::Failed && !results.iter().any(TestResult::is_fail) {
+            return;
+        }
+
+        let dest = artifacts.dir.join(key);
+        if let Err(e) = self.copy_artifacts(work_dir, stem, forge_step, &dest) {
+            eprintln!(
+                "Warning: failed to keep artifacts in {}: {e}",
+                dest.display()
+            );
+            return;
+        }
+        for result in results {
+            result.set_artifacts(&dest);
+        }
+    }
+
+    fn copy_artifacts(
+        &self,
+        work_dir: &Path,
+        stem: &str,
+        forge_step: ForgeStep<'_>,
+        dest: &Path,
+    ) -> std::io::Result<()> {
+        if dest.exists() {
+            fs::remove_dir_all(dest)?;
+        }
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(work_dir)? {
+            let path = entry?.path();
+            if let Some(file_name) = path.file_name() {
+                fs::copy(&path, dest.join(file_name))?;
             }
-        } else {
-            String::new()
+        }
+
+        let forge =
+            fs::canonicalize(&self.forge_binary).unwrap_or_else(|_| self.forge_binary.clone());
+        let ssconvert = self
+            .engine
+            .env()
+            .iter()
+            .map(|(key, value)| format!("{key}={}", shell_quote(value)))
+            .chain(std::iter::once(shell_quote(
+                &self.engine.path().to_string_lossy(),
+            )))
+            .chain(
+                self.engine
+                    .recalc_args(
+                        Path::new(&format!("{stem}.xlsx")),
+                        Path::new(&format!("{stem}_%n.csv")),
+                    )
+                    .iter()
+                    .map(|arg| shell_quote(arg)),
+            )
+            .collect::<Vec<_>>()
+            .join(" ");
+        let forge = shell_quote(&forge.to_string_lossy());
+        let export = format!("{forge} export {stem}.yaml {stem}.xlsx");
+        let (export, command) = match forge_step {
+            ForgeStep::Export(DateSystem::Excel1900) => (export, "export"),
+            ForgeStep::Scenario(scenario) => (
+                format!(
+                    "{forge} export --scenario {} {stem}.yaml {stem}.xlsx",
+                    shell_quote(scenario)
+                ),
+                "export",
+            ),
+            // Re-exporting would drop the 1904 flag set on the kept workbook
+            ForgeStep::Export(DateSystem::Excel1904) => (
+                format!("# {export}\n# (then switched to the 1904 date system, as kept)"),
+                "export",
+            ),
+            ForgeStep::Import => (
+                format!(
+                    "{forge} import {stem}.xlsx {stem}.yaml\n\
+                     {forge} export {stem}.yaml {stem}{FORGE_SUFFIX}.xlsx"
+                ),
+                "import",
+            ),
         };
+        let script = format!(
+            "#!/bin/sh\n\
+             # Reruns forge {command} and Gnumeric's recalculation on the kept files.\n\
+             set -e\n\
+             cd \"$(dirname \"$0\")\"\n\
+             {export}\n\
+             {ssconvert}\n",
+        );
+        let script_path = dest.join("repro.sh");
+        fs::write(&script_path, script)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(())
+    }
 
-        let forge_version = &test_case.forge_version;
-        let yaml_content = format!(
-            r#"_forge_version: "{forge_version}"
-{table_data}assumptions:
-  test_result:
-    value: null
-    formula: "{escaped_formula}"
-"#
+    /// Writes a generated forge YAML, exports it to XLSX with forge (under
+    /// the given scenario, if any) and recalculates it with Gnumeric in the
+    /// given date system. Returns the CSV of every sheet.
+    fn export_and_recalc(
+        &self,
+        yaml_content: &str,
+        dir: &Path,
+        stem: &str,
+        date_system: DateSystem,
+        scenario: Option<&str>,
+        timings: &mut Timings,
+    ) -> Result<Vec<SheetCsv>, String> {
+        let yaml_path = dir.join(format!("{stem}.yaml"));
+        let xlsx_path = dir.join(format!("{stem}.xlsx"));
+
+        timed(&mut timings.yaml, || fs::write(&yaml_path, yaml_content))
+            .map_err(|e| format!("Failed to write YAML: {e}"))?;
+
+        let output = timed(&mut timings.export, || {
+            let mut command = Command::new(&self.forge_binary);
+            command.arg("export");
+            if let Some(scenario) = scenario {
+                command.arg("--scenario").arg(scenario);
+            }
+            command.arg(&yaml_path).arg(&xlsx_path).output()
+        })
+        .map_err(|e| format!("Failed to run forge: {e}"))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "forge export failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        if date_system == DateSystem::Excel1904 {
+            timed(&mut timings.export, || excel::set_date1904(&xlsx_path))?;
+        }
+
+        timed(&mut timings.recalc, || {
+            self.engine.xlsx_to_csv_all_sheets(&xlsx_path, dir)
+        })
+        .map_err(|e| format!("CSV conversion failed: {e}"))
+    }
+
+    /// Runs a dependent scalar chain.
+    ///
+    /// All scalars of the chain are exported together in one model, and
+    /// every scalar with an expected value yields one result.
+    #[must_use]
+    pub fn run_chain(&self, chain: &ChainCase) -> Vec<TestResult> {
+        let mut timings = Timings::default();
+        let fail_all = |error: &str, timings: Timings| -> Vec<TestResult> {
+            let share = timings.share(chain.assertions().count());
+            chain
+                .assertions()
+                .map(|(scalar, expected)| TestResult::Fail {
+                    name: chain.assertion_name(scalar),
+                    formula: scalar.formula.clone().unwrap_or_default(),
+                    expected,
+                    actual: None,
+                    error: Some(error.to_string()),
+                    timings: share,
+                    artifacts: None,
+                    kind: scalar.kind,
+                    assertion: None,
+                })
+                .collect()
+        };
+
+        let yaml_content = match timed(&mut timings.yaml, || {
+            let mut model = Self::source_model(chain.source_file.as_deref(), &chain.forge_version);
+            for scalar in &chain.scalars {
+                model.add_scalar(&scalar.name, scalar.value, scalar.formula.as_deref());
+            }
+            model.to_yaml()
+        }) {
+            Ok(yaml) => yaml,
+            Err(e) => return fail_all(&e, timings),
+        };
+
+        let temp_dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => return fail_all(&format!("Failed to create temp dir: {e}"), timings),
+        };
+        let mut results = 'run: {
+            let sheets = match self.export_and_recalc(
+                &yaml_content,
+                temp_dir.path(),
+                "chain",
+                chain.date_system,
+                None,
+                &mut timings,
+            ) {
+                Ok(sheets) => sheets,
+                Err(e) => break 'run fail_all(&e, timings),
+            };
+
+            let sheet = match find_sheet(&sheets, ASSUMPTIONS) {
+                Ok(sheet) => sheet,
+                Err(e) => break 'run fail_all(&e, timings),
+            };
+            let actuals: Vec<Option<f64>> = timed(&mut timings.parse, || {
+                chain
+                    .assertions()
+                    .map(|(scalar, _)| Self::find_labeled_value(&sheet.path, &scalar.name))
+                    .collect()
+            });
+            // A 1904 run also checks the values forge cached itself
+            let forge_rows = match chain.date_system {
+                DateSystem::Excel1900 => None,
+                DateSystem::Excel1904 => Some(timed(&mut timings.parse, || {
+                    Self::forge_assumptions(&temp_dir.path().join("chain.xlsx"))
+                })),
+            };
+            let share = timings.share(actuals.len());
+
+            chain
+                .assertions()
+                .zip(actuals)
+                .map(|((scalar, expected), actual)| {
+                    let name = chain.assertion_name(scalar);
+                    let formula = scalar.formula.clone().unwrap_or_default();
+                    let forge = match &forge_rows {
+                        None => Ok(()),
+                        Some(Ok(rows)) => {
+                            self.check_forge_value(rows, &scalar.name, expected, scalar.kind)
+                        }
+                        Some(Err(e)) => Err(e.clone()),
+                    };
+                    match (actual, forge) {
+                        (Some(actual), Ok(())) if self.matches(actual, expected) => {
+                            TestResult::Pass {
+                                name,
+                                formula,
+                                expected,
+                                actual,
+                                timings: share,
+                            }
+                        }
+                        (Some(actual), forge) => TestResult::Fail {
+                            name,
+                            formula,
+                            expected,
+                            actual: Some(actual),
+                            error: forge.err(),
+                            timings: share,
+                            artifacts: None,
+                            kind: scalar.kind,
+                            assertion: None,
+                        },
+                        (None, _) => TestResult::Fail {
+                            name,
+                            formula,
+                            expected,
+                            actual: None,
+                            error: Some(format!(
+                                "Could not find scalar `{}` on sheet `{ASSUMPTIONS}`",
+                                scalar.name
+                            )),
+                            timings: share,
+                            artifacts: None,
+                            kind: scalar.kind,
+                            assertion: None,
+                        },
+                    }
+                })
+                .collect()
+        };
+        self.keep_artifacts(
+            temp_dir.path(),
+            "chain",
+            ForgeStep::Export(chain.date_system),
+            &self.artifact_key(chain.source_file.as_deref(), &chain.name),
+            &mut results,
         );
+        results
+    }
+
+    /// Runs a table with asserted calculated columns.
+    ///
+    /// The table is exported with forge, its sheet is located in Gnumeric's
+    /// recalculated output, and every row of every asserted column yields
+    /// one result.
+    #[must_use]
+    pub fn run_table(&self, table: &TableCase) -> Vec<TestResult> {
+        let mut timings = Timings::default();
+
+        let yaml_content = match timed(&mut timings.yaml, || {
+            let spec = match table.source_file.as_deref().map(Self::load_spec) {
+                Some(Ok(spec)) => spec,
+                Some(Err(e)) => return Err(e.to_string()),
+                None => return Err("Table has no source spec".into()),
+            };
+            let mut model = ForgeModel::new(&table.forge_version);
+            model.add_spec_data(&spec);
+            model.to_yaml()
+        }) {
+            Ok(yaml) => yaml,
+            Err(e) => return self.table_results(table, timings, |_, _| Err(e.clone())),
+        };
 
         let temp_dir = match tempfile::tempdir() {
             Ok(d) => d,
             Err(e) => {
-                return TestResult::Fail {
-                    name: test_case.name.clone(),
-                    formula: test_case.formula.clone(),
-                    expected: test_case.expected,
-                    actual: None,
-                    error: Some(format!("Failed to create temp dir: {e}")),
-                };
+                return self.table_results(table, timings, |_, _| {
+                    Err(format!("Failed to create temp dir: {e}"))
+                })
             }
         };
+        let mut results = 'run: {
+            let sheets = match self.export_and_recalc(
+                &yaml_content,
+                temp_dir.path(),
+                "table",
+                DateSystem::Excel1900,
+                None,
+                &mut timings,
+            ) {
+                Ok(sheets) => sheets,
+                Err(e) => break 'run self.table_results(table, timings, |_, _| Err(e.clone())),
+            };
 
-        let yaml_path = temp_dir.path().join("test.yaml");
-        let xlsx_path = temp_dir.path().join("test.xlsx");
-
-        if let Err(e) = fs::write(&yaml_path, &yaml_content) {
-            return TestResult::Fail {
-                name: test_case.name.clone(),
-                formula: test_case.formula.clone(),
-                expected: test_case.expected,
-                actual: None,
-                error: Some(format!("Failed to write YAML: {e}")),
+            let column_names: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+            let sheet = timed(&mut timings.parse, || {
+                let sheet = find_sheet(&sheets, &table.name)?;
+                Self::read_table_columns(&sheet.path, &column_names).ok_or_else(|| {
+                    format!(
+                        "Sheet `{}` has no header row with columns {}",
+                        sheet.sheet,
+                        column_names.join(", ")
+                    )
+                })
+            });
+            let sheet = match sheet {
+                Ok(sheet) => sheet,
+                Err(e) => break 'run self.table_results(table, timings, |_, _| Err(e.clone())),
             };
+
+            self.table_results(table, timings, |column, row| {
+                let cell = sheet[column]
+                    .get(row)
+                    .ok_or_else(|| format!("Table has no row {row}"))?;
+                excel::parse_number(cell).ok_or_else(|| format!("Non-numeric cell: {cell}"))
+            })
+        };
+        self.keep_artifacts(
+            temp_dir.path(),
+            "table",
+            ForgeStep::Export(DateSystem::Excel1900),
+            &self.artifact_key(table.source_file.as_deref(), &table.name),
+            &mut results,
+        );
+        results
+    }
+
+    /// Builds one result per asserted row from the actual value of each
+    /// `(column index, row)`, sharing the table's timings among them.
+    fn table_results<F>(
+        &self,
+        table: &TableCase,
+        timings: Timings,
+        actual_value: F,
+    ) -> Vec<TestResult>
+    where
+        F: Fn(usize, usize) -> Result<f64, String>,
+    {
+        let mut results = Vec::new();
+        let share = timings.share(table.assertion_count());
+
+        for (column_index, column) in table.columns.iter().enumerate() {
+            for (row, &expected) in column.expected.iter().enumerate() {
+                let name = table.row_name(column, row);
+                let formula = column.formula.clone();
+                results.push(match actual_value(column_index, row) {
+                    Ok(actual) if self.matches(actual, expected) => TestResult::Pass {
+                        name,
+                        formula,
+                        expected,
+                        actual,
+                        timings: share,
+                    },
+                    Ok(actual) => TestResult::Fail {
+                        name,
+                        formula,
+                        expected,
+                        actual: Some(actual),
+                        error: None,
+                        timings: share,
+                        artifacts: None,
+                        kind: ValueKind::Number,
+                        assertion: None,
+                    },
+                    Err(error) => TestResult::Fail {
+                        name,
+                        formula,
+                        expected,
+                        actual: None,
+                        error: Some(error),
+                        timings: share,
+                        artifacts: None,
+                        kind: ValueKind::Number,
+                        assertion: None,
+                    },
+                });
+            }
         }
 
-        // Run forge export
-        let output = match Command::new(&self.forge_binary)
-            .arg("export")
-            .arg(&yaml_path)
-            .arg(&xlsx_path)
-            .output()
-        {
-            Ok(o) => o,
-            Err(e) => {
-                return TestResult::Fail {
-                    name: test_case.name.clone(),
-                    formula: test_case.formula.clone(),
-                    expected: test_case.expected,
+        results
+    }
+
+    /// Reads the cells below each named column header of a CSV sheet.
+    ///
+    /// Returns `None` unless a single header row contains every column.
+    fn read_table_columns(csv_path: &Path, columns: &[&str]) -> Option<Vec<Vec<String>>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(csv_path)
+            .ok()?;
+        let mut records = reader.records().filter_map(Result::ok);
+
+        let header = records.by_ref().find(|record| {
+            columns
+                .iter()
+                .all(|column| record.iter().any(|cell| cell.trim() == *column))
+        })?;
+        let indices: Vec<usize> = columns
+            .iter()
+            .filter_map(|column| header.iter().position(|cell| cell.trim() == *column))
+            .collect();
+
+        let mut values = vec![Vec::new(); columns.len()];
+        for record in records {
+            if record.iter().all(|cell| cell.trim().is_empty()) {
+                break;
+            }
+            for (column_values, &index) in values.iter_mut().zip(&indices) {
+                column_values.push(record.get(index).unwrap_or_default().trim().to_string());
+            }
+        }
+
+        Some(values)
+    }
+
+    /// Returns `true` if a cell is the label of a scalar (`name` or
+    /// `section.name`).
+    fn is_label(cell: &str, label: &str) -> bool {
+        let cell = cell.trim();
+        cell == label
+            || cell
+                .strip_suffix(label)
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    }
+
+    /// Finds the raw cell next to a scalar label in a CSV sheet.
+    fn find_labeled_cell(csv_path: &Path, label: &str) -> Option<String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(csv_path)
+            .ok()?;
+
+        reader.records().filter_map(Result::ok).find_map(|record| {
+            let position = record.iter().position(|cell| Self::is_label(cell, label))?;
+            record.get(position + 1).map(str::to_string)
+        })
+    }
+
+    /// Evaluates a formula with forge and with Gnumeric, next to the table
+    /// data of `source_file` if given.
+    ///
+    /// Returns `(forge, gnumeric)`: forge's result is the value it caches
+    /// next to the formula in the exported XLSX, Gnumeric's is its
+    /// recalculation of that workbook.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if forge rejects the formula, recalculation fails
+    /// or the result cell cannot be found.
+    pub fn evaluate_formula(
+        &self,
+        formula: &str,
+        source_file: Option<&Path>,
+    ) -> Result<(CellValue, CellValue), String> {
+        const LABEL: &str = "eval_result";
+
+        let mut model = Self::source_model(source_file, "1.0.0");
+        model.add_scalar(LABEL, None, Some(formula));
+        let yaml_content = model.to_yaml()?;
+
+        let temp_dir =
+            tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {e}"))?;
+        let csv_sheets = self.export_and_recalc(
+            &yaml_content,
+            temp_dir.path(),
+            "eval",
+            DateSystem::Excel1900,
+            None,
+            &mut Timings::default(),
+        )?;
+
+        let sheets = excel::read_xlsx(&temp_dir.path().join("eval.xlsx"))?;
+        let forge = sheets
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(ASSUMPTIONS))
+            .and_then(|(_, rows)| Self::labeled_value(rows, LABEL))
+            .ok_or_else(|| format!("No `{LABEL}` on forge's `{ASSUMPTIONS}` sheet"))?;
+
+        let sheet = find_sheet(&csv_sheets, ASSUMPTIONS)?;
+        let gnumeric = Self::find_labeled_cell(&sheet.path, LABEL)
+            .map(|cell| CellValue::from_csv(&cell))
+            .ok_or_else(|| format!("No `{LABEL}` on sheet `{}`", sheet.sheet))?;
+
+        Ok((forge, gnumeric))
+    }
+
+    /// Runs a distribution test: draws its samples from Gnumeric and checks
+    /// every asserted statistic, one result each.
+    #[must_use]
+    pub fn run_distribution(&self, case: &DistributionCase) -> Vec<TestResult> {
+        const STEM: &str = "distribution";
+
+        let mut timings = Timings::default();
+        let names = case.assertion_names();
+        let fail_all = |error: &str, timings: Timings| -> Vec<TestResult> {
+            let share = timings.share(names.len());
+            names
+                .iter()
+                .map(|name| TestResult::Fail {
+                    name: name.clone(),
+                    formula: case.formula.clone(),
+                    expected: 0.0,
                     actual: None,
-                    error: Some(format!("Failed to run forge: {e}")),
+                    error: Some(error.to_string()),
+                    timings: share,
+                    artifacts: None,
+                    kind: ValueKind::Number,
+                    assertion: Some(format!(
+                        "statistics of {} samples",
+                        case.distribution.samples
+                    )),
+                })
+                .collect()
+        };
+
+        // A range fills every sample into the workbook; recalculation
+        // samples one cell
+        let cells = match case.distribution.sampling {
+            Sampling::Range => case.distribution.samples,
+            Sampling::Recalc => 1,
+        };
+        let yaml_content = match timed(&mut timings.yaml, || {
+            let mut model = Self::source_model(case.source_file.as_deref(), &case.forge_version);
+            for i in 0..cells {
+                model.add_scalar(&format!("test_{i}"), None, Some(&case.formula));
+            }
+            model.to_yaml()
+        }) {
+            Ok(yaml) => yaml,
+            Err(e) => return fail_all(&e, timings),
+        };
+
+        let temp_dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => return fail_all(&format!("Failed to create temp dir: {e}"), timings),
+        };
+        let mut results = 'run: {
+            let mut sheets = match self.export_and_recalc(
+                &yaml_content,
+                temp_dir.path(),
+                STEM,
+                DateSystem::Excel1900,
+                None,
+                &mut timings,
+            ) {
+                Ok(sheets) => sheets,
+                Err(e) => break 'run fail_all(&e, timings),
+            };
+
+            let mut samples = Vec::with_capacity(case.distribution.samples);
+            loop {
+                let parsed = timed(&mut timings.parse, || {
+                    Self::parse_batch_csv(find_sheet(&sheets, ASSUMPTIONS)?, cells)
+                        .into_iter()
+                        .collect::<Result<Vec<f64>, String>>()
+                });
+                match parsed {
+                    Ok(values) => samples.extend(values),
+                    Err(e) => {
+                        break 'run fail_all(&format!("Sample {}: {e}", samples.len()), timings)
+                    }
+                }
+                if samples.len() >= case.distribution.samples {
+                    break;
+                }
+                let xlsx_path = temp_dir.path().join(format!("{STEM}.xlsx"));
+                sheets = match timed(&mut timings.recalc, || {
+                    self.engine
+                        .xlsx_to_csv_all_sheets(&xlsx_path, temp_dir.path())
+                }) {
+                    Ok(sheets) => sheets,
+                    Err(e) => break 'run fail_all(&format!("CSV conversion failed: {e}"), timings),
                 };
             }
+
+            let share = timings.share(names.len());
+            names
+                .iter()
+                .zip(case.check(&samples))
+                .map(|(name, check)| {
+                    if check.passed {
+                        TestResult::Pass {
+                            name: name.clone(),
+                            formula: case.formula.clone(),
+                            expected: check.expected,
+                            actual: check.actual,
+                            timings: share,
+                        }
+                    } else {
+                        TestResult::Fail {
+                            name: name.clone(),
+                            formula: case.formula.clone(),
+                            expected: check.expected,
+                            actual: Some(check.actual),
+                            error: None,
+                            timings: share,
+                            artifacts: None,
+                            kind: ValueKind::Number,
+                            assertion: Some(check.description),
+                        }
+                    }
+                })
+                .collect()
         };
+        self.keep_artifacts(
+            temp_dir.path(),
+            STEM,
+            ForgeStep::Export(DateSystem::Excel1900),
+            &self.artifact_key(case.source_file.as_deref(), &case.name),
+            &mut results,
+        );
+        results
+    }
 
-        if !output.status.success() {
-            return TestResult::Fail {
+    /// Runs an import workbook: writes it, imports and calculates it with
+    /// forge, recalculates the original workbook with Gnumeric and compares
+    /// every formula cell, one result each.
+    ///
+    /// Gnumeric's recalculation is the expected value, forge's the actual.
+    #[must_use]
+    pub fn run_import(&self, case: &ImportCase) -> Vec<TestResult> {
+        const STEM: &str = "import";
+
+        let mut timings = Timings::default();
+        let formulas = case.formulas();
+        let fail_all = |error: &str, timings: Timings| {
+            self.import_results(&formulas, timings, |_| {
+                (Err(error.to_string()), Err(error.to_string()))
+            })
+        };
+
+        let temp_dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => return fail_all(&format!("Failed to create temp dir: {e}"), timings),
+        };
+        let dir = temp_dir.path();
+        let xlsx_path = dir.join(format!("{STEM}.xlsx"));
+
+        let mut results = 'run: {
+            if let Err(e) = timed(&mut timings.yaml, || {
+                excel::write_workbook(&xlsx_path, &case.sheets)
+            }) {
+                break 'run fail_all(&format!("Failed to write workbook: {e}"), timings);
+            }
+            let forge_xlsx = match timed(&mut timings.export, || self.forge_import(dir, STEM)) {
+                Ok(path) => path,
+                Err(e) => break 'run fail_all(&e, timings),
+            };
+            let csv_sheets = match timed(&mut timings.recalc, || {
+                self.engine.xlsx_to_csv_all_sheets(&xlsx_path, dir)
+            }) {
+                Ok(sheets) => sheets,
+                Err(e) => break 'run fail_all(&format!("CSV conversion failed: {e}"), timings),
+            };
+
+            let sheets = timed(&mut timings.parse, || {
+                let forge = excel::read_xlsx(&forge_xlsx)?;
+                let gnumeric = csv_sheets
+                    .iter()
+                    .map(|sheet| Ok((sheet.sheet.clone(), Self::csv_cells(&sheet.path)?)))
+                    .collect::<Result<SheetData, String>>()?;
+                Ok::<_, String>((forge, gnumeric))
+            });
+            let (forge, gnumeric) = match sheets {
+                Ok(sheets) => sheets,
+                Err(e) => break 'run fail_all(&e, timings),
+            };
+
+            self.import_results(&formulas, timings, |cell| {
+                (
+                    Self::import_value(&gnumeric, cell).map_err(|e| format!("Gnumeric: {e}")),
+                    Self::import_value(&forge, cell).map_err(|e| format!("forge: {e}")),
+                )
+            })
+        };
+        self.keep_artifacts(
+            dir,
+            STEM,
+            ForgeStep::Import,
+            &self.artifact_key(case.source_file.as_deref(), STEM),
+            &mut results,
+        );
+        results
+    }
+
+    /// Imports `{stem}.xlsx` into a forge model and exports the model again,
+    /// so that the new workbook caches forge's calculated results. Returns
+    /// the path of that workbook.
+    fn forge_import(&self, dir: &Path, stem: &str) -> Result<PathBuf, String> {
+        let xlsx_path = dir.join(format!("{stem}.xlsx"));
+        let yaml_path = dir.join(format!("{stem}.yaml"));
+        let forge_path = dir.join(format!("{stem}{FORGE_SUFFIX}.xlsx"));
+
+        for (command, input, output) in [
+            ("import", &xlsx_path, &yaml_path),
+            ("export", &yaml_path, &forge_path),
+        ] {
+            let result = Command::new(&self.forge_binary)
+                .arg(command)
+                .arg(input)
+                .arg(output)
+                .output()
+                .map_err(|e| format!("Failed to run forge: {e}"))?;
+            if !result.status.success() {
+                return Err(format!(
+                    "forge {command} failed: {}",
+                    String::from_utf8_lossy(&result.stderr)
+                ));
+            }
+        }
+
+        Ok(forge_path)
+    }
+
+    /// Builds one result per formula cell from its `(gnumeric, forge)`
+    /// values, sharing the workbook's timings among them.
+    fn import_results<F>(
+        &self,
+        formulas: &[ImportFormula],
+        timings: Timings,
+        values: F,
+    ) -> Vec<TestResult>
+    where
+        F: Fn(&ImportFormula) -> (Result<f64, String>, Result<f64, String>),
+    {
+        let share = timings.share(formulas.len());
+
+        formulas
+            .iter()
+            .map(|cell| {
+                let name = cell.name.clone();
+                let formula = cell.formula.clone();
+                let fail = |expected, actual, error, assertion| TestResult::Fail {
+                    name: name.clone(),
+                    formula: formula.clone(),
+                    expected,
+                    actual,
+                    error,
+                    timings: share,
+                    artifacts: None,
+                    kind: ValueKind::Number,
+                    assertion,
+                };
+                match values(cell) {
+                    // Without Gnumeric's value there is nothing to expect
+                    (Err(e), _) => fail(
+                        0.0,
+                        None,
+                        Some(e),
+                        Some("Gnumeric's recalculation".to_string()),
+                    ),
+                    (Ok(expected), Err(e)) => fail(expected, None, Some(e), None),
+                    (Ok(expected), Ok(actual)) if self.matches(actual, expected) => {
+                        TestResult::Pass {
+                            name: name.clone(),
+                            formula: formula.clone(),
+                            expected,
+                            actual,
+                            timings: share,
+                        }
+                    }
+                    (Ok(expected), Ok(actual)) => fail(expected, Some(actual), None, None),
+                }
+            })
+            .collect()
+    }
+
+    /// Finds the numeric value of an import formula cell on the workbook
+    /// sheet of the same name: next to its label for scalars, below its
+    /// column header for table cells.
+    fn import_value(sheets: &SheetData, cell: &ImportFormula) -> Result<f64, String> {
+        let (_, rows) = sheets
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&cell.sheet))
+            .ok_or_else(|| format!("No sheet `{}`", cell.sheet))?;
+
+        let value = match cell.row {
+            None => Self::labeled_value(rows, &cell.label),
+            Some(row) => Self::column_value(rows, &cell.label, row),
+        }
+        .ok_or_else(|| format!("Could not find `{}` on sheet `{}`", cell.name, cell.sheet))?;
+
+        value
+            .as_number()
+            .ok_or_else(|| format!("Non-numeric cell: {value:?}"))
+    }
+
+    /// Finds the cell next to a scalar label (`name` or `section.name`).
+    fn labeled_value(rows: &[Vec<CellValue>], label: &str) -> Option<CellValue> {
+        rows.iter().find_map(|row| {
+            let position = row.iter().position(|cell| {
+                cell.as_text()
+                    .is_some_and(|text| Self::is_label(text, label))
+            })?;
+            Some(row.get(position + 1).cloned().unwrap_or(CellValue::Empty))
+        })
+    }
+
+    /// Finds the cell of a table row (counting from 0) below a column
+    /// header.
+    fn column_value(rows: &[Vec<CellValue>], header: &str, row: usize) -> Option<CellValue> {
+        let is_header = |cell: &CellValue| cell.as_text().is_some_and(|text| text.trim() == header);
+        let (header_row, column) = rows.iter().enumerate().find_map(|(index, cells)| {
+            cells
+                .iter()
+                .position(is_header)
+                .map(|column| (index, column))
+        })?;
+        Some(
+            rows.get(header_row + 1 + row)
+                .and_then(|cells| cells.get(column))
+                .cloned()
+                .unwrap_or(CellValue::Empty),
+        )
+    }
+
+    /// Reads a CSV sheet as cell values.
+    fn csv_cells(csv_path: &Path) -> Result<Vec<Vec<CellValue>>, String> {
+        Ok(Self::csv_records(csv_path)?
+            .iter()
+            .map(|record| record.iter().map(CellValue::from_csv).collect())
+            .collect())
+    }
+
+    /// Finds the value next to a scalar label (`name` or `section.name`).
+    fn find_labeled_value(csv_path: &Path, label: &str) -> Option<f64> {
+        Self::find_labeled_cell(csv_path, label).and_then(|cell| excel::parse_number(&cell))
+    }
+
+    /// Runs a single test case.
+    #[must_use]
+    #[allow(clippy::too_many_lines)]
+    pub fn run_test(&self, test_case: &TestCase) -> TestResult {
+        let started = SystemTime::now();
+        let mut timings = Timings::default();
+        let fail =
+            |actual: Option<f64>, error: Option<String>, timings: Timings| TestResult::Fail {
                 name: test_case.name.clone(),
                 formula: test_case.formula.clone(),
                 expected: test_case.expected,
-                actual: None,
-                error: Some(format!(
-                    "forge export failed: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                )),
+                actual,
+                error,
+                timings,
+                artifacts: None,
+                kind: test_case.kind,
+                assertion: None,
             };
-        }
 
-        // Convert XLSX to CSV using Gnumeric (all sheets)
-        let csv_files = match self
-            .engine
-            .xlsx_to_csv_all_sheets(&xlsx_path, temp_dir.path())
-        {
-            Ok(files) => files,
+        let yaml_content = match timed(&mut timings.yaml, || {
+            let mut model =
+                Self::source_model(test_case.source_file.as_deref(), &test_case.forge_version);
+
+            // Export the spec's input scalars; forge applies the scenario
+            // itself
+            if let Some(scenario) = &test_case.scenario {
+                for (scalar_name, value) in &scenario.base {
+                    model.add_scalar(scalar_name, Some(*value), None);
+                }
+            }
+            model.add_scalar("test_result", None, Some(&test_case.formula));
+            model.to_yaml()
+        }) {
+            Ok(yaml) => yaml,
+            Err(e) => return fail(None, Some(e), timings),
+        };
+
+        let temp_dir = match tempfile::tempdir() {
+            Ok(d) => d,
             Err(e) => {
-                return TestResult::Fail {
-                    name: test_case.name.clone(),
-                    formula: test_case.formula.clone(),
-                    expected: test_case.expected,
-                    actual: None,
-                    error: Some(format!("CSV conversion failed: {e}")),
-                };
+                return fail(
+                    None,
+                    Some(format!("Failed to create temp dir: {e}")),
+                    timings,
+                )
             }
         };
+        let mut result = 'run: {
+            let sheets = match self.export_and_recalc(
+                &yaml_content,
+                temp_dir.path(),
+                "test",
+                test_case.date_system,
+                test_case.scenario.as_ref().map(|s| s.name.as_str()),
+                &mut timings,
+            ) {
+                Ok(sheets) => sheets,
+                Err(e) => break 'run fail(None, Some(e), timings),
+            };
+            if let Some(volatile) = &test_case.volatile {
+                let clock = WallClock {
+                    start: test_case
+                        .date_system
+                        .from_1900(serial_from_system_time(started)),
+                    end: test_case
+                        .date_system
+                        .from_1900(serial_from_system_time(SystemTime::now())),
+                };
+                break 'run Self::check_volatile(test_case, volatile, &sheets, clock, timings);
+            }
+
+            let actual = timed(&mut timings.parse, || {
+                let cell = Self::result_cell(&sheets)?;
+                excel::parse_number(&cell).ok_or_else(|| format!("Non-numeric result: {cell}"))
+            });
+            let forge = match test_case.date_system {
+                DateSystem::Excel1900 => Ok(()),
+                DateSystem::Excel1904 => timed(&mut timings.parse, || {
+                    let rows = Self::forge_assumptions(&temp_dir.path().join("test.xlsx"))?;
+                    self.check_forge_value(&rows, "test_result", test_case.expected, test_case.kind)
+                }),
+            };
 
-        // Search all sheets for the result
-        for csv_path in &csv_files {
-            if let Ok(actual) = Self::find_result_in_csv(csv_path, test_case.expected) {
-                if (actual - test_case.expected).abs() < f64::EPSILON {
-                    return TestResult::Pass {
+            match (actual, forge) {
+                (Ok(actual), Ok(())) if self.matches(actual, test_case.expected) => {
+                    TestResult::Pass {
                         name: test_case.name.clone(),
                         formula: test_case.formula.clone(),
                         expected: test_case.expected,
                         actual,
-                    };
+                        timings,
+                    }
                 }
+                (Ok(actual), forge) => fail(Some(actual), forge.err(), timings),
+                (Err(e), _) => fail(None, Some(e), timings),
+            }
+        };
+        self.keep_artifacts(
+            temp_dir.path(),
+            "test",
+            test_case
+                .scenario
+                .as_ref()
+                .map_or(ForgeStep::Export(test_case.date_system), |scenario| {
+                    ForgeStep::Scenario(&scenario.name)
+                }),
+            &self.artifact_key(test_case.source_file.as_deref(), &test_case.name),
+            std::slice::from_mut(&mut result),
+        );
+        result
+    }
+
+    /// Checks the result of a volatile test against its assertions.
+    fn check_volatile(
+        test_case: &TestCase,
+        volatile: &VolatileExpectation,
+        sheets: &[SheetCsv],
+        clock: WallClock,
+        mut timings: Timings,
+    ) -> TestResult {
+        let cell = timed(&mut timings.parse, || {
+            Self::result_cell(sheets).map(|cell| CellValue::from_csv(&cell))
+        });
+        let cell = match cell {
+            Ok(cell) => cell,
+            Err(e) => {
                 return TestResult::Fail {
                     name: test_case.name.clone(),
                     formula: test_case.formula.clone(),
                     expected: test_case.expected,
-                    actual: Some(actual),
-                    error: None,
+                    actual: None,
+                    error: Some(e),
+                    timings,
+                    artifacts: None,
+                    kind: test_case.kind,
+                    assertion: Some(volatile.describe()),
                 };
             }
-        }
-
-        TestResult::Fail {
-            name: test_case.name.clone(),
-            formula: test_case.formula.clone(),
-            expected: test_case.expected,
-            actual: None,
-            error: Some("Could not find result in any CSV sheet".to_string()),
+        };
+        match volatile.check(&cell, clock) {
+            Ok(()) => TestResult::Pass {
+                name: test_case.name.clone(),
+                formula: test_case.formula.clone(),
+                expected: cell.as_number().unwrap_or(test_case.expected),
+                actual: cell.as_number().unwrap_or(test_case.expected),
+                timings,
+            },
+            Err(error) => TestResult::Fail {
+                name: test_case.name.clone(),
+                formula: test_case.formula.clone(),
+                expected: test_case.expected,
+                actual: cell.as_number(),
+                error: Some(error),
+                timings,
+                artifacts: None,
+                kind: test_case.kind,
+                assertion: Some(volatile.describe()),
+            },
         }
     }
 
-    fn find_result_in_csv(csv_path: &Path, expected: f64) -> Result<f64, String> {
-        let file = fs::File::open(csv_path).map_err(|e| format!("Failed to open CSV: {e}"))?;
-        let reader = BufReader::new(file);
-
-        for line in reader.lines() {
-            let line = line.map_err(|e| format!("Failed to read line: {e}"))?;
-            let cells: Vec<&str> = line
-                .split(',')
-                .map(|s| s.trim_matches('"').trim())
-                .collect();
-
-            for (i, cell) in cells.iter().enumerate() {
-                if (*cell == "result" || *cell == "test_result") && i + 1 < cells.len() {
-                    if let Ok(value) = cells[i + 1].replace(',', "").parse::<f64>() {
-                        return Ok(value);
-                    }
-                }
+    /// Reads the assumptions sheet of forge's exported workbook, with the
+    /// values forge cached next to its formulas.
+    fn forge_assumptions(xlsx_path: &Path) -> Result<Vec<Vec<CellValue>>, String> {
+        excel::read_xlsx(xlsx_path)?
+            .into_iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(ASSUMPTIONS))
+            .map(|(_, rows)| rows)
+            .ok_or_else(|| format!("No `{ASSUMPTIONS}` sheet in forge's workbook"))
+    }
 
-                if let Ok(value) = cell.replace(',', "").parse::<f64>() {
-                    if (value - expected).abs() < 0.0001 {
-                        return Ok(value);
-                    }
-                }
-            }
+    /// Checks the value forge cached for a label in a 1904 run.
+    ///
+    /// Forge calculated it before the workbook was switched to the 1904
+    /// date system, so a date is expected as a 1900-system serial.
+    fn check_forge_value(
+        &self,
+        rows: &[Vec<CellValue>],
+        label: &str,
+        expected: f64,
+        kind: ValueKind,
+    ) -> Result<(), String> {
+        let expected = kind.to_1900(expected);
+        match Self::labeled_value(rows, label) {
+            Some(CellValue::Number(cached)) if self.matches(cached, expected) => Ok(()),
+            Some(cached) => Err(format!(
+                "forge cached {}, expected {expected} (1900 date system)",
+                describe(&cached)
+            )),
+            None => Err(format!("No `{label}` in forge's workbook")),
         }
+    }
 
-        Err("Could not find result in CSV output".to_string())
+    /// Returns the raw `test_result` cell of the assumptions sheet.
+    fn result_cell(sheets: &[SheetCsv]) -> Result<String, String> {
+        let sheet = find_sheet(sheets, ASSUMPTIONS)?;
+        Self::find_labeled_cell(&sheet.path, "test_result")
+            .ok_or_else(|| format!("No `test_result` on sheet `{}`", sheet.sheet))
+    }
+
+    /// Reads every record of a CSV file (quoted cells may contain commas).
+    fn csv_records(csv_path: &Path) -> Result<Vec<csv::StringRecord>, String> {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(csv_path)
+            .map_err(|e| format!("Failed to open CSV: {e}"))?
+            .records()
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read CSV: {e}"))
     }
 }
 
@@ -550,14 +2253,25 @@ impl TestRunner {
 mod tests {
     use super::*;
 
+    #[test]
+    fn keep_artifacts_parses_and_quotes_repro_paths() {
+        assert_eq!("failed".parse(), Ok(KeepArtifacts::Failed));
+        assert_eq!("all".parse(), Ok(KeepArtifacts::All));
+        assert!("some".parse::<KeepArtifacts>().is_err());
+
+        assert_eq!(shell_quote("/opt/forge"), "'/opt/forge'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
     #[test]
     fn load_empty_dir_returns_empty_cases() {
         let temp_dir = tempfile::tempdir().unwrap();
         let result = TestRunner::load_test_cases(temp_dir.path());
         assert!(result.is_ok());
-        let (cases, skips) = result.unwrap();
-        assert!(cases.is_empty());
-        assert!(skips.is_empty());
+        let loaded = result.unwrap();
+        assert!(loaded.tests.is_empty());
+        assert!(loaded.chains.is_empty());
+        assert!(loaded.skips.is_empty());
     }
 
     #[test]
@@ -581,7 +2295,276 @@ assumptions:
 
         let result = TestRunner::load_test_cases(temp_dir.path());
         assert!(result.is_ok());
-        let (cases, _) = result.unwrap();
-        assert_eq!(cases.len(), 1);
+        assert_eq!(result.unwrap().tests.len(), 1);
+    }
+
+    #[test]
+    fn load_records_files_in_sorted_order_with_their_skips() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spec = |skip: &str| {
+            format!(
+                "_forge_version: \"1.0.0\"\nassumptions:\n  test_one:\n    formula: \"=1\"\n    expected: 1\n  test_two:\n    formula: \"=2\"\n    expected: 2\n    skip: \"{skip}\"\n"
+            )
+        };
+        fs::create_dir(temp_dir.path().join("b")).unwrap();
+        fs::write(temp_dir.path().join("b/spec.yaml"), spec("in b")).unwrap();
+        fs::write(temp_dir.path().join("a.yaml"), spec("in a")).unwrap();
+        fs::write(
+            temp_dir.path().join("empty.yaml"),
+            "_forge_version: \"1.0.0\"\n",
+        )
+        .unwrap();
+
+        let suite = TestRunner::load_test_cases(temp_dir.path()).unwrap();
+        assert_eq!(
+            suite.files,
+            vec![
+                temp_dir.path().join("a.yaml"),
+                temp_dir.path().join("b/spec.yaml")
+            ]
+        );
+        assert_eq!(suite.skips[0].reason, "in a");
+        assert_eq!(suite.skips[1].source_file.as_ref(), Some(&suite.files[1]));
+    }
+
+    #[test]
+    fn find_labeled_value_matches_plain_and_qualified_labels() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let csv_path = temp_dir.path().join("sheet.csv");
+        fs::write(
+            &csv_path,
+            "Name,Value\nassumptions.revenue,100000\nprofit,60000\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            TestRunner::find_labeled_value(&csv_path, "revenue"),
+            Some(100_000.0)
+        );
+        assert_eq!(
+            TestRunner::find_labeled_value(&csv_path, "profit"),
+            Some(60_000.0)
+        );
+        assert_eq!(TestRunner::find_labeled_value(&csv_path, "costs"), None);
+    }
+
+    #[test]
+    fn find_labeled_cell_returns_raw_cell() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let csv_path = temp_dir.path().join("sheet.csv");
+        fs::write(
+            &csv_path,
+            "Name,Value\nassumptions.eval_result,#DIV/0!\nprevious_result,\"1,5\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            TestRunner::find_labeled_cell(&csv_path, "eval_result").as_deref(),
+            Some("#DIV/0!")
+        );
+        assert_eq!(
+            TestRunner::find_labeled_cell(&csv_path, "result"),
+            None,
+            "suffixes of other labels do not match"
+        );
+    }
+
+    #[test]
+    fn import_value_reads_only_the_cells_own_sheet() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spec_path = temp_dir.path().join("workbook.yaml");
+        fs::write(
+            &spec_path,
+            r#"
+_forge_version: "1.0.0"
+_workbook:
+  - name: Inputs
+    scalars:
+      - { name: price, value: 10 }
+      - { name: doubled, formula: "=B2*2" }
+  - name: orders
+    columns:
+      - { name: quantity, values: [1, 2] }
+      - { name: total, formula: "=A{{row}}*Inputs!B2" }
+"#,
+        )
+        .unwrap();
+        let loaded = TestRunner::load_test_cases(temp_dir.path()).unwrap();
+        assert_eq!(loaded.files, [spec_path]);
+        let formulas = loaded.imports[0].formulas();
+        assert_eq!(formulas.len(), 3);
+
+        let csv_path = temp_dir.path().join("orders.csv");
+        fs::write(&csv_path, "quantity,total\n1,10\n2,\"20\"\n").unwrap();
+        let text = |s: &str| CellValue::Text(s.to_string());
+        let sheets: SheetData = vec![
+            (
+                "Assumptions".to_string(),
+                vec![vec![text("doubled"), CellValue::Number(-1.0)]],
+            ),
+            (
+                "inputs".to_string(),
+                vec![vec![text("inputs.doubled"), CellValue::Number(20.0)]],
+            ),
+            (
+                "orders".to_string(),
+                TestRunner::csv_cells(&csv_path).unwrap(),
+            ),
+        ];
+
+        let value = |index: usize| TestRunner::import_value(&sheets, &formulas[index]);
+        assert_eq!(value(0), Ok(20.0));
+        assert_eq!(value(1), Ok(10.0));
+        assert_eq!(value(2), Ok(20.0));
+        let missing = ImportFormula {
+            row: Some(5),
+            ..formulas[2].clone()
+        };
+        assert!(TestRunner::import_value(&sheets, &missing).is_err());
+        let elsewhere = ImportFormula {
+            sheet: "Assumptions".to_string(),
+            ..formulas[1].clone()
+        };
+        assert!(TestRunner::import_value(&sheets, &elsewhere).is_err());
+    }
+
+    #[test]
+    fn parse_batch_csv_reads_raw_numbers_by_label() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sheet = SheetCsv {
+            sheet: "assumptions".to_string(),
+            path: temp_dir.path().join("batch_0.csv"),
+        };
+        fs::write(
+            &sheet.path,
+            "Name,Value\nassumptions.test_0,1.5\ntest_1,\"1234.5\"\ntest_2,\"1,234.5\"\ntest_3,12.5%\n",
+        )
+        .unwrap();
+
+        let results = TestRunner::parse_batch_csv(&sheet, 5);
+        assert_eq!(results[0], Ok(1.5));
+        assert_eq!(results[1], Ok(1234.5));
+        assert!(results[2].as_ref().unwrap_err().contains("1,234.5"));
+        assert!(results[3].is_err());
+        assert!(results[4].as_ref().unwrap_err().contains("Missing"));
+    }
+
+    #[test]
+    fn result_cell_reads_only_the_labeled_assumptions_cell() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sheet = |name: &str, content: &str| {
+            let path = temp_dir.path().join(format!("{name}.csv"));
+            fs::write(&path, content).unwrap();
+            SheetCsv {
+                sheet: name.to_string(),
+                path,
+            }
+        };
+        let sales = sheet("sales", "amount\n42\n");
+        let labeled = sheet("assumptions", "Name,Value\nassumptions.test_result,7\n");
+        let unlabeled = sheet("Assumptions", "Name,Value\nother,42\n");
+
+        assert_eq!(
+            TestRunner::result_cell(&[sales.clone(), labeled]).as_deref(),
+            Ok("7")
+        );
+        // A matching number elsewhere is not a result
+        assert!(TestRunner::result_cell(&[sales.clone(), unlabeled]).is_err());
+        assert!(TestRunner::result_cell(&[sales]).is_err());
+    }
+
+    #[test]
+    fn read_table_columns_finds_header_and_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let csv_path = temp_dir.path().join("sales.csv");
+        fs::write(
+            &csv_path,
+            "sales,,\nrevenue,cost,margin\n100,50,50\n200,120,80\n\"1,300\",150,\"1,150\"\n,,\nnotes,,\n",
+        )
+        .unwrap();
+
+        let columns = TestRunner::read_table_columns(&csv_path, &["margin", "cost"]).unwrap();
+        assert_eq!(columns[0], ["50", "80", "1,150"]);
+        assert_eq!(columns[1], ["50", "120", "150"]);
+
+        assert!(TestRunner::read_table_columns(&csv_path, &["margin", "missing"]).is_none());
+    }
+
+    #[test]
+    fn load_spec_merges_nearest_fixture() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spec_dir = temp_dir.path().join("functions");
+        let fixtures_dir = temp_dir.path().join(FIXTURES_DIR);
+        fs::create_dir_all(&spec_dir).unwrap();
+        fs::create_dir_all(&fixtures_dir).unwrap();
+        fs::write(fixtures_dir.join("grid.yaml"), "grid:\n  col_a: [1, 2]\n").unwrap();
+
+        let spec_path = spec_dir.join("lookup.yaml");
+        fs::write(
+            &spec_path,
+            "_forge_version: \"1.0.0\"\n_include: [grid]\nassumptions:\n  test_one:\n    formula: \"=SUM(grid.col_a)\"\n    expected: 3\n",
+        )
+        .unwrap();
+
+        let spec = TestRunner::load_spec(&spec_path).unwrap();
+        assert!(spec.sections.contains_key("grid"));
+
+        // The fixtures directory itself is not loaded as a spec
+        let loaded = TestRunner::load_test_cases(temp_dir.path()).unwrap();
+        assert_eq!(loaded.tests.len(), 1);
+    }
+
+    #[test]
+    fn load_spec_rejects_missing_fixture() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spec_path = temp_dir.path().join("spec.yaml");
+        fs::write(&spec_path, "_forge_version: \"1.0.0\"\n_include: [nope]\n").unwrap();
+
+        let err = TestRunner::load_spec(&spec_path).unwrap_err();
+        assert!(err.to_string().contains("fixture `nope` not found"));
+    }
+
+    #[test]
+    fn tag_filter_selects_files_by_their_tags() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spec_path = temp_dir.path().join("spec.yaml");
+        fs::write(
+            &spec_path,
+            "_forge_version: \"1.0.0\"\n_tags: [random, slow]\nassumptions:\n  test_one: { formula: \"=1\", expected: 1 }\n",
+        )
+        .unwrap();
+        let loaded = TestRunner::load_test_cases(temp_dir.path()).unwrap();
+        let tags = &loaded.tags[&spec_path];
+        assert_eq!(tags, &["random", "slow"]);
+
+        let filter = |tags: &[&str], skip_tags: &[&str]| TagFilter {
+            tags: tags.iter().map(ToString::to_string).collect(),
+            skip_tags: skip_tags.iter().map(ToString::to_string).collect(),
+        };
+        assert!(filter(&[], &[]).selects(tags));
+        assert!(filter(&["slow", "financial"], &[]).selects(tags));
+        assert!(!filter(&["financial"], &[]).selects(tags));
+        assert!(!filter(&[], &["random"]).selects(tags));
+        assert!(!filter(&["slow"], &["random"]).selects(tags));
+        assert!(filter(&[], &["random"]).selects(&[]));
+    }
+
+    #[test]
+    fn load_test_cases_rejects_unknown_table_refs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("spec.yaml"),
+            r#"
+_forge_version: "1.0.0"
+assumptions:
+  test_mirr:
+    formula: "=MIRR(mirr_investment.values, 0.1, 0.12)"
+    expected: 1
+"#,
+        )
+        .unwrap();
+
+        let err = TestRunner::load_test_cases(temp_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("`mirr_investment.values`"));
     }
 }
@@ -0,0 +1,332 @@
+//! Sample statistics for distribution tests.
+//!
+//! Random formulas (`RAND`, `RANDBETWEEN`, forge's Monte-Carlo functions)
+//! cannot be compared for equality. A distribution test draws samples from
+//! Gnumeric and checks their mean (z-test), variance (chi-square test) and
+//! shape (Kolmogorov-Smirnov test against a named distribution), each at a
+//! confidence level. A correct implementation still fails a check with
+//! probability `1 - confidence`, so the checks of a spec file split its
+//! confidence between them (Bonferroni correction).
+
+use std::fmt;
+
+use serde::Deserialize;
+
+/// A reference distribution for the Kolmogorov-Smirnov test.
+///
+/// Written in specs as a single-key map, e.g. `uniform: [0, 1]` (read with
+/// `serde_yaml_ng::with::singleton_map`).
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    /// Continuous uniform on `[min, max)`: `uniform: [0, 1]`.
+    Uniform(f64, f64),
+    /// Integers `min..=max`, equally likely: `uniform_int: [1, 6]`.
+    UniformInt(i64, i64),
+    /// Normal with mean and standard deviation: `normal: [0, 1]`.
+    Normal(f64, f64),
+    /// Exponential with rate λ: `exponential: 2`.
+    Exponential(f64),
+}
+
+impl Distribution {
+    /// Cumulative distribution function, `P(X <= x)`.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn cdf(&self, x: f64) -> f64 {
+        match *self {
+            Self::Uniform(min, max) => ((x - min) / (max - min)).clamp(0.0, 1.0),
+            Self::UniformInt(min, max) => {
+                let count = (max - min + 1) as f64;
+                ((x.floor() - min as f64 + 1.0) / count).clamp(0.0, 1.0)
+            }
+            Self::Normal(mean, std_dev) => normal_cdf((x - mean) / std_dev),
+            Self::Exponential(rate) => {
+                if x <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - (-rate * x).exp()
+                }
+            }
+        }
+    }
+
+    /// Left limit of the CDF, `P(X < x)`; differs from [`Self::cdf`] at
+    /// the values of a discrete distribution.
+    #[must_use]
+    pub fn cdf_before(&self, x: f64) -> f64 {
+        match self {
+            Self::UniformInt(..) if x.fract() == 0.0 => self.cdf(x - 1.0),
+            _ => self.cdf(x),
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uniform(min, max) => write!(f, "uniform({min}, {max})"),
+            Self::UniformInt(min, max) => write!(f, "uniform_int({min}, {max})"),
+            Self::Normal(mean, std_dev) => write!(f, "normal({mean}, {std_dev})"),
+            Self::Exponential(rate) => write!(f, "exponential({rate})"),
+        }
+    }
+}
+
+/// Outcome of one statistical check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatCheck {
+    /// The value the statistic is compared with (expected mean or variance,
+    /// or the KS critical value).
+    pub expected: f64,
+    /// The statistic of the samples.
+    pub actual: f64,
+    /// Whether the statistic is within the confidence bound.
+    pub passed: bool,
+    /// The bound, e.g. `mean 0.5 ± 0.0104 (99.9% confidence, 1000 samples)`.
+    pub description: String,
+}
+
+/// Arithmetic mean.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+/// Unbiased sample variance (divides by `n - 1`).
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn variance(samples: &[f64]) -> f64 {
+    let mean = mean(samples);
+    samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (samples.len() - 1) as f64
+}
+
+/// Two-sided z-test of the sample mean. The standard error uses
+/// `variance` if given, else the sample variance.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn mean_test(
+    samples: &[f64],
+    expected: f64,
+    variance: Option<f64>,
+    confidence: f64,
+) -> StatCheck {
+    let n = samples.len() as f64;
+    let actual = mean(samples);
+    let variance = variance.unwrap_or_else(|| self::variance(samples));
+    let bound = normal_quantile(f64::midpoint(1.0, confidence)) * (variance / n).sqrt();
+    StatCheck {
+        expected,
+        actual,
+        passed: (actual - expected).abs() <= bound,
+        description: format!(
+            "mean {expected} ± {bound:.4} ({})",
+            confidence_label(confidence, samples.len())
+        ),
+    }
+}
+
+/// Two-sided chi-square test of the sample variance.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn variance_test(samples: &[f64], expected: f64, confidence: f64) -> StatCheck {
+    let degrees = (samples.len() - 1) as f64;
+    let actual = variance(samples);
+    let tail = (1.0 - confidence) / 2.0;
+    let low = expected * chi_square_quantile(tail, degrees) / degrees;
+    let high = expected * chi_square_quantile(1.0 - tail, degrees) / degrees;
+    StatCheck {
+        expected,
+        actual,
+        passed: (low..=high).contains(&actual),
+        description: format!(
+            "variance {expected} in [{low:.4}, {high:.4}] ({})",
+            confidence_label(confidence, samples.len())
+        ),
+    }
+}
+
+/// Kolmogorov-Smirnov test of the samples against a distribution, with
+/// the asymptotic critical value.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn ks_test(samples: &[f64], distribution: &Distribution, confidence: f64) -> StatCheck {
+    let n = samples.len() as f64;
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let actual = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let above = (i + 1) as f64 / n - distribution.cdf(x);
+            let below = distribution.cdf_before(x) - i as f64 / n;
+            above.max(below)
+        })
+        .fold(0.0, f64::max);
+    let critical = ((-((1.0 - confidence) / 2.0).ln() / 2.0) / n).sqrt();
+    StatCheck {
+        expected: critical,
+        actual,
+        passed: actual <= critical,
+        description: format!(
+            "KS distance from {distribution} <= {critical:.4} ({})",
+            confidence_label(confidence, samples.len())
+        ),
+    }
+}
+
+fn confidence_label(confidence: f64, samples: usize) -> String {
+    let percent = format!("{:.6}", confidence * 100.0);
+    let percent = percent.trim_end_matches('0').trim_end_matches('.');
+    format!("{percent}% confidence, {samples} samples")
+}
+
+/// Standard normal CDF.
+#[must_use]
+pub fn normal_cdf(z: f64) -> f64 {
+    0.5 * (1.0 + erf(z / std::f64::consts::SQRT_2))
+}
+
+/// Error function (Abramowitz and Stegun 7.1.26, error below 1.5e-7).
+fn erf(x: f64) -> f64 {
+    const P: f64 = 0.327_591_1;
+    const A: [f64; 5] = [
+        0.254_829_592,
+        -0.284_496_736,
+        1.421_413_741,
+        -1.453_152_027,
+        1.061_405_429,
+    ];
+    let t = 1.0 / P.mul_add(x.abs(), 1.0);
+    let poly = A.iter().rev().fold(0.0_f64, |acc, a| acc.mul_add(t, *a)) * t;
+    let y = 1.0 - poly * (-x * x).exp();
+    y.copysign(x)
+}
+
+/// Inverse of the standard normal CDF (Acklam's rational approximation,
+/// relative error below 1.2e-9), for `0 < p < 1`.
+#[must_use]
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const LOW: f64 = 0.024_25;
+
+    let poly = |coefficients: &[f64], x: f64| {
+        coefficients
+            .iter()
+            .fold(0.0_f64, |acc, c| acc.mul_add(x, *c))
+    };
+    if p < LOW {
+        let q = (-2.0 * p.ln()).sqrt();
+        poly(&C, q) / poly(&D, q).mul_add(q, 1.0)
+    } else if p > 1.0 - LOW {
+        -normal_quantile(1.0 - p)
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        poly(&A, r) * q / poly(&B, r).mul_add(r, 1.0)
+    }
+}
+
+/// Quantile of the chi-square distribution with `degrees` degrees of
+/// freedom (Wilson-Hilferty approximation, accurate for the sample sizes of
+/// distribution tests).
+#[must_use]
+pub fn chi_square_quantile(p: f64, degrees: f64) -> f64 {
+    let h = 2.0 / (9.0 * degrees);
+    degrees * normal_quantile(p).mul_add(h.sqrt(), 1.0 - h).powi(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64, tolerance: f64) -> bool {
+        (actual - expected).abs() < tolerance
+    }
+
+    #[test]
+    fn normal_and_chi_square_quantiles_match_tables() {
+        assert!(close(normal_quantile(0.975), 1.959_964, 1e-6));
+        assert!(close(normal_quantile(0.0005), -3.290_527, 1e-6));
+        assert!(close(normal_quantile(0.5), 0.0, 1e-12));
+        assert!(close(normal_cdf(1.959_964), 0.975, 1e-6));
+        // chi-square(99): 2.5% and 97.5% quantiles
+        assert!(close(chi_square_quantile(0.025, 99.0), 73.361, 0.05));
+        assert!(close(chi_square_quantile(0.975, 99.0), 128.422, 0.05));
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn checks_accept_matching_samples_and_reject_others() {
+        // Evenly spread uniform(0, 1) samples
+        let uniform: Vec<f64> = (0..1000).map(|i| (f64::from(i) + 0.5) / 1000.0).collect();
+        assert!(mean_test(&uniform, 0.5, Some(1.0 / 12.0), 0.999).passed);
+        assert!(!mean_test(&uniform, 0.6, Some(1.0 / 12.0), 0.999).passed);
+        assert!(variance_test(&uniform, 1.0 / 12.0, 0.999).passed);
+        assert!(!variance_test(&uniform, 0.5, 0.999).passed);
+        assert!(ks_test(&uniform, &Distribution::Uniform(0.0, 1.0), 0.999).passed);
+        assert!(!ks_test(&uniform, &Distribution::Normal(0.5, 0.1), 0.999).passed);
+
+        // Every face of a die equally often
+        let dice: Vec<f64> = (0..600).map(|i| f64::from(i % 6 + 1)).collect();
+        let check = ks_test(&dice, &Distribution::UniformInt(1, 6), 0.999);
+        assert!(check.passed && check.actual < 1e-9, "{check:?}");
+        assert!(!ks_test(&dice, &Distribution::UniformInt(1, 7), 0.999).passed);
+
+        let check = mean_test(&uniform, 0.5, None, 0.999);
+        assert!(check.description.starts_with("mean 0.5 ± "));
+        assert!(check
+            .description
+            .ends_with("(99.9% confidence, 1000 samples)"));
+    }
+
+    #[test]
+    fn distributions_parse_from_yaml() {
+        let parse = |yaml: &str| -> Distribution {
+            serde_yaml_ng::with::singleton_map::deserialize(serde_yaml_ng::Deserializer::from_str(
+                yaml,
+            ))
+            .unwrap()
+        };
+        assert_eq!(parse("uniform: [0, 1]"), Distribution::Uniform(0.0, 1.0));
+        assert_eq!(parse("uniform_int: [1, 6]"), Distribution::UniformInt(1, 6));
+        assert_eq!(parse("normal: [0, 2]"), Distribution::Normal(0.0, 2.0));
+        let exponential = parse("exponential: 0.5");
+        assert_eq!(exponential, Distribution::Exponential(0.5));
+
+        assert_eq!(
+            Distribution::UniformInt(1, 6).to_string(),
+            "uniform_int(1, 6)"
+        );
+        assert!(close(exponential.cdf(2.0), 1.0 - (-1.0f64).exp(), 1e-12));
+    }
+}
@@ -5,7 +5,14 @@
 #![allow(dead_code)]
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use crate::dates::{iso_from_serial, DateSystem, IsoDate, IsoDateTime};
+use crate::excel::CellValue;
+use crate::formula::{escape_text, Formula};
+use crate::model::ASSUMPTIONS;
+use crate::stats::{self, Distribution, StatCheck};
 
 /// Test specification file structure.
 #[derive(Debug, Deserialize)]
@@ -14,11 +21,224 @@ pub struct TestSpec {
     #[serde(rename = "_forge_version")]
     pub forge_version: String,
 
+    /// Fixture files whose tables are merged into this spec.
+    #[serde(rename = "_include", default)]
+    pub include: Vec<String>,
+
+    /// Tags selecting or skipping this spec's file (`--tag`, `--skip-tag`).
+    #[serde(rename = "_tags", default)]
+    pub tags: Vec<String>,
+
+    /// Oldest Gnumeric release that can run every test of this spec.
+    #[serde(rename = "_min_gnumeric", default)]
+    pub min_gnumeric: Option<GnumericVersion>,
+
+    /// Date systems (`1900`, `1904`) every test of this spec runs under.
+    #[serde(rename = "_date_systems", default)]
+    pub date_systems: Option<Vec<DateSystem>>,
+
+    /// Sheets of a workbook imported with `forge import` and recalculated
+    /// by both forge and Gnumeric.
+    #[serde(rename = "_workbook", default)]
+    pub workbook: Vec<WorkbookSheet>,
+
+    /// Named scenarios (scalar overrides, or legacy scenario data columns).
+    #[serde(default)]
+    pub scenarios: Option<Scenarios>,
+
     /// Named sections containing test definitions.
     #[serde(flatten)]
     pub sections: HashMap<String, Section>,
 }
 
+impl TestSpec {
+    /// Returns the named scenario with its scalar overrides.
+    ///
+    /// Only scenarios declared as scalar overrides can be applied to a test.
+    /// The model exports every input scalar of the spec, so formulas can
+    /// read inputs the scenario leaves alone, and every scalar overridden by
+    /// any scenario needs a base value among them for forge to replace.
+    fn scenario(&self, test: &str, name: &str) -> Result<Scenario, SpecError> {
+        let Some(Scenarios::Overrides(scenarios)) = &self.scenarios else {
+            return Err(SpecError::UnknownScenario {
+                test: test.to_string(),
+                scenario: name.to_string(),
+            });
+        };
+        let overrides = scenarios
+            .get(name)
+            .ok_or_else(|| SpecError::UnknownScenario {
+                test: test.to_string(),
+                scenario: name.to_string(),
+            })?;
+        let base = self.input_values();
+        if let Some(scalar) = scenarios
+            .values()
+            .flat_map(BTreeMap::keys)
+            .find(|scalar| !base.contains_key(*scalar))
+        {
+            return Err(SpecError::MissingScenarioBase {
+                test: test.to_string(),
+                scalar: scalar.clone(),
+            });
+        }
+        Ok(Scenario {
+            name: name.to_string(),
+            overrides: overrides.clone(),
+            base,
+        })
+    }
+
+    /// Returns the values of the input scalars (a `value` without `formula`)
+    /// of every section. A name in several sections keeps the value of the
+    /// first section in name order.
+    fn input_values(&self) -> BTreeMap<String, f64> {
+        let mut sections: Vec<_> = self
+            .sections
+            .iter()
+            .filter(|(section_name, _)| !section_name.starts_with('_'))
+            .collect();
+        sections.sort_by_key(|(section_name, _)| *section_name);
+
+        let mut values = BTreeMap::new();
+        for (_, section) in sections {
+            let Section::ScalarGroup(scalars) = section else {
+                continue;
+            };
+            for (name, scalar) in scalars {
+                if let (None, Some(value)) = (&scalar.formula, scalar.value) {
+                    values.entry(name.clone()).or_insert(value);
+                }
+            }
+        }
+        values
+    }
+
+    /// Returns the Gnumeric release a scalar needs: the later of its own
+    /// `min_gnumeric` and the spec's `_min_gnumeric`.
+    #[must_use]
+    pub fn min_gnumeric_for(&self, scalar: &Scalar) -> Option<GnumericVersion> {
+        self.min_gnumeric.max(scalar.min_gnumeric)
+    }
+
+    /// Returns the date systems a scalar runs under: its own `date_systems`,
+    /// else the spec's `_date_systems`, else the 1900 system only.
+    #[must_use]
+    pub fn date_systems_for(&self, scalar: &Scalar) -> Vec<DateSystem> {
+        scalar
+            .date_systems
+            .clone()
+            .or_else(|| self.date_systems.clone())
+            .unwrap_or_else(|| vec![DateSystem::Excel1900])
+    }
+
+    /// Checks that every `table.column` reference of a formula names a
+    /// column of a table (spec, fixture or scenario data), a scalar of a
+    /// section, or a member of the formula's chain (`assumptions.name`).
+    ///
+    /// Formulas that do not parse are left to the parser warnings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the first reference that names nothing.
+    pub fn check_table_refs(
+        &self,
+        test: &str,
+        formula: &str,
+        chain: &[ChainScalar],
+    ) -> Result<(), SpecError> {
+        let Ok(formula) = Formula::parse(formula) else {
+            return Ok(());
+        };
+        for (table, column) in formula.table_refs() {
+            let in_chain = table == ASSUMPTIONS && chain.iter().any(|scalar| scalar.name == column);
+            let known = in_chain
+                || match (table, self.sections.get(table), &self.scenarios) {
+                    (_, Some(Section::Table(columns)), _)
+                    | ("scenarios", None, Some(Scenarios::Table(columns))) => {
+                        columns.contains_key(column)
+                    }
+                    (_, Some(Section::ScalarGroup(scalars)), _) => scalars.contains_key(column),
+                    _ => false,
+                };
+            if !known {
+                return Err(SpecError::UnknownTableRef {
+                    test: test.to_string(),
+                    reference: format!("{table}.{column}"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the `table.column` references of every scalar formula in the
+    /// spec, including scalars without an expected value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the first reference that names nothing.
+    pub fn check_scalar_refs(&self) -> Result<(), SpecError> {
+        for (section_name, section) in &self.sections {
+            let Section::ScalarGroup(scalars) = section else {
+                continue;
+            };
+            if section_name.starts_with('_') {
+                continue;
+            }
+            for (name, scalar) in scalars {
+                if let Some(formula) = &scalar.formula {
+                    self.check_table_refs(&format!("{section_name}.{name}"), formula, &[])?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the tables of a fixture file into this spec.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a fixture table has the same name as a section
+    /// already in the spec (including tables from earlier fixtures).
+    pub fn merge_fixture(&mut self, fixture_name: &str, fixture: Fixture) -> Result<(), SpecError> {
+        for (table, columns) in fixture {
+            if self.sections.contains_key(&table) {
+                return Err(SpecError::FixtureConflict {
+                    fixture: fixture_name.to_string(),
+                    table,
+                });
+            }
+            self.sections.insert(table, Section::Table(columns));
+        }
+        Ok(())
+    }
+}
+
+/// Shared fixture file: named tables that specs pull in with `_include`.
+pub type Fixture = HashMap<String, HashMap<String, TableColumn>>;
+
+/// The `scenarios` section of a spec.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Scenarios {
+    /// Scenario name to scalar overrides (e.g., `base: { growth_rate: 0.05 }`).
+    Overrides(BTreeMap<String, BTreeMap<String, f64>>),
+    /// Scenario data laid out as table columns (e.g., `base_case: [100, 200]`).
+    Table(HashMap<String, TableColumn>),
+}
+
+/// A scenario applied to a test: its name and scalar overrides.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    /// Scenario name.
+    pub name: String,
+    /// Scalar overrides forge applies when exporting the scenario.
+    pub overrides: BTreeMap<String, f64>,
+    /// Values of the spec's input scalars, exported as plain scalars for
+    /// the scenario to override.
+    pub base: BTreeMap<String, f64>,
+}
+
 /// A section in the test spec (e.g., "assumptions", "projections").
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
@@ -38,227 +258,2558 @@ pub struct Scalar {
     pub formula: Option<String>,
     /// Expected value for E2E validation.
     pub expected: Option<f64>,
+    /// Expected date (`2024-01-01`), compared as its serial number.
+    pub expected_date: Option<IsoDate>,
+    /// Expected date and time (`2024-01-01T12:30:00`), compared as its
+    /// serial number.
+    pub expected_datetime: Option<IsoDateTime>,
+    /// Inclusive bounds of a volatile result (e.g., `RAND()`).
+    pub expect_range: Option<[f64; 2]>,
+    /// Type of a volatile result.
+    pub expect_type: Option<ResultType>,
+    /// A volatile result must be a whole number.
+    #[serde(default)]
+    pub expect_integer: bool,
+    /// A volatile result must be within this many seconds of the wall clock
+    /// (e.g., `NOW()`).
+    pub expect_within_seconds: Option<f64>,
+    /// A volatile result must be today's date (e.g., `TODAY()`).
+    #[serde(default)]
+    pub expect_today: bool,
+    /// Sample statistics asserted on a random formula.
+    pub distribution: Option<DistributionSpec>,
     /// Skip reason (if set, test is skipped).
     pub skip: Option<String>,
+    /// Known-failure reason (if set, test runs and is reported as TAP `TODO`).
+    pub todo: Option<String>,
+    /// Oldest Gnumeric release that can run this test.
+    pub min_gnumeric: Option<GnumericVersion>,
+    /// Date systems the test runs under (overrides the spec's
+    /// `_date_systems`).
+    pub date_systems: Option<Vec<DateSystem>>,
+    /// Argument rows for a table-driven test.
+    ///
+    /// When set, `formula` is a template whose `{{name}}` placeholders are
+    /// filled from each row, producing one test case per row.
+    pub cases: Option<Vec<CaseRow>>,
+    /// Dependent scalars exported together as one model.
+    ///
+    /// Scalars may reference each other; every scalar with an `expected`
+    /// value is asserted.
+    pub chain: Option<Chain>,
+    /// Expected value per scenario.
+    ///
+    /// When set, the formula is tested once per listed scenario, with that
+    /// scenario's overrides applied.
+    pub expected_by_scenario: Option<BTreeMap<String, f64>>,
 }
 
-/// A table column (array of values or formula).
+/// Named scalars of a dependent chain, in declaration order.
+///
+/// Declaration order is kept so that specs can exercise forward references.
+#[derive(Debug, Default)]
+pub struct Chain(pub Vec<(String, ChainMember)>);
+
+/// A scalar of a dependent chain: an input value or a formula, asserted
+/// when it has an expected value.
+///
+/// Test options such as `skip`, `todo` or `cases` belong on the chain's
+/// scalar, so they are unknown fields here.
 #[derive(Debug, Deserialize)]
-#[serde(untagged)]
-pub enum TableColumn {
-    /// Column of numeric values.
-    Numbers(Vec<f64>),
-    /// Column of string values.
-    Strings(Vec<String>),
-    /// Column defined by a formula.
-    Formula(String),
+#[serde(deny_unknown_fields)]
+pub struct ChainMember {
+    /// The literal value (if no formula).
+    pub value: Option<f64>,
+    /// The Excel formula to evaluate.
+    pub formula: Option<String>,
+    /// Expected value.
+    pub expected: Option<f64>,
+    /// Expected date (`2024-01-01`), compared as its serial number.
+    pub expected_date: Option<IsoDate>,
+    /// Expected date and time, compared as its serial number.
+    pub expected_datetime: Option<IsoDateTime>,
 }
 
-/// Individual test case extracted from a spec.
-#[derive(Debug, Clone)]
-pub struct TestCase {
-    /// Fully qualified name (e.g., `assumptions.test_abs`).
-    pub name: String,
-    /// The Excel formula to evaluate.
-    pub formula: String,
-    /// The expected result value.
-    pub expected: f64,
-    /// Source YAML file path (for loading table data).
-    pub source_file: Option<std::path::PathBuf>,
-    /// Forge version from source file.
-    pub forge_version: String,
+impl ChainMember {
+    /// Returns the expected value of this member and how it was written,
+    /// if it is asserted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if more than one of `expected`, `expected_date` and
+    /// `expected_datetime` is set.
+    pub fn expected_value(&self, test: &str) -> Result<Option<(f64, ValueKind)>, SpecError> {
+        expected_value(
+            test,
+            self.expected,
+            self.expected_date,
+            self.expected_datetime,
+        )
+    }
 }
 
-/// A test case that should be skipped.
-#[derive(Debug, Clone)]
-pub struct SkipCase {
-    /// Fully qualified name.
-    pub name: String,
-    /// Reason for skipping.
-    pub reason: String,
+impl<'de> Deserialize<'de> for Chain {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ChainVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ChainVisitor {
+            type Value = Chain;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of scalar names to scalars")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Chain, A::Error> {
+                let mut scalars = Vec::new();
+                while let Some((name, scalar)) = map.next_entry::<String, ChainMember>()? {
+                    scalars.push((name, scalar));
+                }
+                Ok(Chain(scalars))
+            }
+        }
+
+        deserializer.deserialize_map(ChainVisitor)
+    }
 }
 
-/// Result of running a test.
-#[derive(Debug, Serialize)]
-#[serde(tag = "status", rename_all = "lowercase")]
-pub enum TestResult {
-    /// Test passed - actual matches expected.
-    Pass {
-        name: String,
-        formula: String,
-        expected: f64,
-        actual: f64,
-    },
-    /// Test failed - mismatch or error.
-    Fail {
-        name: String,
-        formula: String,
-        expected: f64,
-        actual: Option<f64>,
-        error: Option<String>,
-    },
-    /// Test was skipped.
-    Skip { name: String, reason: String },
+/// One row of a table-driven test: placeholder arguments and the expected value.
+#[derive(Debug, Deserialize)]
+pub struct CaseRow {
+    /// Case name used in the generated test name (defaults to the row index).
+    pub name: Option<String>,
+    /// Expected value for this row.
+    pub expected: Option<f64>,
+    /// Expected date for this row.
+    pub expected_date: Option<IsoDate>,
+    /// Expected date and time for this row.
+    pub expected_datetime: Option<IsoDateTime>,
+    /// Skip reason (if set, this row is skipped).
+    pub skip: Option<String>,
+    /// Known-failure reason for this row (overrides the scalar's `todo`).
+    pub todo: Option<String>,
+    /// Placeholder values, keyed by placeholder name.
+    #[serde(flatten)]
+    pub args: HashMap<String, serde_yaml_ng::Value>,
 }
 
-impl TestResult {
-    /// Returns `true` if this result is a pass.
-    #[must_use]
-    pub const fn is_pass(&self) -> bool {
-        matches!(self, Self::Pass { .. })
+impl Scalar {
+    /// Returns the expected value and how it was written, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if more than one of `expected`, `expected_date` and
+    /// `expected_datetime` is set, or one is set next to `expect_*`
+    /// assertions.
+    pub fn expected_value(&self, test: &str) -> Result<Option<(f64, ValueKind)>, SpecError> {
+        if self.volatile_expectation().is_some()
+            && (self.expected.is_some()
+                || self.expected_date.is_some()
+                || self.expected_datetime.is_some())
+        {
+            return Err(SpecError::ConflictingExpected {
+                test: test.to_string(),
+            });
+        }
+        expected_value(
+            test,
+            self.expected,
+            self.expected_date,
+            self.expected_datetime,
+        )
     }
 
-    /// Returns `true` if this result is a failure.
+    /// Returns the `expect_*` assertions of a volatile test, if any is set.
     #[must_use]
-    pub const fn is_fail(&self) -> bool {
-        matches!(self, Self::Fail { .. })
+    pub fn volatile_expectation(&self) -> Option<VolatileExpectation> {
+        let expectation = VolatileExpectation {
+            range: self.expect_range,
+            value_type: self.expect_type,
+            integer: self.expect_integer,
+            within_seconds: self.expect_within_seconds,
+            today: self.expect_today,
+        };
+        (expectation != VolatileExpectation::default()).then_some(expectation)
     }
+}
 
-    /// Returns the test name.
+impl CaseRow {
+    /// Returns the expected value of this row and how it was written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error unless exactly one of `expected`, `expected_date`
+    /// and `expected_datetime` is set.
+    pub fn expected_value(&self, test: &str) -> Result<(f64, ValueKind), SpecError> {
+        expected_value(
+            test,
+            self.expected,
+            self.expected_date,
+            self.expected_datetime,
+        )?
+        .ok_or_else(|| SpecError::MissingExpected {
+            test: test.to_string(),
+        })
+    }
+
+    /// Returns the generated test name for this row.
     #[must_use]
-    pub fn name(&self) -> &str {
-        match self {
-            Self::Pass { name, .. } | Self::Fail { name, .. } | Self::Skip { name, .. } => name,
+    pub fn test_name(&self, base: &str, index: usize) -> String {
+        match &self.name {
+            Some(name) => format!("{base}[{name}]"),
+            None => format!("{base}[{index}]"),
         }
     }
 }
 
-/// Extracts test cases from a test spec.
-///
-/// Scans all sections for scalar values that have both a formula and
-/// an expected value defined. Tests with `skip` field are excluded.
-pub fn extract_test_cases(spec: &TestSpec, source_file: Option<&std::path::Path>) -> Vec<TestCase> {
-    let mut cases = Vec::new();
+/// Picks the one expected value of a test, converting dates to serials.
+#[allow(clippy::cast_precision_loss)]
+fn expected_value(
+    test: &str,
+    number: Option<f64>,
+    date: Option<IsoDate>,
+    datetime: Option<IsoDateTime>,
+) -> Result<Option<(f64, ValueKind)>, SpecError> {
+    let mut values = [
+        number.map(|number| (number, ValueKind::Number)),
+        date.map(|date| (date.serial() as f64, ValueKind::Date(DateSystem::Excel1900))),
+        datetime.map(|datetime| {
+            (
+                datetime.serial(),
+                ValueKind::DateTime(DateSystem::Excel1900),
+            )
+        }),
+    ]
+    .into_iter()
+    .flatten();
+    let value = values.next();
+    if values.next().is_some() {
+        return Err(SpecError::ConflictingExpected {
+            test: test.to_string(),
+        });
+    }
+    Ok(value)
+}
 
-    for (section_name, section) in &spec.sections {
-        if section_name.starts_with('_') || section_name == "scenarios" {
-            continue;
-        }
+/// Type of a recalculated cell, asserted with `expect_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultType {
+    /// A number (dates and times included).
+    Number,
+    /// A string.
+    Text,
+    /// `TRUE` or `FALSE`.
+    Boolean,
+    /// An error value such as `#VALUE!`.
+    Error,
+}
 
-        if let Section::ScalarGroup(scalars) = section {
-            for (name, scalar) in scalars {
-                // Skip tests marked with skip field
-                if scalar.skip.is_some() {
-                    continue;
-                }
-                if let (Some(formula), Some(expected)) = (&scalar.formula, scalar.expected) {
-                    cases.push(TestCase {
-                        name: format!("{section_name}.{name}"),
-                        formula: formula.clone(),
-                        expected,
-                        source_file: source_file.map(std::path::Path::to_path_buf),
-                        forge_version: spec.forge_version.clone(),
-                    });
-                }
-            }
+impl ResultType {
+    /// Returns the type of a cell (`None` for an empty cell).
+    #[must_use]
+    pub const fn of(cell: &CellValue) -> Option<Self> {
+        match cell {
+            CellValue::Empty => None,
+            CellValue::Number(_) => Some(Self::Number),
+            CellValue::Text(_) => Some(Self::Text),
+            CellValue::Bool(_) => Some(Self::Boolean),
+            CellValue::Error(_) => Some(Self::Error),
         }
     }
+}
 
-    cases
+impl std::fmt::Display for ResultType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Number => "number",
+            Self::Text => "text",
+            Self::Boolean => "boolean",
+            Self::Error => "error",
+        })
+    }
 }
 
-/// Extracts table data sections from a test spec as YAML string.
-///
-/// Returns a string containing all table sections in YAML format,
-/// suitable for inclusion in a generated test file.
-#[must_use]
-pub fn extract_table_data_yaml(spec: &TestSpec) -> String {
-    use std::fmt::Write;
-    let mut yaml = String::new();
+/// Wall-clock serials taken before export and after recalculation, in the
+/// date system of the test's workbook.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallClock {
+    /// Serial when the test started.
+    pub start: f64,
+    /// Serial when Gnumeric finished recalculating.
+    pub end: f64,
+}
 
-    for (section_name, section) in &spec.sections {
-        // Skip metadata sections and assumptions (which contain tests)
-        if section_name.starts_with('_')
-            || section_name == "scenarios"
-            || section_name == "assumptions"
-        {
-            continue;
+/// Assertions on the result of a volatile formula (`NOW`, `TODAY`, `RAND`,
+/// `RANDBETWEEN`), which has no fixed expected value.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VolatileExpectation {
+    /// Inclusive bounds (`expect_range`).
+    pub range: Option<[f64; 2]>,
+    /// Cell type (`expect_type`).
+    pub value_type: Option<ResultType>,
+    /// Must be a whole number (`expect_integer`).
+    pub integer: bool,
+    /// Largest distance from the wall clock, in seconds
+    /// (`expect_within_seconds`).
+    pub within_seconds: Option<f64>,
+    /// Must be the date of the run (`expect_today`).
+    pub today: bool,
+}
+
+impl VolatileExpectation {
+    /// Describes the assertions, as reported in place of an expected value.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(value_type) = self.value_type {
+            parts.push(value_type.to_string());
+        }
+        if let Some([min, max]) = self.range {
+            parts.push(format!("in [{min}, {max}]"));
+        }
+        if self.integer {
+            parts.push("integer".to_string());
+        }
+        if let Some(seconds) = self.within_seconds {
+            parts.push(format!("within {seconds}s of the wall clock"));
         }
+        if self.today {
+            parts.push("today's date".to_string());
+        }
+        parts.join(", ")
+    }
 
-        // Check if this is a table section (has array values, not scalars with formulas)
-        if let Section::Table(columns) = section {
-            let _ = writeln!(yaml, "{section_name}:");
-            for (col_name, col_data) in columns {
-                match col_data {
-                    TableColumn::Numbers(nums) => {
-                        let nums_str: Vec<String> = nums.iter().map(ToString::to_string).collect();
-                        let _ = writeln!(yaml, "  {col_name}: [{}]", nums_str.join(", "));
-                    }
-                    TableColumn::Strings(strs) => {
-                        let strs_escaped: Vec<String> =
-                            strs.iter().map(|s| format!("\"{s}\"")).collect();
-                        let _ = writeln!(yaml, "  {col_name}: [{}]", strs_escaped.join(", "));
-                    }
-                    TableColumn::Formula(f) => {
-                        let _ = writeln!(yaml, "  {col_name}: \"{f}\"");
-                    }
-                }
+    /// Checks a recalculated cell against every assertion.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the cell fails the first assertion it does not meet.
+    pub fn check(&self, cell: &CellValue, clock: WallClock) -> Result<(), String> {
+        let found = ResultType::of(cell);
+        if let Some(value_type) = self.value_type {
+            if found != Some(value_type) {
+                return Err(format!(
+                    "expected a {value_type}, got {}",
+                    describe_cell(cell)
+                ));
+            }
+        }
+        let needs_number =
+            self.range.is_some() || self.integer || self.within_seconds.is_some() || self.today;
+        let Some(value) = cell.as_number() else {
+            return if needs_number {
+                Err(format!("expected a number, got {}", describe_cell(cell)))
+            } else {
+                Ok(())
+            };
+        };
+
+        if let Some([min, max]) = self.range {
+            if !(min..=max).contains(&value) {
+                return Err(format!("{value} is outside [{min}, {max}]"));
+            }
+        }
+        if self.integer && value.fract() != 0.0 {
+            return Err(format!("{value} is not an integer"));
+        }
+        if let Some(seconds) = self.within_seconds {
+            let slack = seconds / 86_400.0;
+            if !(clock.start - slack..=clock.end + slack).contains(&value) {
+                let off = if value < clock.start {
+                    clock.start - value
+                } else {
+                    value - clock.end
+                };
+                return Err(format!(
+                    "{value} is {:.0}s from the wall clock",
+                    off * 86_400.0
+                ));
             }
         }
+        if self.today
+            && (value.fract() != 0.0 || !(clock.start.floor()..=clock.end.floor()).contains(&value))
+        {
+            return Err(format!(
+                "{value} is not the date of the run ({})",
+                clock.end.floor()
+            ));
+        }
+        Ok(())
     }
+}
 
-    yaml
+/// Describes a cell in a failure message.
+fn describe_cell(cell: &CellValue) -> String {
+    match cell {
+        CellValue::Empty => "an empty cell".to_string(),
+        CellValue::Number(n) => format!("number {n}"),
+        CellValue::Text(text) => format!("text `{text}`"),
+        CellValue::Bool(b) => format!("boolean {}", if *b { "TRUE" } else { "FALSE" }),
+        CellValue::Error(error) => format!("error {error}"),
+    }
 }
 
-/// Extracts skip cases from a test spec.
-#[must_use]
-pub fn extract_skip_cases(spec: &TestSpec) -> Vec<SkipCase> {
-    let mut cases = Vec::new();
+/// How a test's expected value is written in its spec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueKind {
+    /// A plain number (`expected`).
+    #[default]
+    Number,
+    /// A date serial (`expected_date`) in the given date system.
+    Date(DateSystem),
+    /// A date and time serial (`expected_datetime`) in the given date system.
+    DateTime(DateSystem),
+}
 
-    for (section_name, section) in &spec.sections {
-        if section_name.starts_with('_') || section_name == "scenarios" {
-            continue;
+impl ValueKind {
+    /// Returns `true` for plain numbers.
+    #[must_use]
+    pub const fn is_number(&self) -> bool {
+        matches!(self, Self::Number)
+    }
+
+    /// Converts a value of this kind into the 1900 date system: dates are
+    /// shifted, plain numbers are left as they are.
+    #[must_use]
+    pub fn to_1900(self, value: f64) -> f64 {
+        match self {
+            Self::Number => value,
+            Self::Date(system) | Self::DateTime(system) => system.to_1900(value),
         }
+    }
 
-        if let Section::ScalarGroup(scalars) = section {
-            for (name, scalar) in scalars {
-                if let Some(reason) = &scalar.skip {
-                    cases.push(SkipCase {
-                        name: format!("{section_name}.{name}"),
-                        reason: reason.clone(),
-                    });
-                }
-            }
+    /// Renders a value as an ISO date (or date and time) for dates.
+    #[must_use]
+    pub fn render(self, value: f64) -> Option<String> {
+        match self {
+            Self::Number => None,
+            Self::Date(system) => iso_from_serial(system.to_1900(value), false),
+            Self::DateTime(system) => iso_from_serial(system.to_1900(value), true),
         }
     }
 
-    cases
+    /// Moves a 1900-system value of this kind into `system`: date serials
+    /// are shifted by the epoch, plain numbers are left as they are.
+    #[must_use]
+    pub fn in_date_system(self, value: f64, system: DateSystem) -> (f64, Self) {
+        match self {
+            Self::Number => (value, self),
+            Self::Date(_) => (system.from_1900(value), Self::Date(system)),
+            Self::DateTime(_) => (system.from_1900(value), Self::DateTime(system)),
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Errors found while extracting test cases from a spec.
+#[derive(Debug, thiserror::Error)]
+pub enum SpecError {
+    /// A formula template references a placeholder the row does not define.
+    #[error("{test}: placeholder {{{{{placeholder}}}}} has no value")]
+    MissingPlaceholder { test: String, placeholder: String },
+    /// A formula template has an unterminated placeholder.
+    #[error("{test}: unterminated placeholder in formula template")]
+    UnterminatedPlaceholder { test: String },
+    /// A test sets more than one of `expected`, `expected_date` and
+    /// `expected_datetime`.
+    #[error(
+        "{test}: set only one of expected, expected_date and expected_datetime, or expect_* assertions"
+    )]
+    ConflictingExpected { test: String },
+    /// A case row has no expected value.
+    #[error("{test}: no expected value")]
+    MissingExpected { test: String },
+    /// A test has `cases` but no formula template to fill in.
+    #[error("{test}: cases need a formula template")]
+    MissingFormula { test: String },
+    /// A test with `cases` also sets a test-level expectation, which the
+    /// rows would silently replace.
+    #[error("{test}: expected values belong in the case rows when cases are set")]
+    ExpectedBesideCases { test: String },
+    /// A `distribution` block cannot be tested.
+    #[error("{test}: invalid distribution: {reason}")]
+    InvalidDistribution { test: String, reason: String },
+    /// A `_workbook` sheet cannot be written.
+    #[error("_workbook sheet `{sheet}`: {reason}")]
+    InvalidWorkbook { sheet: String, reason: String },
+    /// A fixture defines a table whose name is already taken.
+    #[error("fixture {fixture}: table `{table}` is already defined")]
+    FixtureConflict { fixture: String, table: String },
+    /// A formula references a table or column no table or fixture defines.
+    #[error("{test}: `{reference}` is not a column of any table or fixture")]
+    UnknownTableRef { test: String, reference: String },
+    /// A test expects a value for a scenario the spec does not define.
+    #[error("{test}: unknown scenario `{scenario}`")]
+    UnknownScenario { test: String, scenario: String },
+    /// A scenario overrides a scalar the spec gives no base value.
+    #[error("{test}: scenario overrides `{scalar}`, which has no base value")]
+    MissingScenarioBase { test: String, scalar: String },
+    /// A placeholder value cannot be rendered into a formula.
+    #[error("{test}: placeholder {{{{{placeholder}}}}} must be a number, string, bool or null")]
+    InvalidPlaceholderValue { test: String, placeholder: String },
+}
 
-    #[test]
-    fn parse_spec_extracts_test_cases() {
-        let yaml = r#"
-_forge_version: "1.0.0"
-assumptions:
-  test_abs:
-    value: null
-    formula: "=ABS(-42)"
-    expected: 42
-"#;
-        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
-        assert_eq!(spec.forge_version, "1.0.0");
+/// A Gnumeric release version (`major.minor.patch`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct GnumericVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
 
-        let cases = extract_test_cases(&spec, None);
-        assert_eq!(cases.len(), 1);
-        assert_eq!(cases[0].name, "assumptions.test_abs");
+/// A version that is not `major.minor` or `major.minor.patch`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid version `{0}`, expected MAJOR.MINOR[.PATCH]")]
+pub struct VersionError(String);
+
+impl GnumericVersion {
+    /// Creates a version.
+    #[must_use]
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
     }
 
-    #[test]
-    fn test_result_is_pass() {
-        let pass = TestResult::Pass {
-            name: "test".to_string(),
-            formula: "=1".to_string(),
-            expected: 1.0,
-            actual: 1.0,
-        };
+    /// Finds the first version number in a text such as ssconvert's
+    /// `ssconvert version '1.12.55'`.
+    #[must_use]
+    pub fn find_in(text: &str) -> Option<Self> {
+        text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .map(|token| token.trim_matches('.'))
+            .filter(|token| token.contains('.'))
+            .find_map(|token| token.parse().ok())
+    }
+}
+
+impl std::str::FromStr for GnumericVersion {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        let number = |part: &str| part.parse::<u32>().map_err(|_| VersionError(s.to_string()));
+        match parts.as_slice() {
+            [major, minor] => Ok(Self::new(number(major)?, number(minor)?, 0)),
+            [major, minor, patch] => Ok(Self::new(number(major)?, number(minor)?, number(patch)?)),
+            _ => Err(VersionError(s.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for GnumericVersion {
+    type Error = VersionError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl std::fmt::Display for GnumericVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A table column (array of values or formula).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum TableColumn {
+    /// Column of numeric values.
+    Numbers(Vec<f64>),
+    /// Column of string values.
+    Strings(Vec<String>),
+    /// Column defined by a formula.
+    Formula(String),
+    /// Column defined by a formula, with expected values for every row.
+    Calculated(CalculatedColumn),
+}
+
+/// A row-wise calculated column with expected values for E2E validation.
+#[derive(Debug, Deserialize)]
+pub struct CalculatedColumn {
+    /// The row-wise formula (e.g., `=revenue - cost`).
+    pub formula: String,
+    /// Expected value for each row.
+    pub expected: Vec<f64>,
+    /// Known-failure reason, reported as TAP `TODO` for every row.
+    #[serde(default)]
+    pub todo: Option<String>,
+}
+
+/// Individual test case extracted from a spec.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    /// Fully qualified name (e.g., `assumptions.test_abs`).
+    pub name: String,
+    /// The Excel formula to evaluate.
+    pub formula: String,
+    /// The expected result value (unused by volatile tests).
+    pub expected: f64,
+    /// How the expected value is written (number or date).
+    pub kind: ValueKind,
+    /// Source YAML file path (for loading table data).
+    pub source_file: Option<std::path::PathBuf>,
+    /// Forge version from source file.
+    pub forge_version: String,
+    /// Scenario applied when exporting the test.
+    pub scenario: Option<Scenario>,
+    /// Known-failure reason, reported as TAP `TODO`.
+    pub todo: Option<String>,
+    /// Oldest Gnumeric release that can run this test.
+    pub min_gnumeric: Option<GnumericVersion>,
+    /// Date system of the workbook the test is recalculated in.
+    pub date_system: DateSystem,
+    /// Assertions of a volatile test, checked instead of `expected`.
+    pub volatile: Option<VolatileExpectation>,
+}
+
+impl TestCase {
+    /// Returns this 1900-system test case moved into `system`.
+    ///
+    /// Outside the 1900 system the name gets a `[1904]` suffix and date
+    /// expectations are shifted to that system's serials.
+    #[must_use]
+    pub fn in_date_system(&self, system: DateSystem) -> Self {
+        let (expected, kind) = self.kind.in_date_system(self.expected, system);
+        Self {
+            name: date_system_name(&self.name, system),
+            expected,
+            kind,
+            date_system: system,
+            ..self.clone()
+        }
+    }
+}
+
+/// Names a test run under `system`; 1900-system names are unchanged.
+fn date_system_name(name: &str, system: DateSystem) -> String {
+    match system {
+        DateSystem::Excel1900 => name.to_string(),
+        DateSystem::Excel1904 => format!("{name} [{system}]"),
+    }
+}
+
+/// A chain of dependent scalars extracted from a spec.
+#[derive(Debug, Clone)]
+pub struct ChainCase {
+    /// Fully qualified name of the chain (e.g., `assumptions.test_profit`).
+    pub name: String,
+    /// Scalars of the chain, in declaration order.
+    pub scalars: Vec<ChainScalar>,
+    /// Source YAML file path (for loading table data).
+    pub source_file: Option<std::path::PathBuf>,
+    /// Forge version from source file.
+    pub forge_version: String,
+    /// Known-failure reason, reported as TAP `TODO` for every assertion.
+    pub todo: Option<String>,
+    /// Oldest Gnumeric release that can run this chain.
+    pub min_gnumeric: Option<GnumericVersion>,
+    /// Date system of the workbook the chain is recalculated in.
+    pub date_system: DateSystem,
+}
+
+/// A scalar inside a dependent chain.
+#[derive(Debug, Clone)]
+pub struct ChainScalar {
+    /// Scalar name within the generated model.
+    pub name: String,
+    /// The literal value (if no formula).
+    pub value: Option<f64>,
+    /// The Excel formula to evaluate.
+    pub formula: Option<String>,
+    /// Expected value, if this scalar is asserted.
+    pub expected: Option<f64>,
+    /// How the expected value is written (number or date).
+    pub kind: ValueKind,
+}
+
+impl ChainCase {
+    /// Returns the asserted scalars with their expected values.
+    pub fn assertions(&self) -> impl Iterator<Item = (&ChainScalar, f64)> {
+        self.scalars
+            .iter()
+            .filter_map(|scalar| scalar.expected.map(|expected| (scalar, expected)))
+    }
+
+    /// Returns this 1900-system chain moved into `system` (see
+    /// [`TestCase::in_date_system`]).
+    #[must_use]
+    pub fn in_date_system(&self, system: DateSystem) -> Self {
+        let scalars = self
+            .scalars
+            .iter()
+            .map(|scalar| {
+                let (expected, kind) = match scalar.expected {
+                    Some(expected) => {
+                        let (expected, kind) = scalar.kind.in_date_system(expected, system);
+                        (Some(expected), kind)
+                    }
+                    None => (None, scalar.kind),
+                };
+                ChainScalar {
+                    expected,
+                    kind,
+                    ..scalar.clone()
+                }
+            })
+            .collect();
+        Self {
+            name: date_system_name(&self.name, system),
+            scalars,
+            date_system: system,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the test name reported for an asserted scalar.
+    #[must_use]
+    pub fn assertion_name(&self, scalar: &ChainScalar) -> String {
+        format!("{}.{}", self.name, scalar.name)
+    }
+}
+
+/// A table whose calculated columns have expected values.
+#[derive(Debug, Clone)]
+pub struct TableCase {
+    /// Table name (also the exported sheet name).
+    pub name: String,
+    /// Asserted calculated columns.
+    pub columns: Vec<ColumnAssertion>,
+    /// Source YAML file path (for loading table data).
+    pub source_file: Option<std::path::PathBuf>,
+    /// Forge version from source file.
+    pub forge_version: String,
+    /// Oldest Gnumeric release that can run this table (from the spec).
+    pub min_gnumeric: Option<GnumericVersion>,
+}
+
+/// Expected values for one calculated table column.
+#[derive(Debug, Clone)]
+pub struct ColumnAssertion {
+    /// Column name.
+    pub name: String,
+    /// The row-wise formula.
+    pub formula: String,
+    /// Expected value for each row.
+    pub expected: Vec<f64>,
+    /// Known-failure reason, reported as TAP `TODO` for every row.
+    pub todo: Option<String>,
+}
+
+impl TableCase {
+    /// Returns the number of asserted rows across all columns.
+    #[must_use]
+    pub fn assertion_count(&self) -> usize {
+        self.columns
+            .iter()
+            .map(|column| column.expected.len())
+            .sum()
+    }
+
+    /// Returns the test name reported for one row of a column.
+    #[must_use]
+    pub fn row_name(&self, column: &ColumnAssertion, row: usize) -> String {
+        format!("{}.{}[{row}]", self.name, column.name)
+    }
+
+    /// Returns the `todo` of the column a reported row belongs to.
+    #[must_use]
+    pub fn todo_for(&self, result_name: &str) -> Option<&str> {
+        self.columns
+            .iter()
+            .find(|column| result_name.starts_with(&format!("{}.{}[", self.name, column.name)))
+            .and_then(|column| column.todo.as_deref())
+    }
+}
+
+/// Sample statistics asserted on a random formula (`distribution:`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DistributionSpec {
+    /// Number of samples drawn.
+    pub samples: usize,
+    /// How the samples are drawn.
+    #[serde(default)]
+    pub sampling: Sampling,
+    /// Expected mean (z-test).
+    pub mean: Option<f64>,
+    /// Expected variance (chi-square test), also used by the z-test.
+    pub variance: Option<f64>,
+    /// Distribution the samples follow (Kolmogorov-Smirnov test), e.g.
+    /// `ks: { uniform: [0, 1] }`.
+    #[serde(default, with = "serde_yaml_ng::with::singleton_map")]
+    pub ks: Option<Distribution>,
+    /// Confidence level shared by every check of the spec file: each check
+    /// runs at `1 - (1 - confidence) / m` for the file's `m` checks.
+    #[serde(default = "DistributionSpec::default_confidence")]
+    pub confidence: f64,
+}
+
+impl DistributionSpec {
+    /// Default confidence level: a correct formula fails its file once in a
+    /// thousand runs.
+    pub const DEFAULT_CONFIDENCE: f64 = 0.999;
+
+    const fn default_confidence() -> f64 {
+        Self::DEFAULT_CONFIDENCE
+    }
+}
+
+/// How a distribution test draws its samples from Gnumeric.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sampling {
+    /// One workbook with the formula filled into `samples` cells.
+    #[default]
+    Range,
+    /// One cell, recalculated `samples` times.
+    Recalc,
+}
+
+/// A random formula whose sample statistics are asserted.
+#[derive(Debug, Clone)]
+pub struct DistributionCase {
+    /// Fully qualified name (e.g., `assumptions.test_rand`).
+    pub name: String,
+    /// The Excel formula sampled.
+    pub formula: String,
+    /// Sample size, sampling mode and asserted statistics.
+    pub distribution: DistributionSpec,
+    /// Source YAML file path (for loading table data).
+    pub source_file: Option<std::path::PathBuf>,
+    /// Forge version from source file.
+    pub forge_version: String,
+    /// Known-failure reason, reported as TAP `TODO` for every statistic.
+    pub todo: Option<String>,
+    /// Oldest Gnumeric release that can run this test.
+    pub min_gnumeric: Option<GnumericVersion>,
+    /// Number of checks in the spec file, which share its confidence
+    /// (Bonferroni correction).
+    pub checks_in_file: usize,
+}
+
+impl DistributionCase {
+    /// Confidence level of each check: `1 - (1 - confidence) / m` for the
+    /// `m` checks of the spec file, so that all of them pass a correct
+    /// formula with probability at least `confidence`.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn check_confidence(&self) -> f64 {
+        let checks = self.checks_in_file.max(1) as f64;
+        1.0 - (1.0 - self.distribution.confidence) / checks
+    }
+
+    /// Returns the test names reported for the asserted statistics
+    /// (`name.mean`, `name.variance`, `name.ks`).
+    #[must_use]
+    pub fn assertion_names(&self) -> Vec<String> {
+        let spec = &self.distribution;
+        [
+            ("mean", spec.mean.is_some()),
+            ("variance", spec.variance.is_some()),
+            ("ks", spec.ks.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, asserted)| *asserted)
+        .map(|(statistic, _)| format!("{}.{statistic}", self.name))
+        .collect()
+    }
+
+    /// Checks samples against every asserted statistic, in the order of
+    /// [`Self::assertion_names`].
+    #[must_use]
+    pub fn check(&self, samples: &[f64]) -> Vec<StatCheck> {
+        let spec = &self.distribution;
+        let confidence = self.check_confidence();
+        spec.mean
+            .map(|mean| stats::mean_test(samples, mean, spec.variance, confidence))
+            .into_iter()
+            .chain(
+                spec.variance
+                    .map(|variance| stats::variance_test(samples, variance, confidence)),
+            )
+            .chain(
+                spec.ks
+                    .map(|distribution| stats::ks_test(samples, &distribution, confidence)),
+            )
+            .collect()
+    }
+}
+
+/// A sheet of an import workbook: either `scalars` as label/value rows or
+/// a table of `columns` below a header row.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WorkbookSheet {
+    /// Sheet name.
+    pub name: String,
+    /// Scalars, one row each: the name in column A, the value in column B.
+    #[serde(default)]
+    pub scalars: Vec<WorkbookScalar>,
+    /// Table columns from column A, with their names in row 1.
+    #[serde(default)]
+    pub columns: Vec<WorkbookColumn>,
+}
+
+/// A scalar row of an import workbook.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WorkbookScalar {
+    /// Label written in column A.
+    pub name: String,
+    /// Input value.
+    pub value: Option<f64>,
+    /// Excel formula with cell references (e.g., `=B2-B3`).
+    pub formula: Option<String>,
+    /// Known-failure reason for the formula, reported as TAP `TODO`.
+    #[serde(default)]
+    pub todo: Option<String>,
+}
+
+/// A table column of an import workbook.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WorkbookColumn {
+    /// Header written in row 1.
+    pub name: String,
+    /// Input values, from row 2.
+    pub values: Option<Vec<f64>>,
+    /// Excel formula written in every row, with `{{row}}` replaced by the
+    /// row number (e.g., `=A{{row}}*B{{row}}`).
+    pub formula: Option<String>,
+    /// Known-failure reason for every formula row, reported as TAP `TODO`.
+    #[serde(default)]
+    pub todo: Option<String>,
+}
+
+impl WorkbookSheet {
+    /// Returns the number of table rows: the length of the longest values
+    /// column.
+    #[must_use]
+    pub fn rows(&self) -> usize {
+        self.columns
+            .iter()
+            .filter_map(|column| column.values.as_ref().map(Vec::len))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Placeholder for the row number in a workbook column formula.
+const ROW_PLACEHOLDER: &str = "{{row}}";
+
+impl WorkbookColumn {
+    /// Returns the formula of a table row (counting from 0), if the column
+    /// is calculated.
+    #[must_use]
+    pub fn formula_at(&self, row: usize) -> Option<String> {
+        self.formula
+            .as_ref()
+            .map(|formula| formula.replace(ROW_PLACEHOLDER, &(row + 2).to_string()))
+    }
+}
+
+/// A workbook whose formulas are imported into forge, calculated by forge
+/// and compared with Gnumeric's recalculation of the original workbook.
+#[derive(Debug, Clone)]
+pub struct ImportCase {
+    /// Name of the workbook (the spec's file stem).
+    pub name: String,
+    /// Sheets written to the workbook, in order.
+    pub sheets: Vec<WorkbookSheet>,
+    /// Source YAML file path.
+    pub source_file: Option<std::path::PathBuf>,
+    /// Oldest Gnumeric release that can run this test.
+    pub min_gnumeric: Option<GnumericVersion>,
+}
+
+/// A formula cell of an import workbook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportFormula {
+    /// Test name: `sheet.name` for scalars, `sheet.column[row]` for table
+    /// cells.
+    pub name: String,
+    /// The Excel formula of the cell.
+    pub formula: String,
+    /// Sheet holding the cell.
+    pub sheet: String,
+    /// Scalar label or column header.
+    pub label: String,
+    /// Table row (counting from 0), `None` for scalars.
+    pub row: Option<usize>,
+    /// Known-failure reason, reported as TAP `TODO`.
+    pub todo: Option<String>,
+}
+
+impl ImportCase {
+    /// Returns every formula cell of the workbook, scalars first on each
+    /// sheet and table cells row by row.
+    #[must_use]
+    pub fn formulas(&self) -> Vec<ImportFormula> {
+        let mut formulas = Vec::new();
+        for sheet in &self.sheets {
+            for scalar in &sheet.scalars {
+                if let Some(formula) = &scalar.formula {
+                    formulas.push(ImportFormula {
+                        name: format!("{}.{}", sheet.name, scalar.name),
+                        formula: formula.clone(),
+                        sheet: sheet.name.clone(),
+                        label: scalar.name.clone(),
+                        row: None,
+                        todo: scalar.todo.clone(),
+                    });
+                }
+            }
+            for row in 0..sheet.rows() {
+                for column in &sheet.columns {
+                    if let Some(formula) = column.formula_at(row) {
+                        formulas.push(ImportFormula {
+                            name: format!("{}.{}[{row}]", sheet.name, column.name),
+                            formula,
+                            sheet: sheet.name.clone(),
+                            label: column.name.clone(),
+                            row: Some(row),
+                            todo: column.todo.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        formulas
+    }
+}
+
+/// A test case that should be skipped.
+#[derive(Debug, Clone)]
+pub struct SkipCase {
+    /// Fully qualified name.
+    pub name: String,
+    /// Reason for skipping.
+    pub reason: String,
+    /// Source YAML file path.
+    pub source_file: Option<std::path::PathBuf>,
+}
+
+/// Time spent in each stage of running a test.
+///
+/// Tests that share one export (batch mode, chains, tables) are each
+/// charged an equal share of it. Serialized in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Timings {
+    /// Building, serializing and writing the forge model.
+    #[serde(rename = "yaml_ms", serialize_with = "serialize_millis")]
+    pub yaml: Duration,
+    /// `forge export` to XLSX.
+    #[serde(rename = "export_ms", serialize_with = "serialize_millis")]
+    pub export: Duration,
+    /// Recalculation and CSV export with `ssconvert`.
+    #[serde(rename = "recalc_ms", serialize_with = "serialize_millis")]
+    pub recalc: Duration,
+    /// Reading the results from the CSV output.
+    #[serde(rename = "parse_ms", serialize_with = "serialize_millis")]
+    pub parse: Duration,
+}
+
+fn serialize_millis<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+impl Timings {
+    /// Returns the time spent in all stages.
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.yaml + self.export + self.recalc + self.parse
+    }
+
+    /// Returns an equal share of these timings for one of `tests` tests.
+    #[must_use]
+    pub fn share(&self, tests: usize) -> Self {
+        let tests = u32::try_from(tests.max(1)).unwrap_or(u32::MAX);
+        Self {
+            yaml: self.yaml / tests,
+            export: self.export / tests,
+            recalc: self.recalc / tests,
+            parse: self.parse / tests,
+        }
+    }
+}
+
+/// Result of running a test.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum TestResult {
+    /// Test passed - actual matches expected.
+    Pass {
+        name: String,
+        formula: String,
+        expected: f64,
+        actual: f64,
+        timings: Timings,
+    },
+    /// Test failed - mismatch or error.
+    Fail {
+        name: String,
+        formula: String,
+        expected: f64,
+        actual: Option<f64>,
+        error: Option<String>,
+        timings: Timings,
+        artifacts: Option<std::path::PathBuf>,
+        #[serde(skip_serializing_if = "ValueKind::is_number")]
+        kind: ValueKind,
+        /// Assertions of a volatile test, reported instead of `expected`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        assertion: Option<String>,
+    },
+    /// Test was skipped.
+    Skip { name: String, reason: String },
+    /// Test is a known failure (TAP `TODO`): marked `todo` in its spec or
+    /// covered by a known divergence. `passed` records an unexpected pass.
+    Todo {
+        name: String,
+        formula: String,
+        expected: f64,
+        actual: Option<f64>,
+        error: Option<String>,
+        reason: String,
+        excel: Option<f64>,
+        passed: bool,
+        timings: Timings,
+        artifacts: Option<std::path::PathBuf>,
+        #[serde(skip_serializing_if = "ValueKind::is_number")]
+        kind: ValueKind,
+        /// Assertions of a volatile test, reported instead of `expected`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        assertion: Option<String>,
+    },
+}
+
+impl TestResult {
+    /// Returns `true` if this result is a pass.
+    #[must_use]
+    pub const fn is_pass(&self) -> bool {
+        matches!(self, Self::Pass { .. })
+    }
+
+    /// Returns `true` if this result is a failure.
+    #[must_use]
+    pub const fn is_fail(&self) -> bool {
+        matches!(self, Self::Fail { .. })
+    }
+
+    /// Returns the test name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Pass { name, .. }
+            | Self::Fail { name, .. }
+            | Self::Skip { name, .. }
+            | Self::Todo { name, .. } => name,
+        }
+    }
+
+    /// Returns the stage timings (`None` for skipped tests).
+    #[must_use]
+    pub const fn timings(&self) -> Option<&Timings> {
+        match self {
+            Self::Pass { timings, .. }
+            | Self::Fail { timings, .. }
+            | Self::Todo { timings, .. } => Some(timings),
+            Self::Skip { .. } => None,
+        }
+    }
+
+    /// Records where the generated files of a failed test were kept.
+    pub fn set_artifacts(&mut self, dir: &std::path::Path) {
+        if let Self::Fail { artifacts, .. } | Self::Todo { artifacts, .. } = self {
+            *artifacts = Some(dir.to_path_buf());
+        }
+    }
+
+    /// Returns `true` if this is a known failure that passed anyway.
+    #[must_use]
+    pub const fn is_unexpected_pass(&self) -> bool {
+        matches!(self, Self::Todo { passed: true, .. })
+    }
+
+    /// Marks a pass or failure as a known failure (TAP `TODO`).
+    ///
+    /// Skips and existing `Todo` results are returned unchanged.
+    #[must_use]
+    pub fn into_todo(self, reason: String, excel: Option<f64>) -> Self {
+        match self {
+            Self::Pass {
+                name,
+                formula,
+                expected,
+                actual,
+                timings,
+            } => Self::Todo {
+                name,
+                formula,
+                expected,
+                actual: Some(actual),
+                error: None,
+                reason,
+                excel,
+                passed: true,
+                timings,
+                artifacts: None,
+                kind: ValueKind::Number,
+                assertion: None,
+            },
+            Self::Fail {
+                name,
+                formula,
+                expected,
+                actual,
+                error,
+                timings,
+                artifacts,
+                kind,
+                assertion,
+            } => Self::Todo {
+                name,
+                formula,
+                expected,
+                actual,
+                error,
+                reason,
+                excel,
+                passed: false,
+                timings,
+                artifacts,
+                kind,
+                assertion,
+            },
+            other => other,
+        }
+    }
+}
+
+/// Extracts test cases from a test spec.
+///
+/// Scans all sections for scalar values that have both a formula and
+/// an expected value defined. Scalars with `cases` are expanded into one
+/// test case per row. Tests with `skip` field are excluded.
+///
+/// # Errors
+///
+/// Returns an error if a formula template cannot be filled from a case row.
+pub fn extract_test_cases(
+    spec: &TestSpec,
+    source_file: Option<&std::path::Path>,
+) -> Result<Vec<TestCase>, SpecError> {
+    let mut cases = Vec::new();
+
+    for (section_name, section) in &spec.sections {
+        if section_name.starts_with('_') {
+            continue;
+        }
+
+        if let Section::ScalarGroup(scalars) = section {
+            for (name, scalar) in scalars {
+                // Skip tests marked with skip field
+                if scalar.skip.is_some() {
+                    continue;
+                }
+                let base_name = format!("{section_name}.{name}");
+                let mut scalar_cases = Vec::new();
+                if let Some(rows) = &scalar.cases {
+                    let Some(template) = &scalar.formula else {
+                        return Err(SpecError::MissingFormula { test: base_name });
+                    };
+                    if scalar.expected_value(&base_name)?.is_some()
+                        || scalar.expected_by_scenario.is_some()
+                        || scalar.volatile_expectation().is_some()
+                    {
+                        return Err(SpecError::ExpectedBesideCases { test: base_name });
+                    }
+                    for (i, row) in rows.iter().enumerate() {
+                        if row.skip.is_some() {
+                            continue;
+                        }
+                        let test_name = row.test_name(&base_name, i);
+                        let (expected, kind) = row.expected_value(&test_name)?;
+                        scalar_cases.push(TestCase {
+                            formula: render_template(template, &row.args, &test_name)?,
+                            name: test_name,
+                            expected,
+                            kind,
+                            source_file: source_file.map(std::path::Path::to_path_buf),
+                            forge_version: spec.forge_version.clone(),
+                            scenario: None,
+                            todo: row.todo.clone().or_else(|| scalar.todo.clone()),
+                            min_gnumeric: spec.min_gnumeric_for(scalar),
+                            date_system: DateSystem::Excel1900,
+                            volatile: None,
+                        });
+                    }
+                } else if let (Some(formula), Some(by_scenario)) =
+                    (&scalar.formula, &scalar.expected_by_scenario)
+                {
+                    for (scenario_name, &expected) in by_scenario {
+                        let test_name = format!("{base_name}@{scenario_name}");
+                        let scenario = spec.scenario(&test_name, scenario_name)?;
+                        scalar_cases.push(TestCase {
+                            name: test_name,
+                            formula: formula.clone(),
+                            expected,
+                            source_file: source_file.map(std::path::Path::to_path_buf),
+                            forge_version: spec.forge_version.clone(),
+                            kind: ValueKind::Number,
+                            scenario: Some(scenario),
+                            todo: scalar.todo.clone(),
+                            min_gnumeric: spec.min_gnumeric_for(scalar),
+                            date_system: DateSystem::Excel1900,
+                            volatile: None,
+                        });
+                    }
+                } else if let (Some(formula), Some((expected, kind))) =
+                    (&scalar.formula, scalar.expected_value(&base_name)?)
+                {
+                    scalar_cases.push(TestCase {
+                        name: base_name,
+                        formula: formula.clone(),
+                        expected,
+                        kind,
+                        source_file: source_file.map(std::path::Path::to_path_buf),
+                        forge_version: spec.forge_version.clone(),
+                        scenario: None,
+                        todo: scalar.todo.clone(),
+                        min_gnumeric: spec.min_gnumeric_for(scalar),
+                        date_system: DateSystem::Excel1900,
+                        volatile: None,
+                    });
+                } else if let (Some(formula), Some(volatile)) =
+                    (&scalar.formula, scalar.volatile_expectation())
+                {
+                    scalar_cases.push(TestCase {
+                        name: base_name,
+                        formula: formula.clone(),
+                        expected: 0.0,
+                        kind: ValueKind::Number,
+                        source_file: source_file.map(std::path::Path::to_path_buf),
+                        forge_version: spec.forge_version.clone(),
+                        scenario: None,
+                        todo: scalar.todo.clone(),
+                        min_gnumeric: spec.min_gnumeric_for(scalar),
+                        date_system: DateSystem::Excel1900,
+                        volatile: Some(volatile),
+                    });
+                }
+                for system in spec.date_systems_for(scalar) {
+                    cases.extend(scalar_cases.iter().map(|case| case.in_date_system(system)));
+                }
+            }
+        }
+    }
+
+    Ok(cases)
+}
+
+/// Fills the `{{name}}` placeholders of a formula template.
+///
+/// Numbers are written as-is, strings verbatim (so a row can pass a
+/// reference or a sub-expression), booleans as `TRUE`/`FALSE` and null as
+/// an omitted argument. Inside a string literal of the template
+/// (`"{{name}}"`) a string is escaped as literal text instead.
+///
+/// # Errors
+///
+/// Returns an error if a placeholder is unterminated, has no value in
+/// `args`, or its value is a sequence or mapping.
+pub fn render_template<S: std::hash::BuildHasher>(
+    template: &str,
+    args: &HashMap<String, serde_yaml_ng::Value, S>,
+    test_name: &str,
+) -> Result<String, SpecError> {
+    use serde_yaml_ng::Value;
+
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    // Whether the template text so far leaves a string literal open
+    let mut in_text = false;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        in_text ^= rest[..start].matches('"').count() % 2 == 1;
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| SpecError::UnterminatedPlaceholder {
+                test: test_name.to_string(),
+            })?;
+        let placeholder = after[..end].trim();
+        let value = args
+            .get(placeholder)
+            .ok_or_else(|| SpecError::MissingPlaceholder {
+                test: test_name.to_string(),
+                placeholder: placeholder.to_string(),
+            })?;
+        match value {
+            Value::Number(n) => out.push_str(&n.to_string()),
+            Value::String(s) if in_text => out.push_str(&escape_text(s)),
+            Value::String(s) => out.push_str(s),
+            Value::Bool(true) => out.push_str("TRUE"),
+            Value::Bool(false) => out.push_str("FALSE"),
+            Value::Null => {}
+            _ => {
+                return Err(SpecError::InvalidPlaceholderValue {
+                    test: test_name.to_string(),
+                    placeholder: placeholder.to_string(),
+                })
+            }
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+/// Extracts dependent scalar chains from a test spec.
+///
+/// Chains marked with `skip` or without any asserted scalar are excluded.
+///
+/// # Errors
+///
+/// Returns an error if a chain scalar sets more than one expected value.
+pub fn extract_chain_cases(
+    spec: &TestSpec,
+    source_file: Option<&std::path::Path>,
+) -> Result<Vec<ChainCase>, SpecError> {
+    let mut cases = Vec::new();
+
+    for (section_name, section) in &spec.sections {
+        if section_name.starts_with('_') {
+            continue;
+        }
+
+        if let Section::ScalarGroup(scalars) = section {
+            for (name, scalar) in scalars {
+                let Some(chain) = &scalar.chain else { continue };
+                if scalar.skip.is_some() {
+                    continue;
+                }
+                let chain_name = format!("{section_name}.{name}");
+                let scalars = chain
+                    .0
+                    .iter()
+                    .map(|(scalar_name, member)| {
+                        let expected =
+                            member.expected_value(&format!("{chain_name}.{scalar_name}"))?;
+                        Ok(ChainScalar {
+                            name: scalar_name.clone(),
+                            value: member.value,
+                            formula: member.formula.clone(),
+                            expected: expected.map(|(value, _)| value),
+                            kind: expected.map(|(_, kind)| kind).unwrap_or_default(),
+                        })
+                    })
+                    .collect::<Result<_, SpecError>>()?;
+                let chain_case = ChainCase {
+                    name: chain_name,
+                    scalars,
+                    source_file: source_file.map(std::path::Path::to_path_buf),
+                    forge_version: spec.forge_version.clone(),
+                    todo: scalar.todo.clone(),
+                    min_gnumeric: spec.min_gnumeric_for(scalar),
+                    date_system: DateSystem::Excel1900,
+                };
+                if chain_case.assertions().next().is_some() {
+                    cases.extend(
+                        spec.date_systems_for(scalar)
+                            .into_iter()
+                            .map(|system| chain_case.in_date_system(system)),
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(cases)
+}
+
+/// Extracts tables with asserted calculated columns from a test spec.
+#[must_use]
+pub fn extract_table_cases(
+    spec: &TestSpec,
+    source_file: Option<&std::path::Path>,
+) -> Vec<TableCase> {
+    let mut cases = Vec::new();
+
+    for (section_name, section) in &spec.sections {
+        if section_name.starts_with('_') {
+            continue;
+        }
+
+        if let Section::Table(columns) = section {
+            let mut asserted: Vec<ColumnAssertion> = columns
+                .iter()
+                .filter_map(|(col_name, col_data)| match col_data {
+                    TableColumn::Calculated(column) => Some(ColumnAssertion {
+                        name: col_name.clone(),
+                        formula: column.formula.clone(),
+                        expected: column.expected.clone(),
+                        todo: column.todo.clone(),
+                    }),
+                    _ => None,
+                })
+                .collect();
+
+            if !asserted.is_empty() {
+                asserted.sort_by(|a, b| a.name.cmp(&b.name));
+                cases.push(TableCase {
+                    name: section_name.clone(),
+                    columns: asserted,
+                    source_file: source_file.map(std::path::Path::to_path_buf),
+                    forge_version: spec.forge_version.clone(),
+                    min_gnumeric: spec.min_gnumeric,
+                });
+            }
+        }
+    }
+
+    cases
+}
+
+/// Extracts random formulas with asserted sample statistics from a test
+/// spec. Tests marked with `skip` are excluded; the others share the
+/// file's confidence (see [`DistributionCase::check_confidence`]).
+///
+/// # Errors
+///
+/// Returns an error if a `distribution` block has fewer than two samples,
+/// a confidence outside `(0, 1)` or no asserted statistic.
+pub fn extract_distribution_cases(
+    spec: &TestSpec,
+    source_file: Option<&std::path::Path>,
+) -> Result<Vec<DistributionCase>, SpecError> {
+    let mut cases = Vec::new();
+
+    for (section_name, section) in &spec.sections {
+        if section_name.starts_with('_') {
+            continue;
+        }
+
+        if let Section::ScalarGroup(scalars) = section {
+            for (name, scalar) in scalars {
+                let (Some(formula), Some(distribution)) = (&scalar.formula, &scalar.distribution)
+                else {
+                    continue;
+                };
+                if scalar.skip.is_some() {
+                    continue;
+                }
+                let test = format!("{section_name}.{name}");
+                let invalid = |reason: &str| SpecError::InvalidDistribution {
+                    test: test.clone(),
+                    reason: reason.to_string(),
+                };
+                if distribution.samples < 2 {
+                    return Err(invalid("`samples` must be at least 2"));
+                }
+                if !(distribution.confidence > 0.0 && distribution.confidence < 1.0) {
+                    return Err(invalid("`confidence` must be between 0 and 1"));
+                }
+                let case = DistributionCase {
+                    name: test.clone(),
+                    formula: formula.clone(),
+                    distribution: distribution.clone(),
+                    source_file: source_file.map(std::path::Path::to_path_buf),
+                    forge_version: spec.forge_version.clone(),
+                    todo: scalar.todo.clone(),
+                    min_gnumeric: spec.min_gnumeric_for(scalar),
+                    checks_in_file: 0,
+                };
+                if case.assertion_names().is_empty() {
+                    return Err(invalid("set at least one of `mean`, `variance` and `ks`"));
+                }
+                cases.push(case);
+            }
+        }
+    }
+
+    let checks_in_file = cases.iter().map(|case| case.assertion_names().len()).sum();
+    for case in &mut cases {
+        case.checks_in_file = checks_in_file;
+    }
+    Ok(cases)
+}
+
+/// Extracts the import workbook of a test spec, if it has one.
+///
+/// # Errors
+///
+/// Returns an error if a sheet mixes scalars and columns, a scalar or
+/// column sets both or neither of its value and formula, a table has no
+/// values column, or the workbook has no formula.
+pub fn extract_import_cases(
+    spec: &TestSpec,
+    source_file: Option<&std::path::Path>,
+) -> Result<Vec<ImportCase>, SpecError> {
+    if spec.workbook.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    for sheet in &spec.workbook {
+        let invalid = |reason: String| SpecError::InvalidWorkbook {
+            sheet: sheet.name.clone(),
+            reason,
+        };
+        if sheet.scalars.is_empty() == sheet.columns.is_empty() {
+            return Err(invalid("set either `scalars` or `columns`".into()));
+        }
+        for scalar in &sheet.scalars {
+            if scalar.value.is_some() == scalar.formula.is_some() {
+                return Err(invalid(format!(
+                    "scalar `{}` needs either `value` or `formula`",
+                    scalar.name
+                )));
+            }
+        }
+        for column in &sheet.columns {
+            if column.values.is_some() == column.formula.is_some() {
+                return Err(invalid(format!(
+                    "column `{}` needs either `values` or `formula`",
+                    column.name
+                )));
+            }
+        }
+        if !sheet.columns.is_empty() && sheet.rows() == 0 {
+            return Err(invalid("a table needs a non-empty `values` column".into()));
+        }
+    }
+
+    let case = ImportCase {
+        name: source_file
+            .and_then(std::path::Path::file_stem)
+            .map_or_else(
+                || "workbook".to_string(),
+                |stem| stem.to_string_lossy().to_string(),
+            ),
+        sheets: spec.workbook.clone(),
+        source_file: source_file.map(std::path::Path::to_path_buf),
+        min_gnumeric: spec.min_gnumeric,
+    };
+    if case.formulas().is_empty() {
+        return Err(SpecError::InvalidWorkbook {
+            sheet: spec.workbook[0].name.clone(),
+            reason: "the workbook has no formula".into(),
+        });
+    }
+    Ok(vec![case])
+}
+
+/// Extracts skip cases from a test spec.
+#[must_use]
+pub fn extract_skip_cases(spec: &TestSpec, source_file: Option<&std::path::Path>) -> Vec<SkipCase> {
+    let mut cases = Vec::new();
+
+    for (section_name, section) in &spec.sections {
+        if section_name.starts_with('_') {
+            continue;
+        }
+
+        if let Section::ScalarGroup(scalars) = section {
+            for (name, scalar) in scalars {
+                if let Some(reason) = &scalar.skip {
+                    cases.push(SkipCase {
+                        name: format!("{section_name}.{name}"),
+                        reason: reason.clone(),
+                        source_file: source_file.map(std::path::Path::to_path_buf),
+                    });
+                } else if let Some(rows) = &scalar.cases {
+                    let base_name = format!("{section_name}.{name}");
+                    for (i, row) in rows.iter().enumerate() {
+                        if let Some(reason) = &row.skip {
+                            cases.push(SkipCase {
+                                name: row.test_name(&base_name, i),
+                                reason: reason.clone(),
+                                source_file: source_file.map(std::path::Path::to_path_buf),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ForgeModel, ModelColumn, ModelScenarios};
+
+    #[test]
+    fn parse_spec_extracts_test_cases() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+assumptions:
+  test_abs:
+    value: null
+    formula: "=ABS(-42)"
+    expected: 42
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        assert_eq!(spec.forge_version, "1.0.0");
+
+        let cases = extract_test_cases(&spec, None).unwrap();
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].name, "assumptions.test_abs");
+    }
+
+    #[test]
+    fn parse_spec_expands_case_rows() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+assumptions:
+  test_pmt:
+    formula: "=ROUND(PMT({{rate}}/12, {{nper}}, {{pv}}), 2)"
+    cases:
+      - { name: mortgage, rate: 0.06, nper: 360, pv: 200000, expected: -1199.10 }
+      - { rate: 0, nper: 12, pv: 12000, expected: -1000 }
+      - { rate: 0.05, nper: 60, pv: 25000, expected: -471.78, skip: "flaky" }
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+
+        let mut cases = extract_test_cases(&spec, None).unwrap();
+        cases.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].name, "assumptions.test_pmt[1]");
+        assert_eq!(cases[0].formula, "=ROUND(PMT(0/12, 12, 12000), 2)");
+        assert_eq!(cases[1].name, "assumptions.test_pmt[mortgage]");
+        assert_eq!(cases[1].formula, "=ROUND(PMT(0.06/12, 360, 200000), 2)");
+        assert!((cases[1].expected + 1199.10).abs() < f64::EPSILON);
+
+        let skips = extract_skip_cases(&spec, None);
+        assert_eq!(skips.len(), 1);
+        assert_eq!(skips[0].name, "assumptions.test_pmt[2]");
+
+        let no_formula = yaml.replace(
+            "    formula: \"=ROUND(PMT({{rate}}/12, {{nper}}, {{pv}}), 2)\"\n",
+            "",
+        );
+        let spec: TestSpec = serde_yaml_ng::from_str(&no_formula).unwrap();
+        assert!(matches!(
+            extract_test_cases(&spec, None),
+            Err(SpecError::MissingFormula { .. })
+        ));
+
+        for expectation in ["expected: 1", "expected_by_scenario: { base: 1 }"] {
+            let both = yaml.replace("    cases:\n", &format!("    {expectation}\n    cases:\n"));
+            let spec: TestSpec = serde_yaml_ng::from_str(&both).unwrap();
+            assert!(
+                matches!(
+                    extract_test_cases(&spec, None),
+                    Err(SpecError::ExpectedBesideCases { .. })
+                ),
+                "{expectation}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_spec_extracts_chains_in_order() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+assumptions:
+  test_profit_chain:
+    chain:
+      profit: { formula: "=assumptions.revenue - assumptions.costs", expected: 60000 }
+      revenue: { value: 100000 }
+      costs: { value: 40000, expected: 40000 }
+  test_unasserted:
+    chain:
+      revenue: { value: 1 }
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        assert!(extract_test_cases(&spec, None).unwrap().is_empty());
+
+        let chains = extract_chain_cases(&spec, None).unwrap();
+        assert_eq!(chains.len(), 1);
+        let names: Vec<&str> = chains[0].scalars.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["profit", "revenue", "costs"]);
+
+        let asserted: Vec<String> = chains[0]
+            .assertions()
+            .map(|(scalar, _)| chains[0].assertion_name(scalar))
+            .collect();
+        assert_eq!(
+            asserted,
+            [
+                "assumptions.test_profit_chain.profit",
+                "assumptions.test_profit_chain.costs"
+            ]
+        );
+
+        // Test options of members would be ignored, so they do not parse
+        for option in ["skip: wip", "todo: wip", "expect_integer: true"] {
+            let member = format!("{{ formula: \"=1\", expected: 1, {option} }}");
+            let err = serde_yaml_ng::from_str::<Chain>(&format!("one: {member}\n")).unwrap_err();
+            assert!(err.to_string().contains("unknown field"), "{option}: {err}");
+        }
+    }
+
+    #[test]
+    fn parse_spec_extracts_calculated_columns() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+sales:
+  revenue: [100, 200, 300]
+  cost: [50, 120, 150]
+  margin:
+    formula: "=revenue - cost"
+    expected: [50, 80, 150]
+  margin_pct:
+    formula: "=margin / revenue"
+    expected: [0.5, 0.4, 0.5]
+    todo: "forge rounds the ratio"
+  ratio: "=cost / revenue"
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+
+        let tables = extract_table_cases(&spec, None);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].columns.len(), 2);
+        assert_eq!(tables[0].assertion_count(), 6);
+        assert_eq!(
+            tables[0].row_name(&tables[0].columns[0], 2),
+            "sales.margin[2]"
+        );
+        assert_eq!(tables[0].todo_for("sales.margin[1]"), None);
+        assert_eq!(
+            tables[0].todo_for("sales.margin_pct[1]"),
+            Some("forge rounds the ratio")
+        );
+
+        let mut model = ForgeModel::new("1.0.0");
+        model.add_spec_data(&spec);
+        assert_eq!(
+            model.tables["sales"]["margin"],
+            ModelColumn::Formula("=revenue - cost".to_string())
+        );
+        assert_eq!(
+            model.tables["sales"]["ratio"],
+            ModelColumn::Formula("=cost / revenue".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_spec_expands_scenario_expectations() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+scenarios:
+  base:
+    growth_rate: 0.05
+  optimistic:
+    growth_rate: 0.15
+assumptions:
+  growth_rate:
+    value: 0.05
+  test_growth:
+    formula: "=1000 * (1 + assumptions.growth_rate)"
+    expected_by_scenario:
+      base: 1050
+      optimistic: 1150
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        assert!(!spec.sections.contains_key("scenarios"));
+
+        let cases = extract_test_cases(&spec, None).unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].name, "assumptions.test_growth@base");
+        let scenario = cases[1].scenario.as_ref().unwrap();
+        assert_eq!(scenario.name, "optimistic");
+        assert!((scenario.overrides["growth_rate"] - 0.15).abs() < f64::EPSILON);
+        assert!((scenario.base["growth_rate"] - 0.05).abs() < f64::EPSILON);
+
+        let mut model = ForgeModel::new("1.0.0");
+        model.add_spec_data(&spec);
+        let Some(ModelScenarios::Overrides(scenarios)) = &model.scenarios else {
+            panic!("expected scenario overrides");
+        };
+        assert!((scenarios["base"]["growth_rate"] - 0.05).abs() < f64::EPSILON);
+
+        let unknown = yaml.replace("optimistic: 1150", "downside: 900");
+        let spec: TestSpec = serde_yaml_ng::from_str(&unknown).unwrap();
+        assert!(matches!(
+            extract_test_cases(&spec, None),
+            Err(SpecError::UnknownScenario { .. })
+        ));
+
+        let no_base = yaml.replace("  growth_rate:\n    value: 0.05\n", "");
+        let spec: TestSpec = serde_yaml_ng::from_str(&no_base).unwrap();
+        assert!(matches!(
+            extract_test_cases(&spec, None),
+            Err(SpecError::MissingScenarioBase { .. })
+        ));
+    }
+
+    #[test]
+    fn scenario_tests_get_every_input_scalar() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+scenarios:
+  growth:
+    growth_rate: 0.15
+  downside:
+    discount_rate: 0.2
+assumptions:
+  growth_rate: { value: 0.05 }
+  discount_rate: { value: 0.1 }
+  units: { value: 100 }
+  test_value:
+    formula: "=assumptions.units * (1 + assumptions.growth_rate) / (1 + assumptions.discount_rate)"
+    expected_by_scenario:
+      growth: 104.55
+      downside: 87.5
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        let cases = extract_test_cases(&spec, None).unwrap();
+        assert_eq!(cases.len(), 2);
+        for case in &cases {
+            let scenario = case.scenario.as_ref().unwrap();
+            assert_eq!(
+                scenario.base.keys().collect::<Vec<_>>(),
+                ["discount_rate", "growth_rate", "units"]
+            );
+        }
+
+        // A key of another scenario needs a base value too
+        let no_base = yaml.replace("  discount_rate: { value: 0.1 }\n", "");
+        let spec: TestSpec = serde_yaml_ng::from_str(&no_base).unwrap();
+        assert!(matches!(
+            spec.scenario("test", "growth"),
+            Err(SpecError::MissingScenarioBase { ref scalar, .. }) if scalar == "discount_rate"
+        ));
+    }
+
+    #[test]
+    fn scenario_data_columns_are_exported_as_table() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+scenarios:
+  base_case: [100, 200, 300]
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        assert!(spec.scenario("test", "base_case").is_err());
+        let mut model = ForgeModel::new("1.0.0");
+        model.add_spec_data(&spec);
+        assert_eq!(
+            model.scenarios,
+            Some(ModelScenarios::Table(BTreeMap::from([(
+                "base_case".to_string(),
+                ModelColumn::Numbers(vec![100.0, 200.0, 300.0])
+            )])))
+        );
+    }
+
+    #[test]
+    fn render_template_fills_placeholders() {
+        let args: HashMap<String, serde_yaml_ng::Value> = serde_yaml_ng::from_str(
+            "{ text: '\"abc\"', flag: true, skipped: null, ref: sales.revenue }",
+        )
+        .unwrap();
+
+        let formula = render_template(
+            "=IF({{ flag }}, LEN({{text}}), SUM({{ref}}, {{skipped}}))",
+            &args,
+            "t",
+        )
+        .unwrap();
+        assert_eq!(formula, "=IF(TRUE, LEN(\"abc\"), SUM(sales.revenue, ))");
+
+        // Inside a string literal the value is escaped as text
+        let formula = render_template("=LEN(\"<{{text}}>\") + {{flag}}", &args, "t").unwrap();
+        assert_eq!(formula, "=LEN(\"<\"\"abc\"\">\") + TRUE");
+        assert!(crate::formula::Formula::parse(&formula).is_ok());
+
+        // Array constants are left alone
+        assert_eq!(
+            render_template("=SUM({1,2,3})", &args, "t").unwrap(),
+            "=SUM({1,2,3})"
+        );
+
+        assert!(matches!(
+            render_template("=ABS({{missing}})", &args, "t"),
+            Err(SpecError::MissingPlaceholder { .. })
+        ));
+        assert!(matches!(
+            render_template("=ABS({{text", &args, "t"),
+            Err(SpecError::UnterminatedPlaceholder { .. })
+        ));
+    }
+
+    #[test]
+    fn merge_fixture_adds_tables_and_rejects_conflicts() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+_include: [lookup_grid]
+sales:
+  amount: [1, 2, 3]
+"#;
+        let mut spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        assert_eq!(spec.include, vec!["lookup_grid".to_string()]);
+
+        let fixture: Fixture = serde_yaml_ng::from_str("grid:\n  col_a: [10, 20]\n").unwrap();
+        spec.merge_fixture("lookup_grid", fixture).unwrap();
+        assert!(spec.sections.contains_key("grid"));
+
+        let clash: Fixture = serde_yaml_ng::from_str("sales:\n  amount: [9]\n").unwrap();
+        let err = spec.merge_fixture("other", clash).unwrap_err();
+        assert!(matches!(err, SpecError::FixtureConflict { ref table, .. } if table == "sales"));
+    }
+
+    #[test]
+    fn check_table_refs_rejects_unknown_tables_and_columns() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+_include: [cash_flows]
+assumptions:
+  rate: { value: 0.1 }
+  unused: { formula: "=SUM(missing.values)" }
+"#;
+        let mut spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        let fixture: Fixture =
+            serde_yaml_ng::from_str("mirr_project:\n  values: [-100, 60, 60]\n").unwrap();
+        spec.merge_fixture("cash_flows", fixture).unwrap();
+
+        let check = |formula: &str| spec.check_table_refs("t", formula, &[]);
+        assert!(check("=MIRR(mirr_project.values, assumptions.rate, 0.12)").is_ok());
+        assert!(check("=MIRR(mirr_investment.values, 0.1, 0.12)").is_err());
+        assert!(check("=SUM(mirr_project.dates)").is_err());
+        assert!(check("=SUM({{unparsed}})").is_ok());
+
+        let chain = [ChainScalar {
+            name: "principal".to_string(),
+            value: Some(1000.0),
+            formula: None,
+            expected: None,
+            kind: ValueKind::Number,
+        }];
+        assert!(spec
+            .check_table_refs("t", "=assumptions.principal * 1.1", &chain)
+            .is_ok());
+        assert!(check("=assumptions.principal * 1.1").is_err());
+
+        let err = spec.check_scalar_refs().unwrap_err();
+        assert!(
+            matches!(err, SpecError::UnknownTableRef { ref reference, .. } if reference == "missing.values")
+        );
+    }
+
+    #[test]
+    fn test_result_is_pass() {
+        let pass = TestResult::Pass {
+            name: "test".to_string(),
+            formula: "=1".to_string(),
+            expected: 1.0,
+            actual: 1.0,
+            timings: Timings::default(),
+        };
         assert!(pass.is_pass());
         assert!(!pass.is_fail());
     }
 
+    #[test]
+    fn parse_spec_carries_todo_markers() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+assumptions:
+  test_known:
+    formula: "=SUM(1)"
+    expected: 2
+    todo: "forge returns 1"
+  test_rows:
+    formula: "=ABS({{x}})"
+    todo: "all rows"
+    cases:
+      - { x: 1, expected: 1 }
+      - { name: odd, x: -2, expected: 3, todo: "this row only" }
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+
+        let cases = extract_test_cases(&spec, None).unwrap();
+        let todo = |name: &str| {
+            cases
+                .iter()
+                .find(|tc| tc.name == name)
+                .and_then(|tc| tc.todo.clone())
+        };
+        assert_eq!(
+            todo("assumptions.test_known").as_deref(),
+            Some("forge returns 1")
+        );
+        assert_eq!(
+            todo("assumptions.test_rows[0]").as_deref(),
+            Some("all rows")
+        );
+        assert_eq!(
+            todo("assumptions.test_rows[odd]").as_deref(),
+            Some("this row only")
+        );
+    }
+
+    #[test]
+    fn parse_spec_takes_the_later_min_gnumeric() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+_min_gnumeric: "1.12"
+assumptions:
+  test_old:
+    formula: "=SUM(1)"
+    expected: 1
+  test_new:
+    formula: "=SUM(2)"
+    expected: 2
+    min_gnumeric: "1.12.50"
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+
+        let cases = extract_test_cases(&spec, None).unwrap();
+        let min = |name: &str| {
+            cases
+                .iter()
+                .find(|tc| tc.name == name)
+                .and_then(|tc| tc.min_gnumeric)
+        };
+        assert_eq!(
+            min("assumptions.test_old"),
+            Some(GnumericVersion::new(1, 12, 0))
+        );
+        assert_eq!(
+            min("assumptions.test_new"),
+            Some(GnumericVersion::new(1, 12, 50))
+        );
+        assert_eq!(
+            GnumericVersion::new(1, 12, 50).to_string(),
+            "1.12.50".to_string()
+        );
+        assert!("1.x".parse::<GnumericVersion>().is_err());
+        assert!("1".parse::<GnumericVersion>().is_err());
+    }
+
+    #[test]
+    fn parse_spec_runs_tests_under_each_date_system() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+_date_systems: [1900, 1904]
+assumptions:
+  test_date:
+    formula: "=DATE(2024, 1, 1)"
+    expected_date: 2024-01-01
+  test_year:
+    formula: "=YEAR(DATE(2024, 1, 1))"
+    expected: 2024
+  test_leap_bug:
+    formula: "=DATE(1900, 2, 29)"
+    expected_date: "1900-02-29"
+    date_systems: [1900]
+  test_chain:
+    chain:
+      start: { formula: "=DATE(2024, 1, 1)" }
+      end: { formula: "=start + 1", expected_date: 2024-01-02 }
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+
+        let cases = extract_test_cases(&spec, None).unwrap();
+        let case = |name: &str| cases.iter().find(|tc| tc.name == name).unwrap();
+        assert_eq!(cases.len(), 5);
+        assert!((case("assumptions.test_date").expected - 45292.0).abs() < f64::EPSILON);
+        let date_1904 = case("assumptions.test_date [1904]");
+        assert!((date_1904.expected - 43830.0).abs() < f64::EPSILON);
+        assert_eq!(date_1904.date_system, DateSystem::Excel1904);
+        assert_eq!(date_1904.kind, ValueKind::Date(DateSystem::Excel1904));
+        assert!((case("assumptions.test_year [1904]").expected - 2024.0).abs() < f64::EPSILON);
+        assert!(!cases
+            .iter()
+            .any(|tc| tc.name == "assumptions.test_leap_bug [1904]"));
+
+        let chains = extract_chain_cases(&spec, None).unwrap();
+        assert_eq!(chains.len(), 2);
+        let chain = chains
+            .iter()
+            .find(|chain| chain.date_system == DateSystem::Excel1904)
+            .unwrap();
+        assert_eq!(chain.name, "assumptions.test_chain [1904]");
+        let (end, expected) = chain.assertions().next().unwrap();
+        assert_eq!(
+            chain.assertion_name(end),
+            "assumptions.test_chain [1904].end"
+        );
+        assert!((expected - 43831.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn volatile_expectations_check_bounds_type_and_clock() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+assumptions:
+  test_dice:
+    formula: "=RANDBETWEEN(1, 6)"
+    expect_range: [1, 6]
+    expect_integer: true
+  test_now:
+    formula: "=NOW()"
+    expect_type: number
+    expect_within_seconds: 5
+  test_today:
+    formula: "=TODAY()"
+    expect_today: true
+  test_both:
+    formula: "=RAND()"
+    expected: 0.5
+    expect_range: [0, 1]
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        assert!(matches!(
+            extract_test_cases(&spec, None),
+            Err(SpecError::ConflictingExpected { .. })
+        ));
+
+        let yaml = yaml.split("  test_both:").next().unwrap();
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        let cases = extract_test_cases(&spec, None).unwrap();
+        let volatile = |name: &str| {
+            cases
+                .iter()
+                .find(|tc| tc.name == name)
+                .and_then(|tc| tc.volatile)
+                .unwrap()
+        };
+        let clock = WallClock {
+            start: 45292.75,
+            end: 45292.75 + 1.0 / 86_400.0,
+        };
+
+        let dice = volatile("assumptions.test_dice");
+        assert_eq!(dice.describe(), "in [1, 6], integer");
+        assert!(dice.check(&CellValue::Number(6.0), clock).is_ok());
+        assert!(dice.check(&CellValue::Number(7.0), clock).is_err());
+        assert!(dice.check(&CellValue::Number(2.5), clock).is_err());
+        assert!(dice
+            .check(&CellValue::Error("#NUM!".to_string()), clock)
+            .unwrap_err()
+            .contains("#NUM!"));
+
+        let now = volatile("assumptions.test_now");
+        assert!(now.check(&CellValue::Number(45292.75), clock).is_ok());
+        assert!(now
+            .check(&CellValue::Number(45292.75 + 4.0 / 86_400.0), clock)
+            .is_ok());
+        assert!(now
+            .check(&CellValue::Number(45292.75 - 60.0 / 86_400.0), clock)
+            .unwrap_err()
+            .contains("60s"));
+        assert!(now
+            .check(&CellValue::Text("now".to_string()), clock)
+            .is_err());
+
+        let today = volatile("assumptions.test_today");
+        assert!(today.check(&CellValue::Number(45292.0), clock).is_ok());
+        assert!(today.check(&CellValue::Number(45291.0), clock).is_err());
+    }
+
+    #[test]
+    fn parse_spec_reads_distribution_tests() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+assumptions:
+  test_rand:
+    formula: "=RAND()"
+    distribution:
+      samples: 1000
+      mean: 0.5
+      variance: 0.0833333
+      ks: { uniform: [0, 1] }
+  test_dice:
+    formula: "=RANDBETWEEN(1, 6)"
+    distribution:
+      samples: 600
+      sampling: recalc
+      ks: { uniform_int: [1, 6] }
+      confidence: 0.99
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        let cases = extract_distribution_cases(&spec, None).unwrap();
+        let case = |name: &str| cases.iter().find(|case| case.name == name).unwrap();
+
+        let rand = case("assumptions.test_rand");
+        assert_eq!(rand.distribution.sampling, Sampling::Range);
+        assert!(
+            (rand.distribution.confidence - DistributionSpec::DEFAULT_CONFIDENCE).abs()
+                < f64::EPSILON
+        );
+        assert_eq!(
+            rand.assertion_names(),
+            [
+                "assumptions.test_rand.mean",
+                "assumptions.test_rand.variance",
+                "assumptions.test_rand.ks",
+            ]
+        );
+        let samples: Vec<f64> = (0..1000).map(|i| (f64::from(i) + 0.5) / 1000.0).collect();
+        assert!(rand.check(&samples).iter().all(|check| check.passed));
+        let skewed: Vec<f64> = samples.iter().map(|x| x * x).collect();
+        let checks = rand.check(&skewed);
+        assert!(!checks[0].passed, "mean of x² is 1/3");
+        assert!(!checks[2].passed, "x² is not uniform");
+
+        assert_eq!(rand.checks_in_file, 4);
+        assert!((rand.check_confidence() - (1.0 - 0.001 / 4.0)).abs() < 1e-12);
+        assert!(rand.check(&samples)[0]
+            .description
+            .contains("99.975% confidence"));
+
+        let dice = case("assumptions.test_dice");
+        assert!((dice.check_confidence() - (1.0 - 0.01 / 4.0)).abs() < 1e-12);
+        assert_eq!(dice.distribution.sampling, Sampling::Recalc);
+        assert_eq!(dice.distribution.ks, Some(Distribution::UniformInt(1, 6)));
+        assert_eq!(dice.assertion_names(), ["assumptions.test_dice.ks"]);
+
+        for (distribution, reason) in [
+            ("{ samples: 1, mean: 0.5 }", "at least 2"),
+            (
+                "{ samples: 100, mean: 0.5, confidence: 1.5 }",
+                "between 0 and 1",
+            ),
+            ("{ samples: 100 }", "at least one"),
+        ] {
+            let yaml = format!(
+                "_forge_version: \"1.0.0\"\nassumptions:\n  test_rand:\n    formula: \"=RAND()\"\n    distribution: {distribution}\n"
+            );
+            let spec: TestSpec = serde_yaml_ng::from_str(&yaml).unwrap();
+            let error = extract_distribution_cases(&spec, None).unwrap_err();
+            assert!(error.to_string().contains(reason), "{error}");
+        }
+    }
+
+    #[test]
+    fn parse_spec_reads_import_workbook() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+_workbook:
+  - name: Inputs
+    scalars:
+      - { name: revenue, value: 100000 }
+      - { name: costs, value: 40000 }
+      - { name: profit, formula: "=B2-B3" }
+  - name: sales
+    columns:
+      - { name: price, values: [10, 20] }
+      - { name: quantity, values: [3, 4] }
+      - { name: total, formula: "=A{{row}}*B{{row}}", todo: "rounding" }
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        assert!(spec.sections.is_empty());
+
+        let cases =
+            extract_import_cases(&spec, Some(std::path::Path::new("tests/import/model.yaml")))
+                .unwrap();
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].name, "model");
+        let formulas: Vec<(String, String, Option<usize>)> = cases[0]
+            .formulas()
+            .into_iter()
+            .map(|cell| (cell.name, cell.formula, cell.row))
+            .collect();
+        assert_eq!(
+            formulas,
+            [
+                ("Inputs.profit".to_string(), "=B2-B3".to_string(), None),
+                ("sales.total[0]".to_string(), "=A2*B2".to_string(), Some(0)),
+                ("sales.total[1]".to_string(), "=A3*B3".to_string(), Some(1)),
+            ]
+        );
+        let todos: Vec<Option<String>> = cases[0]
+            .formulas()
+            .into_iter()
+            .map(|cell| cell.todo)
+            .collect();
+        assert_eq!(
+            todos,
+            [
+                None,
+                Some("rounding".to_string()),
+                Some("rounding".to_string())
+            ]
+        );
+
+        for (sheet, reason) in [
+            (
+                "{ name: S, scalars: [{ name: x, value: 1 }] }",
+                "no formula",
+            ),
+            (
+                "{ name: S, scalars: [{ name: x, value: 1, formula: \"=1\" }] }",
+                "either `value` or `formula`",
+            ),
+            (
+                "{ name: S, columns: [{ name: x, formula: \"=1\" }] }",
+                "non-empty",
+            ),
+            ("{ name: S }", "either `scalars` or `columns`"),
+        ] {
+            let yaml = format!("_forge_version: \"1.0.0\"\n_workbook:\n  - {sheet}\n");
+            let spec: TestSpec = serde_yaml_ng::from_str(&yaml).unwrap();
+            let error = extract_import_cases(&spec, None).unwrap_err();
+            assert!(error.to_string().contains(reason), "{error}");
+        }
+    }
+
+    #[test]
+    fn parse_spec_converts_expected_dates_to_serials() {
+        let yaml = r#"
+_forge_version: "1.0.0"
+assumptions:
+  test_date:
+    formula: "=DATE(2024, 1, 1)"
+    expected_date: 2024-01-01
+  test_leap_bug:
+    formula: "=DATE(1900, 2, 29)"
+    expected_date: "1900-02-29"
+  test_noon:
+    formula: "=DATE(2024, 1, 1) + 0.5"
+    expected_datetime: 2024-01-01T12:00:00
+  test_rows:
+    formula: "=DATE({{y}}, 3, 1)"
+    cases:
+      - { y: 1900, expected_date: 1900-03-01 }
+      - { y: 2024, expected: 45352 }
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+
+        let cases = extract_test_cases(&spec, None).unwrap();
+        let case = |name: &str| cases.iter().find(|tc| tc.name == name).unwrap();
+        for (name, serial) in [
+            ("assumptions.test_date", 45292.0),
+            ("assumptions.test_leap_bug", 60.0),
+            ("assumptions.test_noon", 45292.5),
+            ("assumptions.test_rows[0]", 61.0),
+        ] {
+            assert!(
+                (case(name).expected - serial).abs() < f64::EPSILON,
+                "{name}"
+            );
+        }
+        assert_eq!(
+            case("assumptions.test_date").kind,
+            ValueKind::Date(DateSystem::Excel1900)
+        );
+        assert_eq!(
+            case("assumptions.test_noon").kind,
+            ValueKind::DateTime(DateSystem::Excel1900)
+        );
+        assert_eq!(case("assumptions.test_rows[1]").kind, ValueKind::Number);
+
+        assert_eq!(
+            ValueKind::Date(DateSystem::Excel1900)
+                .render(45293.0)
+                .as_deref(),
+            Some("2024-01-02")
+        );
+        assert_eq!(
+            ValueKind::Date(DateSystem::Excel1904)
+                .render(43831.0)
+                .as_deref(),
+            Some("2024-01-02")
+        );
+        assert_eq!(ValueKind::Number.render(45293.0), None);
+        assert!(
+            (ValueKind::Date(DateSystem::Excel1904).to_1900(43831.0) - 45293.0).abs()
+                < f64::EPSILON
+        );
+        assert!((ValueKind::Number.to_1900(120.0) - 120.0).abs() < f64::EPSILON);
+
+        let yaml = r#"
+_forge_version: "1.0.0"
+assumptions:
+  test_both:
+    formula: "=1"
+    expected: 1
+    expected_date: 2024-01-01
+"#;
+        let spec: TestSpec = serde_yaml_ng::from_str(yaml).unwrap();
+        assert!(matches!(
+            extract_test_cases(&spec, None),
+            Err(SpecError::ConflictingExpected { .. })
+        ));
+    }
+
+    #[test]
+    fn timings_share_and_serialize_in_millis() {
+        let timings = Timings {
+            yaml: Duration::from_millis(4),
+            export: Duration::from_millis(40),
+            recalc: Duration::from_millis(400),
+            parse: Duration::from_millis(8),
+        };
+        assert_eq!(timings.total(), Duration::from_millis(452));
+        assert_eq!(timings.share(4).export, Duration::from_millis(10));
+        assert_eq!(timings.share(0), timings);
+
+        let result = TestResult::Pass {
+            name: "test".to_string(),
+            formula: "=1".to_string(),
+            expected: 1.0,
+            actual: 1.0,
+            timings,
+        };
+        let value: serde_yaml_ng::Value =
+            serde_yaml_ng::from_str(&serde_yaml_ng::to_string(&result).unwrap()).unwrap();
+        assert_eq!(value["status"], serde_yaml_ng::Value::from("pass"));
+        assert_eq!(
+            value["timings"]["recalc_ms"],
+            serde_yaml_ng::Value::from(400.0)
+        );
+        assert_eq!(result.timings(), Some(&timings));
+    }
+
+    #[test]
+    fn into_todo_records_unexpected_passes() {
+        let pass = TestResult::Pass {
+            name: "test".to_string(),
+            formula: "=1".to_string(),
+            expected: 1.0,
+            actual: 1.0,
+            timings: Timings::default(),
+        };
+        let todo = pass.into_todo("stale".to_string(), None);
+        assert!(todo.is_unexpected_pass());
+        assert!(!todo.is_pass() && !todo.is_fail());
+
+        let fail = TestResult::Fail {
+            name: "test".to_string(),
+            formula: "=1".to_string(),
+            expected: 2.0,
+            actual: Some(1.0),
+            error: None,
+            timings: Timings::default(),
+            artifacts: None,
+            kind: ValueKind::Date(DateSystem::Excel1900),
+            assertion: None,
+        };
+        let todo = fail.into_todo("known".to_string(), None);
+        assert!(matches!(
+            todo,
+            TestResult::Todo {
+                passed: false,
+                kind: ValueKind::Date(DateSystem::Excel1900),
+                ..
+            }
+        ));
+        assert!(!todo.is_unexpected_pass());
+    }
+
     #[test]
     fn test_table_extraction() {
         let yaml = r#"
@@ -282,10 +2833,11 @@ assumptions:
             }
         }
 
-        let table_yaml = extract_table_data_yaml(&spec);
-        println!("Extracted table YAML:\n{}", table_yaml);
+        let mut model = ForgeModel::new("1.0.0");
+        model.add_spec_data(&spec);
+        println!("Extracted tables: {:?}", model.tables.keys());
         assert!(
-            table_yaml.contains("agg_data") || table_yaml.is_empty(),
+            model.tables.contains_key("agg_data") || model.tables.is_empty(),
             "Should extract agg_data table or be empty if not parsed as Table"
         );
     }